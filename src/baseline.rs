@@ -0,0 +1,277 @@
+//! Baselines: a copy of some of what's known about a process at one moment, to compare against
+//! later.  This answers questions like "what has this process opened since 10 minutes ago?".
+//!
+//! Everything in a baseline is turned into a list of [`Item`]s, each with a key that identifies it
+//! (like a file descriptor number, or an environment variable name) so that the same thing can be
+//! found again in a later reading.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+
+use procfs::process::{Limits, MMapPath};
+
+use crate::collect::{
+    changes::{fmt_target, socket_endpoints, socket_state},
+    named_limits, CGroupInfo, Mappings, OpenFile, Socket, TaskInfo,
+};
+use crate::util::limit_to_string;
+
+/// One thing in a baseline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    /// What identifies this item from one reading to the next
+    pub key: String,
+    pub value: String,
+    /// For things that can grow (like memory), their size
+    pub bytes: Option<u64>,
+}
+
+impl Item {
+    pub fn new(key: impl Into<String>, value: impl Into<String>) -> Item {
+        Item {
+            key: key.into(),
+            value: value.into(),
+            bytes: None,
+        }
+    }
+    pub fn with_bytes(mut self, bytes: u64) -> Item {
+        self.bytes = Some(bytes);
+        self
+    }
+}
+
+/// How an item differs from the baseline
+#[derive(Debug, PartialEq, Eq)]
+pub enum Diff<'a> {
+    Added(&'a Item),
+    Removed(&'a Item),
+    /// The item is still there, but its value or its size changed
+    Changed {
+        old: &'a Item,
+        new: &'a Item,
+    },
+}
+
+impl Diff<'_> {
+    /// How much bigger the item got, if it has a size (negative if it shrank)
+    pub fn growth(&self) -> Option<i64> {
+        match self {
+            Diff::Changed { old, new } => Some(new.bytes? as i64 - old.bytes? as i64),
+            _ => None,
+        }
+    }
+}
+
+/// Matches up the items in `left` and `right` by their keys: every item of `right` (in order),
+/// paired with the item of `left` that has the same key (if any), followed by the items of `left`
+/// that are missing from `right`.  Items with the same key are matched up in order
+pub fn pair<'a>(left: &'a [Item], right: &'a [Item]) -> Vec<(Option<&'a Item>, Option<&'a Item>)> {
+    let mut by_key: HashMap<&str, Vec<&Item>> = HashMap::new();
+    for item in left.iter().rev() {
+        by_key.entry(&item.key).or_default().push(item);
+    }
+    let mut pairs = Vec::new();
+    for item in right {
+        let matched = by_key.get_mut(item.key.as_str()).and_then(|items| items.pop());
+        pairs.push((matched, Some(item)));
+    }
+    for item in left {
+        if let Some(items) = by_key.get_mut(item.key.as_str()) {
+            if items.last().is_some_and(|&last| std::ptr::eq(last, item)) {
+                items.pop();
+                pairs.push((Some(item), None));
+            }
+        }
+    }
+    pairs
+}
+
+/// Compares the items in `now` with the ones in `base`, and returns only the differences: items
+/// that were added or changed (in the order of `now`), followed by the ones that were removed
+pub fn diff<'a>(base: &'a [Item], now: &'a [Item]) -> Vec<Diff<'a>> {
+    pair(base, now)
+        .into_iter()
+        .filter_map(|pair| match pair {
+            (None, Some(new)) => Some(Diff::Added(new)),
+            (Some(old), None) => Some(Diff::Removed(old)),
+            (Some(old), Some(new)) if old != new => Some(Diff::Changed { old, new }),
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn env_items(env: &HashMap<OsString, OsString>) -> Vec<Item> {
+    let mut items: Vec<Item> = env
+        .iter()
+        .map(|(k, v)| Item::new(k.to_string_lossy(), v.to_string_lossy()))
+        .collect();
+    items.sort_by(|a, b| a.key.cmp(&b.key));
+    items
+}
+
+pub fn file_items(fds: &[OpenFile]) -> Vec<Item> {
+    fds.iter()
+        .map(|fd| Item::new(format!("fd {}", fd.fd), fmt_target(&fd.target)))
+        .collect()
+}
+
+pub fn socket_items(sockets: &[Socket]) -> Vec<Item> {
+    sockets
+        .iter()
+        .map(|s| Item::new(socket_endpoints(s), socket_state(s)))
+        .collect()
+}
+
+/// Regions are keyed by where they start and what's mapped, so a region that grows (like the heap)
+/// is the same item.  Their size is the resident memory when it's known, or else the size of the
+/// region
+pub fn map_items(maps: &Mappings) -> Vec<Item> {
+    maps.regions
+        .iter()
+        .map(|map| {
+            let path = match &map.pathname {
+                MMapPath::Path(path) => path.display().to_string(),
+                p => format!("{p:?}"),
+            };
+            let size = match map.extension.map.get("Rss") {
+                Some(rss) => *rss,
+                None => map.address.1 - map.address.0,
+            };
+            Item::new(format!("0x{:012x} {path}", map.address.0), map.perms.as_str()).with_bytes(size)
+        })
+        .collect()
+}
+
+/// The files that are mapped (mostly shared libraries), once each.  A library is keyed by its name
+/// without the version, so that two versions of it are the same item, with different paths
+pub fn library_items(maps: &Mappings) -> Vec<Item> {
+    let mut items: Vec<Item> = Vec::new();
+    for map in &maps.regions {
+        if let MMapPath::Path(path) = &map.pathname {
+            let path = path.display().to_string();
+            if !items.iter().any(|item| item.value == path) {
+                items.push(Item::new(library_name(&path).to_string(), path));
+            }
+        }
+    }
+    items.sort_by(|a, b| a.key.cmp(&b.key));
+    items
+}
+
+/// The name of a library without its directory or version, like `libssl` for
+/// `/usr/lib/libssl.so.3`, or `libc` for `/lib/libc-2.31.so`.  Other files just lose their
+/// directory
+fn library_name(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let name = match name.find(".so") {
+        Some(idx) if idx > 0 => &name[..idx],
+        _ => return name,
+    };
+    // a version before the `.so` has a dot in it, unlike the end of a name like `ld-linux-x86-64`
+    match name.rsplit_once('-') {
+        Some((base, version))
+            if !base.is_empty() && version.contains('.') && version.chars().all(|c| c.is_ascii_digit() || c == '.') =>
+        {
+            base
+        }
+        _ => name,
+    }
+}
+
+pub fn mem_items(rollup: &HashMap<String, u64>) -> Vec<Item> {
+    let mut items: Vec<Item> = rollup
+        .iter()
+        .map(|(k, v)| Item::new(k.clone(), "").with_bytes(*v))
+        .collect();
+    items.sort_by(|a, b| a.key.cmp(&b.key));
+    items
+}
+
+pub fn task_items<'a>(tasks: impl IntoIterator<Item = &'a TaskInfo>) -> Vec<Item> {
+    tasks
+        .into_iter()
+        .map(|t| Item::new(format!("thread {}", t.tid), t.stat.comm.clone()))
+        .collect()
+}
+
+pub fn limit_items(limits: &Limits) -> Vec<Item> {
+    named_limits(limits)
+        .iter()
+        .map(|(name, limit, _)| {
+            Item::new(
+                *name,
+                format!(
+                    "{} / {}",
+                    limit_to_string(&limit.soft_limit),
+                    limit_to_string(&limit.hard_limit)
+                ),
+            )
+        })
+        .collect()
+}
+
+pub fn cgroup_items(groups: &[CGroupInfo]) -> Vec<Item> {
+    groups
+        .iter()
+        .map(|cg| {
+            let controllers = if cg.controllers.is_empty() {
+                "unified".to_string()
+            } else {
+                cg.controllers.join(",")
+            };
+            Item::new(format!("{}:{controllers}", cg.hierarchy), cg.pathname.clone())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, library_name, Diff, Item};
+
+    #[test]
+    fn test_diff() {
+        let base = [
+            Item::new("fd 0", "/dev/null"),
+            Item::new("fd 3", "pipe:[7]"),
+            Item::new("Rss", "").with_bytes(1000),
+            Item::new("thread 5", "worker"),
+            Item::new("thread 5", "worker"),
+        ];
+        let now = [
+            Item::new("fd 0", "/dev/null"),
+            Item::new("Rss", "").with_bytes(3000),
+            Item::new("fd 4", "/tmp/log"),
+            Item::new("thread 5", "worker"),
+        ];
+        let diffs = diff(&base, &now);
+        assert_eq!(
+            diffs,
+            [
+                Diff::Changed {
+                    old: &base[2],
+                    new: &now[1]
+                },
+                Diff::Added(&now[2]),
+                Diff::Removed(&base[1]),
+                Diff::Removed(&base[4]),
+            ]
+        );
+        assert_eq!(diffs[0].growth(), Some(2000));
+        assert_eq!(diffs[1].growth(), None);
+        assert!(diff(&now, &now).is_empty());
+    }
+
+    #[test]
+    fn test_library_name() {
+        assert_eq!(library_name("/usr/lib/x86_64-linux-gnu/libssl.so.3"), "libssl");
+        assert_eq!(library_name("/lib64/ld-linux-x86-64.so.2"), "ld-linux-x86-64");
+        assert_eq!(library_name("/usr/bin/python3.11"), "python3.11");
+        // glibc before 2.34 puts its version before the `.so`
+        assert_eq!(library_name("/lib/x86_64-linux-gnu/libc-2.31.so"), "libc");
+        assert_eq!(
+            library_name("/lib/x86_64-linux-gnu/libc-2.31.so"),
+            library_name("/lib/x86_64-linux-gnu/libc-2.35.so")
+        );
+        assert_eq!(library_name("/lib/x86_64-linux-gnu/libpthread-2.31.so"), "libpthread");
+    }
+}
@@ -0,0 +1,240 @@
+//! Copies the parts of `/proc` that procdump reads into a directory, so that they can be browsed
+//! later (possibly on another machine) with `--proc-root`.
+
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+/// Files that are read for the process being captured, relative to `/proc/<pid>`
+const PROCESS_FILES: &[&str] = &[
+    "stat",
+    "status",
+    "cmdline",
+    "environ",
+    "maps",
+    "smaps",
+    "smaps_rollup",
+    "limits",
+    "io",
+    "cgroup",
+    "mountinfo",
+    "attr/current",
+    "net/tcp",
+    "net/tcp6",
+    "net/udp",
+    "net/udp6",
+    "net/unix",
+];
+
+/// Files that are read for every other process, so that the process tree and pipes still work
+const OTHER_PROCESS_FILES: &[&str] = &["stat", "cmdline"];
+
+/// Files that are read for each thread of the process being captured, relative to `/proc/<pid>/task/<tid>`
+const TASK_FILES: &[&str] = &["stat", "status", "io", "wchan"];
+
+/// System-wide files, relative to `/proc`
+const SYSTEM_FILES: &[&str] = &["stat", "locks", "cgroups"];
+
+/// The result of a capture
+#[derive(Debug, Default)]
+pub struct Capture {
+    /// The number of files (and symlinks) that were copied
+    pub copied: usize,
+    /// Files belonging to the captured process that couldn't be read.  Files that don't exist (like
+    /// `net/tcp6` on a system without IPv6) aren't included, and neither are failures for other
+    /// processes, since most of those are expected to be unreadable.
+    pub errors: Vec<(PathBuf, io::Error)>,
+}
+
+/// Captures process `pid` from `proc_root` (usually `/proc`) into `dest`.
+///
+/// The target process is captured in full, along with its threads.  For every other process, only
+/// enough is captured to rebuild the process tree, to find the other end of pipes, and to see who
+/// shares a namespace (plus init's mounts, to compare against).  A `self`
+/// symlink pointing at the target process is created, so that `--proc-root <dest>` opens it by
+/// default.
+pub fn capture(proc_root: &Path, pid: i32, dest: &Path) -> io::Result<Capture> {
+    let mut capture = Capture::default();
+    fs::create_dir_all(dest)?;
+
+    for name in SYSTEM_FILES {
+        capture.copy_file(&proc_root.join(name), &dest.join(name), false)?;
+    }
+
+    let pid_dir = pid.to_string();
+    for entry in fs::read_dir(proc_root)? {
+        let entry = entry?;
+        let Some(other) = entry.file_name().to_str().and_then(|s| s.parse::<i32>().ok()) else {
+            continue;
+        };
+        if other == pid {
+            continue;
+        }
+        let src = entry.path();
+        let dst = dest.join(entry.file_name());
+        for name in OTHER_PROCESS_FILES {
+            capture.copy_file(&src.join(name), &dst.join(name), false)?;
+        }
+        capture.copy_links(&src.join("fd"), &dst.join("fd"), false)?;
+        // for the processes that share a namespace with the target
+        capture.copy_links(&src.join("ns"), &dst.join("ns"), false)?;
+        // the Mounts tab shows which mounts differ from init's
+        if other == 1 {
+            capture.copy_file(&src.join("mountinfo"), &dst.join("mountinfo"), false)?;
+        }
+    }
+
+    let src = proc_root.join(&pid_dir);
+    let dst = dest.join(&pid_dir);
+    // make sure the target exists, even if nothing in it can be read
+    fs::create_dir_all(&dst)?;
+    for name in PROCESS_FILES {
+        capture.copy_file(&src.join(name), &dst.join(name), true)?;
+    }
+    capture.copy_links(&src.join("fd"), &dst.join("fd"), true)?;
+    capture.copy_links(&src.join("ns"), &dst.join("ns"), true)?;
+    capture.copy_dir(&src.join("fdinfo"), &dst.join("fdinfo"))?;
+    match fs::read_dir(src.join("task")) {
+        Ok(tasks) => {
+            for task in tasks {
+                let task = task?;
+                for name in TASK_FILES {
+                    capture.copy_file(
+                        &task.path().join(name),
+                        &dst.join("task").join(task.file_name()).join(name),
+                        true,
+                    )?;
+                }
+                // reading a kernel stack needs root, so not being able to isn't worth reporting
+                capture.copy_file(
+                    &task.path().join("stack"),
+                    &dst.join("task").join(task.file_name()).join("stack"),
+                    false,
+                )?;
+            }
+        }
+        Err(e) => capture.error(src.join("task"), e),
+    }
+
+    let link = dest.join("self");
+    if fs::symlink_metadata(&link).is_ok() {
+        fs::remove_file(&link)?;
+    }
+    std::os::unix::fs::symlink(&pid_dir, &link)?;
+
+    Ok(capture)
+}
+
+impl Capture {
+    fn error(&mut self, path: PathBuf, e: io::Error) {
+        if e.kind() != ErrorKind::NotFound {
+            self.errors.push((path, e));
+        }
+    }
+
+    /// Copies a single file.  Read errors are recorded (if `report` is set) and skipped, but write
+    /// errors are returned
+    fn copy_file(&mut self, src: &Path, dst: &Path, report: bool) -> io::Result<()> {
+        // files in /proc report a size of zero, so fs::copy can't be used
+        match fs::read(src) {
+            Ok(data) => {
+                if let Some(parent) = dst.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(dst, data)?;
+                self.copied += 1;
+            }
+            Err(e) if report => self.error(src.to_owned(), e),
+            Err(..) => {}
+        }
+        Ok(())
+    }
+
+    /// Copies every file in a directory (like `fdinfo/`).  Files that can't be read are skipped,
+    /// since they're usually for fds that were closed in the meantime
+    fn copy_dir(&mut self, src: &Path, dst: &Path) -> io::Result<()> {
+        let entries = match fs::read_dir(src) {
+            Ok(entries) => entries,
+            Err(e) => {
+                self.error(src.to_owned(), e);
+                return Ok(());
+            }
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            self.copy_file(&entry.path(), &dst.join(entry.file_name()), false)?;
+        }
+        Ok(())
+    }
+
+    /// Recreates every symlink in a directory (like `fd/`), with the same targets
+    fn copy_links(&mut self, src: &Path, dst: &Path, report: bool) -> io::Result<()> {
+        let entries = match fs::read_dir(src) {
+            Ok(entries) => entries,
+            Err(e) if report => {
+                self.error(src.to_owned(), e);
+                return Ok(());
+            }
+            Err(..) => return Ok(()),
+        };
+        fs::create_dir_all(dst)?;
+        for entry in entries.filter_map(|e| e.ok()) {
+            // fds can be closed while we're looking at them
+            if let Ok(target) = fs::read_link(entry.path()) {
+                let link = dst.join(entry.file_name());
+                if fs::symlink_metadata(&link).is_ok() {
+                    fs::remove_file(&link)?;
+                }
+                std::os::unix::fs::symlink(target, link)?;
+                self.copied += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        collect::{Collector, NamespaceCollector},
+        source::{fixture, DataSource, FixtureSource},
+        util::ProcessTree,
+    };
+
+    #[test]
+    fn test_capture_fixture() {
+        let dest = std::env::temp_dir().join(format!("procdump-capture-{}", std::process::id()));
+        let source = fixture();
+        let capture = super::capture(source.root(), 101, &dest).unwrap();
+        assert!(capture.errors.is_empty(), "{:?}", capture.errors);
+
+        let captured = FixtureSource::new(&dest);
+        assert_eq!(captured.myself().unwrap().pid, 101);
+        assert_eq!(captured.system_info.boot_time_secs, 1700000000);
+        assert_eq!(captured.process(101).unwrap().cmdline().unwrap(), ["sleep", "1000"]);
+        let tree = ProcessTree::new(&captured, None).unwrap();
+        let flat: Vec<i32> = tree.flatten().into_iter().map(|(_, e)| e.pid).collect();
+        assert_eq!(flat, [1, 100, 101, 102]);
+        // namespaces are captured for every process, so the ones that are shared can be found
+        let captured = Arc::new(captured);
+        let ns = NamespaceCollector::new(captured.clone())
+            .collect(&captured.process(101).unwrap())
+            .unwrap();
+        let net = ns.namespaces.iter().find(|ns| ns.kind == "net").unwrap();
+        assert_eq!(net.inode, 4026531840);
+        assert_eq!(net.differs_from_init, Some(false));
+        assert_eq!(net.shared_with.iter().map(|m| m.pid).collect::<Vec<_>>(), [1, 100]);
+        assert_eq!(captured.process(1).unwrap().mountinfo().unwrap().0.len(), 2);
+
+        // without init, the rest of the tree hangs off a made-up root
+        std::fs::remove_dir_all(dest.join("1")).unwrap();
+        let tree = ProcessTree::new(&*captured, None).unwrap();
+        let flat: Vec<(u8, i32)> = tree.flatten().into_iter().map(|(d, e)| (d, e.pid)).collect();
+        assert_eq!(flat, [(1, 1), (2, 100), (3, 101), (3, 102), (2, 201)]);
+
+        std::fs::remove_dir_all(&dest).unwrap();
+    }
+}
@@ -0,0 +1,561 @@
+//! Command line parsing
+
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::{bail, Context};
+use procdump::{select::FollowBy, trigger::Triggers};
+
+pub const USAGE: &str = "\
+Usage: procdump [OPTIONS] [PID]
+       procdump [OPTIONS] -- <COMMAND> [ARGS...]
+       procdump --list [PID...]
+       procdump --group [PID...]
+       procdump --compare <PID1> <PID2>
+
+If PID is missing, procdump will show information about its own running process, unless one of
+the options below picks a process.
+
+Picking a process:
+    --name <PATTERN>
+                  Processes named PATTERN, or whose command line matches the regex PATTERN
+    --pidfile <FILE>
+                  The process whose PID is in FILE
+    --unit <UNIT> Processes in the cgroup of a systemd unit, like nginx.service.  If UNIT has no
+                  type, .service is assumed
+    --user <USER> Processes owned by USER (a name or a UID)
+    --cgroup <PATH>
+                  Processes in the cgroup PATH (like /system.slice/nginx.service), or in the
+                  cgroups below it
+
+    --name, --unit, --user, and --cgroup can be combined, and then a process has to match all of them.  If
+    more than one process matches, procdump asks which one to show.
+
+    --follow      When the process exits, switch to the one that takes its place (like a service
+                  restarted by its supervisor): a newer process with the same executable and
+                  command line
+    --follow-by <command|cgroup|parent>
+                  Like --follow, but recognize the new process by its command (the default), its
+                  cgroups, or its parent and name
+
+    -- <COMMAND> [ARGS...]
+                  Run COMMAND (with its output discarded) and show it from the start.  Its exit
+                  status is shown once it ends, and procdump exits with the same status.  Quitting
+                  procdump kills COMMAND if it's still running
+
+Core dumps and reports:
+    --cpu-above <PERCENT>
+                  When the CPU usage stays above PERCENT (of one core) for --cpu-for seconds
+    --cpu-for <SECONDS>
+                  How long the CPU usage has to stay high (default 10)
+    --rss-above <SIZE>
+                  When the resident memory grows above SIZE, like 500M or 2G
+    --fds-above <N>
+                  When the process has more than N open files
+    --threads-above <N>
+                  When the process has more than N threads
+    --stuck-for <SECONDS>
+                  When a thread has been in uninterruptible sleep (D state) for SECONDS
+    --on-exit     When the process exits (only for reports)
+    --dumps <N>   Stop after the triggers have fired N times (default 1)
+    --cooldown <SECONDS>
+                  Wait at least SECONDS after a trigger fires before another one can (default 60)
+    --dump-dir <DIR>
+                  Write core dumps into DIR (default: the current directory)
+    --report-dir <DIR>
+                  Write reports into DIR.  A report is a directory with the contents of every tab,
+                  the process tree, pipes, socket tables, cgroup files, and the recent CPU and IO
+                  history.  Without --dump-dir, no core dumps are written
+    --ui          Keep the interactive UI running while watching for triggers
+
+    With any of the triggers above, procdump watches the process without starting the
+    interactive UI (unless --ui is given), and writes an ELF core file (which gdb can open) or a
+    report whenever one of them is met.  It exits once everything is written, or when the process
+    exits.  Core dumps need permission to ptrace the process.
+
+Watching several processes:
+    --list        Show a list of processes, like a small top, with their CPU and memory usage,
+                  threads, open files, and IO rates.  The processes are the PIDs given, or all
+                  the ones that match --name, --unit, --user, and --cgroup (which are looked for
+                  again on every refresh).  Enter shows everything about a process, and Esc goes
+                  back
+    --group       Like --list, but also show the totals of all the processes (including their
+                  PSS), and graphs of them.  A PID stands for the process and all of its
+                  descendants
+
+Comparing two processes:
+    --compare <PID1> <PID2>
+                  Show two processes side by side (like two replicas of the same service), with
+                  the differences in their environment, libraries, memory, limits, and cgroups
+                  highlighted.  With --snapshot, print the comparison and exit
+
+Options:
+    --snapshot    Print every tab as plain text and exit, without starting the interactive UI
+    --json        Print all collected data as a JSON document and exit (see docs/json.md)
+    --proc-root <DIR>
+                  Read processes from DIR instead of /proc, for example a copy of /proc from
+                  another machine.  If PID is missing, DIR/self is used
+    --capture <DIR>
+                  Copy everything procdump needs to know about the process into DIR, and exit.
+                  The copy can be viewed later with --proc-root DIR
+    --record <FILE>
+                  While the interactive UI is running, write every sample it takes into FILE
+    --replay <FILE>
+                  Play back a file written by --record in the interactive UI.  Space pauses,
+                  '.' and ',' step one sample forward and back, and ']' and '[' jump a minute
+    --interval <SECONDS>
+                  Time between refreshes (default 1.5).  While running, + and - change it, and p
+                  pauses updates
+    --keytest     Print the key events received from the terminal, to debug key handling
+    -h, --help    Print this help and exit";
+
+#[derive(Debug, Default)]
+pub struct Options {
+    /// The process to inspect.  If `None`, procdump inspects itself
+    pub pid: Option<i32>,
+    pub keytest: bool,
+    pub snapshot: bool,
+    pub json: bool,
+    /// Read from this directory instead of `/proc`
+    pub proc_root: Option<PathBuf>,
+    /// Capture the process into this directory, instead of displaying it
+    pub capture: Option<PathBuf>,
+    /// Record every sample into this file
+    pub record: Option<PathBuf>,
+    /// Play back a recording, instead of reading a process
+    pub replay: Option<PathBuf>,
+    /// Time between refreshes
+    pub interval: Option<Duration>,
+    /// Select a process by name or command line
+    pub name: Option<String>,
+    /// Select the process in this pidfile
+    pub pidfile: Option<PathBuf>,
+    /// Select a process from this systemd unit
+    pub unit: Option<String>,
+    /// Select a process owned by this user
+    pub user: Option<String>,
+    /// Select a process in this cgroup
+    pub cgroup: Option<String>,
+    /// Run this command, and show it
+    pub command: Vec<String>,
+    /// Switch to the process that replaces this one when it exits
+    pub follow: Option<FollowBy>,
+    /// When to write core dumps
+    pub triggers: Triggers,
+    /// How many core dumps to write
+    pub dumps: Option<usize>,
+    /// The least time between two core dumps
+    pub cooldown: Option<Duration>,
+    /// Where to write core dumps
+    pub dump_dir: Option<PathBuf>,
+    /// Where to write reports
+    pub report_dir: Option<PathBuf>,
+    /// Watch for triggers with the interactive UI running
+    pub ui: bool,
+    /// Show a list of processes, instead of just one
+    pub list: bool,
+    /// With `--list`, the processes to show (the first one is also in `pid`)
+    pub pids: Vec<i32>,
+    /// Show the totals of the processes in the list.  Each PID also brings in its descendants
+    pub group: bool,
+    /// Show these two processes side by side
+    pub compare: Option<(i32, i32)>,
+    pub help: bool,
+}
+
+impl Options {
+    /// Parses the command line arguments (not including the program name)
+    pub fn parse<I>(args: I) -> anyhow::Result<Options>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut opts = Options::default();
+        let mut cpu_for = None;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--keytest" => opts.keytest = true,
+                "--snapshot" => opts.snapshot = true,
+                "--json" => opts.json = true,
+                "--proc-root" => opts.proc_root = Some(args.next().context("--proc-root needs a directory")?.into()),
+                "--capture" => opts.capture = Some(args.next().context("--capture needs a directory")?.into()),
+                "--record" => opts.record = Some(args.next().context("--record needs a file")?.into()),
+                "--replay" => opts.replay = Some(args.next().context("--replay needs a file")?.into()),
+                "--interval" => {
+                    let secs = args.next().context("--interval needs a number of seconds")?;
+                    opts.interval = Some(parse_interval(&secs)?);
+                }
+                "--name" => opts.name = Some(args.next().context("--name needs a pattern")?),
+                "--pidfile" => opts.pidfile = Some(args.next().context("--pidfile needs a file")?.into()),
+                "--unit" => opts.unit = Some(args.next().context("--unit needs a unit name")?),
+                "--user" => opts.user = Some(args.next().context("--user needs a user name or UID")?),
+                "--cgroup" => opts.cgroup = Some(args.next().context("--cgroup needs a cgroup path")?),
+                "--follow" => opts.follow = Some(opts.follow.unwrap_or_default()),
+                "--follow-by" => {
+                    let by = args.next().context("--follow-by needs command, cgroup, or parent")?;
+                    opts.follow = Some(by.parse().map_err(|e| anyhow::anyhow!("Invalid --follow-by: {e}"))?);
+                }
+                "--cpu-above" => {
+                    let percent = args.next().context("--cpu-above needs a percentage")?;
+                    opts.triggers.cpu = Some(parse_number(&percent, "percentage")?);
+                }
+                "--cpu-for" => {
+                    let secs = args.next().context("--cpu-for needs a number of seconds")?;
+                    cpu_for = Some(parse_seconds(&secs)?);
+                }
+                "--rss-above" => {
+                    let size = args.next().context("--rss-above needs a size")?;
+                    opts.triggers.rss = Some(parse_size(&size)?);
+                }
+                "--fds-above" => {
+                    let n = args.next().context("--fds-above needs a number")?;
+                    opts.triggers.fds = Some(parse_number(&n, "number")?);
+                }
+                "--threads-above" => {
+                    let n = args.next().context("--threads-above needs a number")?;
+                    opts.triggers.threads = Some(parse_number(&n, "number")?);
+                }
+                "--stuck-for" => {
+                    let secs = args.next().context("--stuck-for needs a number of seconds")?;
+                    opts.triggers.stuck_for = Some(parse_seconds(&secs)?);
+                }
+                "--dumps" => {
+                    let n = args.next().context("--dumps needs a number")?;
+                    let n = parse_number(&n, "number")?;
+                    if n == 0 {
+                        bail!("--dumps must be at least 1");
+                    }
+                    opts.dumps = Some(n);
+                }
+                "--cooldown" => {
+                    let secs = args.next().context("--cooldown needs a number of seconds")?;
+                    opts.cooldown = Some(parse_seconds(&secs)?);
+                }
+                "--on-exit" => opts.triggers.on_exit = true,
+                "--dump-dir" => opts.dump_dir = Some(args.next().context("--dump-dir needs a directory")?.into()),
+                "--report-dir" => opts.report_dir = Some(args.next().context("--report-dir needs a directory")?.into()),
+                "--ui" => opts.ui = true,
+                "--list" => opts.list = true,
+                "--group" => {
+                    opts.list = true;
+                    opts.group = true;
+                }
+                "--compare" => {
+                    let mut pid = || -> anyhow::Result<i32> {
+                        let pid = args.next().context("--compare needs two PIDs")?;
+                        pid.parse().with_context(|| format!("Invalid PID: {pid}"))
+                    };
+                    opts.compare = Some((pid()?, pid()?));
+                }
+                "--" => {
+                    opts.command = args.by_ref().collect();
+                    if opts.command.is_empty() {
+                        bail!("-- needs a command to run");
+                    }
+                }
+                "-h" | "--help" => opts.help = true,
+                a if a.starts_with('-') => bail!("Unknown option: {a}"),
+                a => {
+                    let pid = a.parse().with_context(|| format!("Invalid PID: {a}"))?;
+                    opts.pid.get_or_insert(pid);
+                    opts.pids.push(pid);
+                }
+            }
+        }
+        if opts.pids.len() > 1 && !opts.list {
+            bail!("Unexpected argument: {}", opts.pids[1]);
+        }
+
+        if let Some(cpu_for) = cpu_for {
+            if opts.triggers.cpu.is_none() {
+                bail!("--cpu-for only works with --cpu-above");
+            }
+            opts.triggers.cpu_for = cpu_for;
+        }
+
+        let searching = opts.name.is_some() || opts.unit.is_some() || opts.user.is_some() || opts.cgroup.is_some();
+        let launching = !opts.command.is_empty();
+        if [opts.pid.is_some(), opts.pidfile.is_some(), searching, launching]
+            .iter()
+            .filter(|&&b| b)
+            .count()
+            > 1
+        {
+            bail!("Only one of a PID, --pidfile, --name/--unit/--user/--cgroup, or -- <COMMAND> can be given");
+        }
+        let headless = opts.snapshot || opts.json || opts.capture.is_some();
+        if opts.record.is_some() && (headless || opts.replay.is_some()) {
+            bail!("--record only works with the interactive UI");
+        }
+        if opts.replay.is_some()
+            && (headless || opts.proc_root.is_some() || opts.pid.is_some() || opts.pidfile.is_some() || searching)
+        {
+            bail!("--replay can't be combined with picking a process, --proc-root, --snapshot, --json, or --capture");
+        }
+        if launching && (headless || opts.proc_root.is_some() || opts.replay.is_some()) {
+            bail!("-- <COMMAND> only works with the interactive UI");
+        }
+
+        let watching = !opts.triggers.is_empty();
+        if !watching
+            && (opts.dumps.is_some()
+                || opts.cooldown.is_some()
+                || opts.dump_dir.is_some()
+                || opts.report_dir.is_some()
+                || opts.ui)
+        {
+            bail!("--dumps, --cooldown, --dump-dir, --report-dir, and --ui need a trigger, like --cpu-above");
+        }
+        if watching {
+            if headless || opts.replay.is_some() || opts.proc_root.is_some() {
+                bail!("Triggers can't be combined with --snapshot, --json, --capture, --replay, or --proc-root");
+            }
+            if opts.record.is_some() && !opts.ui {
+                bail!("--record only works with the interactive UI, so it needs --ui when there are triggers");
+            }
+            if opts.pid.is_none() && opts.pidfile.is_none() && !searching && !launching {
+                bail!("Triggers need a process to watch");
+            }
+            if opts.triggers.on_exit && opts.report_dir.is_none() {
+                bail!("--on-exit needs --report-dir, since there's nothing left to dump once the process is gone");
+            }
+        }
+
+        if opts.follow.is_some()
+            && (headless || launching || opts.replay.is_some() || opts.proc_root.is_some() || (watching && !opts.ui))
+        {
+            bail!("--follow only works with the interactive UI, and not with -- <COMMAND>, --replay, or --proc-root");
+        }
+
+        if opts.list
+            && (opts.pidfile.is_some()
+                || launching
+                || headless
+                || watching
+                || opts.follow.is_some()
+                || opts.record.is_some()
+                || opts.replay.is_some()
+                || opts.compare.is_some())
+        {
+            bail!("--list and --group can only be combined with PIDs, --name, --unit, --user, --cgroup, --proc-root, and --interval");
+        }
+        if opts.list && opts.pids.is_empty() && !searching {
+            bail!("--list and --group need some PIDs, or --name, --unit, --user, or --cgroup to find the processes");
+        }
+
+        if opts.compare.is_some()
+            && (opts.pid.is_some()
+                || opts.pidfile.is_some()
+                || searching
+                || launching
+                || watching
+                || opts.follow.is_some()
+                || opts.json
+                || opts.capture.is_some()
+                || opts.record.is_some()
+                || opts.replay.is_some())
+        {
+            bail!("--compare can only be combined with --snapshot, --proc-root, and --interval");
+        }
+
+        Ok(opts)
+    }
+}
+
+/// Parses a number, naming what it was meant to be if it isn't one
+fn parse_number<T: std::str::FromStr>(n: &str, what: &str) -> anyhow::Result<T> {
+    n.parse().ok().with_context(|| format!("Invalid {what}: {n}"))
+}
+
+/// Parses a number of seconds, like `30` or `2.5`
+fn parse_seconds(secs: &str) -> anyhow::Result<Duration> {
+    secs.parse()
+        .ok()
+        .and_then(|secs: f64| Duration::try_from_secs_f64(secs).ok())
+        .with_context(|| format!("Invalid number of seconds: {secs}"))
+}
+
+/// Parses a size in bytes, with an optional K, M, G, or T suffix (powers of 1000, like the sizes
+/// procdump shows)
+fn parse_size(size: &str) -> anyhow::Result<u64> {
+    let s = size.trim_end_matches(['B', 'b']);
+    let (num, scale) = match s.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&s[..i], 1e3),
+        Some((i, 'M' | 'm')) => (&s[..i], 1e6),
+        Some((i, 'G' | 'g')) => (&s[..i], 1e9),
+        Some((i, 'T' | 't')) => (&s[..i], 1e12),
+        _ => (s, 1.0),
+    };
+    match num.parse::<f64>() {
+        Ok(n) if n >= 0.0 && n.is_finite() => Ok((n * scale) as u64),
+        _ => bail!("Invalid size: {size}"),
+    }
+}
+
+/// Parses a number of seconds (like `2` or `0.5`) between 0.1 and 60
+fn parse_interval(secs: &str) -> anyhow::Result<Duration> {
+    let interval = secs
+        .parse()
+        .ok()
+        .and_then(|secs: f64| Duration::try_from_secs_f64(secs).ok())
+        .with_context(|| format!("Invalid interval: {secs}"))?;
+    if !(crate::ui::MIN_INTERVAL..=crate::ui::MAX_INTERVAL).contains(&interval) {
+        bail!("The interval must be between 0.1 and 60 seconds, not {secs}");
+    }
+    Ok(interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Options;
+
+    fn parse(args: &[&str]) -> anyhow::Result<Options> {
+        Options::parse(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        let opts = parse(&[]).unwrap();
+        assert_eq!(opts.pid, None);
+        assert!(!opts.snapshot);
+
+        let opts = parse(&["--snapshot", "1234"]).unwrap();
+        assert_eq!(opts.pid, Some(1234));
+        assert!(opts.snapshot);
+
+        assert!(parse(&["abc"]).is_err());
+        assert!(parse(&["1", "2"]).is_err());
+        assert!(parse(&["--bogus"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let opts = parse(&["--json"]).unwrap();
+        assert!(opts.json);
+    }
+
+    #[test]
+    fn test_parse_proc_root() {
+        let opts = parse(&["--proc-root", "/tmp/capture", "42"]).unwrap();
+        assert_eq!(opts.proc_root.as_deref(), Some(std::path::Path::new("/tmp/capture")));
+        assert_eq!(opts.pid, Some(42));
+        assert!(parse(&["--capture"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_record_replay() {
+        let opts = parse(&["--record", "/tmp/rec", "42"]).unwrap();
+        assert_eq!(opts.record.as_deref(), Some(std::path::Path::new("/tmp/rec")));
+        assert!(parse(&["--record", "/tmp/rec", "--json"]).is_err());
+        assert!(parse(&["--replay", "/tmp/rec"]).is_ok());
+        assert!(parse(&["--replay", "/tmp/rec", "42"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        let opts = parse(&["--interval", "0.5"]).unwrap();
+        assert_eq!(opts.interval, Some(std::time::Duration::from_millis(500)));
+        assert!(parse(&["--interval", "0"]).is_err());
+        assert!(parse(&["--interval", "-1"]).is_err());
+        assert!(parse(&["--interval", "soon"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_selection() {
+        let opts = parse(&["--name", "nginx", "--user", "www-data"]).unwrap();
+        assert_eq!(opts.name.as_deref(), Some("nginx"));
+        assert_eq!(opts.user.as_deref(), Some("www-data"));
+        assert!(parse(&["--unit", "nginx", "42"]).is_err());
+        assert!(parse(&["--pidfile", "/run/nginx.pid", "--name", "nginx"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_command() {
+        // everything after -- is the command, even if it looks like an option
+        let opts = parse(&["--interval", "1", "--", "ls", "-l", "--json"]).unwrap();
+        assert_eq!(opts.command, ["ls", "-l", "--json"]);
+        assert!(!opts.json);
+        assert!(parse(&["--"]).is_err());
+        assert!(parse(&["42", "--", "ls"]).is_err());
+        assert!(parse(&["--snapshot", "--", "ls"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_triggers() {
+        let opts = parse(&[
+            "--cpu-above",
+            "90",
+            "--cpu-for",
+            "5",
+            "--rss-above",
+            "1.5G",
+            "--dumps",
+            "3",
+            "42",
+        ])
+        .unwrap();
+        assert_eq!(opts.triggers.cpu, Some(90.0));
+        assert_eq!(opts.triggers.cpu_for, std::time::Duration::from_secs(5));
+        assert_eq!(opts.triggers.rss, Some(1_500_000_000));
+        assert_eq!(opts.dumps, Some(3));
+        assert_eq!(
+            parse(&["--rss-above", "512k", "1"]).unwrap().triggers.rss,
+            Some(512_000)
+        );
+        assert!(parse(&["--rss-above", "lots", "1"]).is_err());
+        assert!(parse(&["--cpu-for", "5", "42"]).is_err());
+        assert!(parse(&["--dumps", "2", "42"]).is_err());
+        assert!(parse(&["--fds-above", "100"]).is_err());
+        assert!(parse(&["--stuck-for", "30", "--snapshot", "42"]).is_err());
+        assert!(parse(&["--threads-above", "-1", "42"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_reports() {
+        let opts = parse(&["--on-exit", "--report-dir", "/tmp/reports", "--ui", "--", "make"]).unwrap();
+        assert!(opts.triggers.on_exit && opts.ui);
+        assert_eq!(opts.report_dir.as_deref(), Some(std::path::Path::new("/tmp/reports")));
+        assert!(parse(&["--on-exit", "42"]).is_err());
+        assert!(parse(&["--report-dir", "/tmp/reports", "42"]).is_err());
+        assert!(parse(&["--fds-above", "10", "--record", "/tmp/rec", "42"]).is_err());
+        assert!(parse(&["--fds-above", "10", "--record", "/tmp/rec", "--ui", "42"]).is_ok());
+    }
+
+    #[test]
+    fn test_parse_follow() {
+        let opts = parse(&["--follow", "--name", "nginx"]).unwrap();
+        assert_eq!(opts.follow, Some(procdump::select::FollowBy::Command));
+        let opts = parse(&["--follow-by", "cgroup", "--follow", "42"]).unwrap();
+        assert_eq!(opts.follow, Some(procdump::select::FollowBy::Cgroup));
+        assert!(parse(&["--follow-by", "exe", "42"]).is_err());
+        assert!(parse(&["--follow", "--", "make"]).is_err());
+        assert!(parse(&["--follow", "--cpu-above", "90", "42"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_compare() {
+        let opts = parse(&["--compare", "10", "20", "--snapshot"]).unwrap();
+        assert_eq!(opts.compare, Some((10, 20)));
+        assert!(parse(&["--compare", "10"]).is_err());
+        assert!(parse(&["--compare", "10", "20", "30"]).is_err());
+        assert!(parse(&["--compare", "10", "20", "--json"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let opts = parse(&["--list", "10", "20", "30"]).unwrap();
+        assert_eq!(opts.pids, [10, 20, 30]);
+        assert!(parse(&["--list", "--user", "www-data"]).unwrap().list);
+        assert!(parse(&["--list", "10", "--snapshot"]).is_err());
+        assert!(parse(&["--list", "--", "make"]).is_err());
+        assert!(parse(&["--list"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_group() {
+        let opts = parse(&["--group", "--cgroup", "/system.slice/nginx.service"]).unwrap();
+        assert!(opts.list && opts.group);
+        assert_eq!(opts.cgroup.as_deref(), Some("/system.slice/nginx.service"));
+        assert!(parse(&["--group", "42", "--cgroup", "/"]).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs::read_to_string,
+    path::PathBuf,
+};
+
+use procfs::{process::Process, ProcResult};
+
+use super::Collector;
+use crate::source::DataSource;
+
+/// The files that procdump knows how to read for each v1 controller
+const CONTROLLER_FILES: &[(&str, &[&str])] = &[
+    ("pids", &["pids.current", "pids.max"]),
+    ("freezer", &["freezer.state"]),
+    (
+        "memory",
+        &[
+            "memory.usage_in_bytes",
+            "memory.limit_in_bytes",
+            "memory.kmem.usage_in_bytes",
+            "memory.kmem.limit_in_bytes",
+            "memory.stat",
+        ],
+    ),
+    ("net_cls", &["net_cls.classid"]),
+    ("net_prio", &["net_prio.prioidx", "net_prio.ifpriomap"]),
+    ("cpuacct", &["cpuacct.usage", "cpuacct.usage_all"]),
+];
+
+/// A control group that a process belongs to
+pub struct CGroupInfo {
+    pub hierarchy: u32,
+    pub controllers: Vec<String>,
+    pub pathname: String,
+    /// Where this v1 hierarchy is mounted, if procdump knows about it
+    pub mount_point: Option<PathBuf>,
+    /// The contents of the interesting files in the cgroup directory, keyed by filename
+    pub files: BTreeMap<&'static str, String>,
+}
+
+/// Reads the control groups of a process, along with some details about each of them
+pub struct CGroupCollector {
+    // map from controller name to mount path
+    v1_controllers: HashMap<BTreeSet<String>, PathBuf>,
+}
+
+impl CGroupCollector {
+    pub fn new(source: &dyn DataSource, proc: &Process) -> CGroupCollector {
+        let mut map = HashMap::new();
+
+        // get the list of v1 controllers on this system
+        let groups: HashSet<String> = source
+            .cgroups()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|cg| if cg.enabled { Some(cg.name) } else { None })
+            .collect();
+
+        if let Ok(mountinfo) = proc.mountinfo() {
+            for mut mi in mountinfo {
+                if mi.fs_type == "cgroup" {
+                    let super_options: HashSet<String> = HashSet::from_iter(mi.super_options.drain().map(|(k, _)| k));
+                    let controllers: BTreeSet<String> = super_options.intersection(&groups).cloned().collect();
+                    map.insert(controllers, mi.mount_point);
+                }
+            }
+        }
+
+        CGroupCollector { v1_controllers: map }
+    }
+}
+
+impl Collector for CGroupCollector {
+    type Output = ProcResult<Vec<CGroupInfo>>;
+
+    fn collect(&mut self, proc: &Process) -> Self::Output {
+        let mut cgroups = proc.cgroups()?.0;
+        cgroups.sort_by_key(|g| g.hierarchy);
+
+        Ok(cgroups
+            .into_iter()
+            .map(|cg| {
+                let groups = BTreeSet::from_iter(cg.controllers.clone());
+                let mount_point = self.v1_controllers.get(&groups).cloned();
+                let mut files = BTreeMap::new();
+                if let Some(mountpoint) = &mount_point {
+                    let root = if cg.pathname.starts_with('/') {
+                        mountpoint.join(&cg.pathname[1..])
+                    } else {
+                        mountpoint.join(&cg.pathname)
+                    };
+                    for (controller, names) in CONTROLLER_FILES {
+                        if groups.contains(*controller) {
+                            for name in *names {
+                                if let Ok(contents) = read_to_string(root.join(name)) {
+                                    files.insert(*name, contents);
+                                }
+                            }
+                        }
+                    }
+                }
+                CGroupInfo {
+                    hierarchy: cg.hierarchy,
+                    controllers: cg.controllers,
+                    pathname: cg.pathname,
+                    mount_point,
+                    files,
+                }
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,287 @@
+//! Compares two readings from a collector, to find out what happened in between: threads that
+//! started or exited, files that were opened or closed, and so on.  Anything that came and went
+//! between the two readings is missed, so the readings should be taken often.
+
+use std::fmt;
+
+use indexmap::IndexMap;
+use procfs::process::{FDTarget, ProcState};
+
+use super::{OpenFile, Socket, TaskInfo};
+use crate::util::{ProcessTree, ProcessTreeEntry};
+
+/// What a [`Change`] is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// A thread started or exited
+    Thread,
+    /// A thread went into (or came out of) uninterruptible sleep, or became a zombie
+    State,
+    /// A file descriptor was opened or closed
+    File,
+    /// A network connection was made or closed, or changed state
+    Net,
+    /// A child process started or exited
+    Child,
+    /// Something happened to the process as a whole, like an exec or a restart
+    Process,
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            ChangeKind::Thread => "thread",
+            ChangeKind::State => "state",
+            ChangeKind::File => "file",
+            ChangeKind::Net => "net",
+            ChangeKind::Child => "child",
+            ChangeKind::Process => "process",
+        })
+    }
+}
+
+/// One thing that changed between two readings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub message: String,
+}
+
+impl Change {
+    pub fn new(kind: ChangeKind, message: String) -> Change {
+        Change { kind, message }
+    }
+}
+
+/// Threads that started or exited, and threads that went into the D or Z state, or came out of D
+pub fn task_changes(old: &IndexMap<i32, TaskInfo>, new: &IndexMap<i32, TaskInfo>) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for (tid, task) in new {
+        match old.get(tid) {
+            None => changes.push(Change::new(
+                ChangeKind::Thread,
+                format!("thread {tid} ({}) started", task.stat.comm),
+            )),
+            Some(prev) if prev.stat.state != task.stat.state => {
+                if matches!(task.stat.state, 'D' | 'Z') || prev.stat.state == 'D' {
+                    changes.push(Change::new(
+                        ChangeKind::State,
+                        format!(
+                            "thread {tid} ({}) went from {} to {}",
+                            task.stat.comm,
+                            fmt_state(prev.stat.state),
+                            fmt_state(task.stat.state)
+                        ),
+                    ));
+                }
+            }
+            Some(..) => {}
+        }
+    }
+    for (tid, task) in old {
+        if !new.contains_key(tid) {
+            changes.push(Change::new(
+                ChangeKind::Thread,
+                format!("thread {tid} ({}) exited", task.stat.comm),
+            ));
+        }
+    }
+    changes
+}
+
+/// A state letter, along with its name, like "D (Waiting)"
+fn fmt_state(state: char) -> String {
+    match ProcState::from_char(state) {
+        Some(s) => format!("{state} ({s:?})"),
+        None => state.to_string(),
+    }
+}
+
+/// File descriptors that were opened or closed.  A descriptor that was closed and then reused for
+/// something else counts as both
+pub fn file_changes(old: &[OpenFile], new: &[OpenFile]) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for file in old {
+        if !new
+            .iter()
+            .any(|f| f.fd == file.fd && same_target(&f.target, &file.target))
+        {
+            changes.push(Change::new(
+                ChangeKind::File,
+                format!("closed fd {} ({})", file.fd, fmt_target(&file.target)),
+            ));
+        }
+    }
+    for file in new {
+        if !old
+            .iter()
+            .any(|f| f.fd == file.fd && same_target(&f.target, &file.target))
+        {
+            changes.push(Change::new(
+                ChangeKind::File,
+                format!("opened fd {} ({})", file.fd, fmt_target(&file.target)),
+            ));
+        }
+    }
+    changes
+}
+
+fn same_target(a: &FDTarget, b: &FDTarget) -> bool {
+    // FDTarget isn't PartialEq, but its text form tells them apart
+    fmt_target(a) == fmt_target(b)
+}
+
+/// The target of a file descriptor, written the way `/proc/<pid>/fd` shows it
+pub(crate) fn fmt_target(target: &FDTarget) -> String {
+    match target {
+        FDTarget::Path(path) => path.display().to_string(),
+        FDTarget::Socket(inode) => format!("socket:[{inode}]"),
+        FDTarget::Net(inode) => format!("net:[{inode}]"),
+        FDTarget::Pipe(inode) => format!("pipe:[{inode}]"),
+        FDTarget::AnonInode(name) => format!("anon_inode:{name}"),
+        FDTarget::MemFD(name) => format!("/memfd:{name}"),
+        FDTarget::Other(kind, inode) => format!("{kind}:[{inode}]"),
+    }
+}
+
+/// Sockets that were opened or closed, and TCP connections that changed state
+pub fn socket_changes(old: &[Socket], new: &[Socket]) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for socket in new {
+        match old.iter().find(|s| s.inode() == socket.inode()) {
+            None => changes.push(Change::new(ChangeKind::Net, format!("new {}", fmt_socket(socket)))),
+            Some(Socket::Tcp(prev)) => {
+                if let Socket::Tcp(entry) = socket {
+                    if prev.state != entry.state {
+                        changes.push(Change::new(
+                            ChangeKind::Net,
+                            format!("{} (was {:?})", fmt_socket(socket), prev.state),
+                        ));
+                    }
+                }
+            }
+            Some(..) => {}
+        }
+    }
+    for socket in old {
+        if !new.iter().any(|s| s.inode() == socket.inode()) {
+            changes.push(Change::new(ChangeKind::Net, format!("closed {}", fmt_socket(socket))));
+        }
+    }
+    changes
+}
+
+fn fmt_socket(socket: &Socket) -> String {
+    format!("{} ({})", socket_endpoints(socket), socket_state(socket))
+}
+
+/// The protocol and addresses of a socket, like "tcp 127.0.0.1:80 -> 10.0.0.1:5000"
+pub(crate) fn socket_endpoints(socket: &Socket) -> String {
+    match socket {
+        Socket::Tcp(e) => format!("tcp {} -> {}", e.local_address, e.remote_address),
+        Socket::Udp(e) => format!("udp {} -> {}", e.local_address, e.remote_address),
+        Socket::Unix(e) => match &e.path {
+            Some(path) => format!("unix socket {}", path.display()),
+            None => format!("unix socket [{}]", e.inode),
+        },
+    }
+}
+
+pub(crate) fn socket_state(socket: &Socket) -> String {
+    match socket {
+        Socket::Tcp(e) => format!("{:?}", e.state),
+        Socket::Udp(e) => format!("{:?}", e.state),
+        Socket::Unix(e) => format!("{:?}", e.state),
+    }
+}
+
+/// Children of `pid` that started or exited.  A child is told apart from a later process with the
+/// same PID by its start time
+pub fn child_changes(old: &ProcessTree, new: &ProcessTree, pid: i32) -> Vec<Change> {
+    fn children(tree: &ProcessTree, pid: i32) -> Vec<&ProcessTreeEntry> {
+        let mut children: Vec<_> = tree
+            .entries
+            .values()
+            .filter(|e| e.ppid == pid && e.pid != pid)
+            .collect();
+        children.sort_by_key(|e| e.pid);
+        children
+    }
+    let (old, new) = (children(old, pid), children(new, pid));
+    let mut changes = Vec::new();
+    for child in &new {
+        if !old.iter().any(|c| c.pid == child.pid && c.starttime == child.starttime) {
+            changes.push(Change::new(
+                ChangeKind::Child,
+                format!("child {} started: {}", child.pid, child.cmdline),
+            ));
+        }
+    }
+    for child in &old {
+        if !new.iter().any(|c| c.pid == child.pid && c.starttime == child.starttime) {
+            changes.push(Change::new(
+                ChangeKind::Child,
+                format!("child {} exited: {}", child.pid, child.cmdline),
+            ));
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use procfs::process::{FDPermissions, FDTarget};
+
+    use super::*;
+
+    fn file(fd: i32, target: FDTarget) -> OpenFile {
+        OpenFile {
+            fd,
+            mode: FDPermissions::READ,
+            target,
+            lock: None,
+            pipe_peer: None,
+            socket: None,
+            mnt_id: None,
+            mount_point: None,
+        }
+    }
+
+    fn tree(children: &[(i32, u64, &str)]) -> ProcessTree {
+        let mut entries = HashMap::new();
+        for &(pid, starttime, cmdline) in children {
+            entries.insert(
+                pid,
+                ProcessTreeEntry {
+                    pid,
+                    starttime,
+                    ppid: 100,
+                    cmdline: cmdline.to_string(),
+                    num_siblings: 0,
+                    children: Vec::new(),
+                },
+            );
+        }
+        ProcessTree { entries }
+    }
+
+    #[test]
+    fn test_changes() {
+        let old = [file(0, FDTarget::Path("/dev/null".into())), file(3, FDTarget::Pipe(77))];
+        let new = [
+            file(0, FDTarget::Path("/dev/null".into())),
+            file(3, FDTarget::Socket(88)),
+        ];
+        let messages: Vec<_> = file_changes(&old, &new).into_iter().map(|c| c.message).collect();
+        assert_eq!(messages, ["closed fd 3 (pipe:[77])", "opened fd 3 (socket:[88])"]);
+
+        // 201 exited, and its PID was reused by another child
+        let old = tree(&[(200, 10, "sleep 5"), (201, 11, "cat")]);
+        let new = tree(&[(200, 10, "sleep 5"), (201, 12, "grep x")]);
+        let messages: Vec<_> = child_changes(&old, &new, 100).into_iter().map(|c| c.message).collect();
+        assert_eq!(messages, ["child 201 started: grep x", "child 201 exited: cat"]);
+        assert!(child_changes(&new, &new, 100).is_empty());
+    }
+}
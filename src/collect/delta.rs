@@ -0,0 +1,114 @@
+use std::time::{Duration, Instant};
+
+use procfs::{
+    process::{Io, Process, Stat},
+    ProcResult,
+};
+
+/// Holds the two most recent samples of some counters, so that rates can be computed
+pub struct StatDelta<T> {
+    tps: u64,
+    old: T,
+    old_when: Instant,
+    new: T,
+    new_when: Instant,
+}
+
+impl<T: Clone> StatDelta<T> {
+    /// Starts tracking some counters from their first sample.  `tps` is the number of clock ticks
+    /// per second, which is needed to turn CPU times into percentages
+    pub fn from_sample(sample: T, when: Instant, tps: u64) -> StatDelta<T> {
+        StatDelta {
+            old: sample.clone(),
+            new: sample,
+            old_when: when,
+            new_when: when,
+            tps,
+        }
+    }
+    /// Records a new sample, forgetting the oldest one
+    pub fn push(&mut self, sample: T, when: Instant) {
+        std::mem::swap(&mut self.old, &mut self.new);
+        self.old_when = self.new_when;
+        self.new = sample;
+        self.new_when = when;
+    }
+}
+
+impl StatDelta<Io> {
+    pub fn new(proc: &Process, when: Instant) -> ProcResult<StatDelta<Io>> {
+        Ok(StatDelta::from_sample(proc.io()?, when, procfs::ticks_per_second()))
+    }
+    pub fn update(&mut self, proc: &Process, when: Instant) {
+        if let Ok(io) = proc.io() {
+            self.push(io, when);
+        }
+    }
+}
+
+impl StatDelta<Stat> {
+    pub fn new(proc: &Process, tps: u64, when: Instant) -> ProcResult<StatDelta<Stat>> {
+        Ok(StatDelta::from_sample(proc.stat()?, when, tps))
+    }
+    pub fn update(&mut self, proc: &Process, when: Instant) {
+        if let Ok(new_stat) = proc.stat() {
+            self.push(new_stat, when);
+        }
+    }
+    pub fn cpu_percentage(&self) -> f32 {
+        let d = self.duration();
+        if d < Duration::from_millis(100) {
+            return 0.0;
+        }
+        let cputime_delta =
+            ((self.new.utime - self.old.utime) + (self.new.stime - self.old.stime)) as f32 / self.tps as f32;
+        let usage = cputime_delta / (d.as_millis() as f32 / 1000.0);
+
+        usage * 100.0
+    }
+}
+
+impl<T> StatDelta<T> {
+    pub fn latest(&self) -> &T {
+        &self.new
+    }
+    pub fn previous(&self) -> &T {
+        &self.old
+    }
+    pub fn duration(&self) -> Duration {
+        self.new_when - self.old_when
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::StatDelta;
+    use crate::source::fake_stat;
+
+    fn stat(utime: u64, stime: u64) -> procfs::process::Stat {
+        fake_stat(100, "sh", utime, stime)
+    }
+
+    #[test]
+    fn test_cpu_percentage() {
+        let start = Instant::now();
+        let mut delta = StatDelta::from_sample(stat(10, 10), start, 100);
+        // no time has passed yet
+        assert_eq!(delta.cpu_percentage(), 0.0);
+
+        // 50 ticks of user time and 25 ticks of kernel time over 1.5 seconds
+        delta.push(stat(60, 35), start + Duration::from_millis(1500));
+        assert_eq!(delta.cpu_percentage(), 50.0);
+
+        // multithreaded processes can use more than 100%
+        delta.push(stat(460, 35), start + Duration::from_millis(3500));
+        assert_eq!(delta.cpu_percentage(), 200.0);
+        assert_eq!(delta.previous().utime, 60);
+
+        // samples closer together than 100ms are too noisy to be useful
+        delta.push(stat(470, 35), start + Duration::from_millis(3550));
+        assert_eq!(delta.cpu_percentage(), 0.0);
+    }
+}
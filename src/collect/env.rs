@@ -0,0 +1,17 @@
+use std::{collections::HashMap, ffi::OsString};
+
+use procfs::{process::Process, ProcResult};
+
+use super::Collector;
+
+/// Reads the environment variables of a process
+#[derive(Default)]
+pub struct EnvCollector;
+
+impl Collector for EnvCollector {
+    type Output = ProcResult<HashMap<OsString, OsString>>;
+
+    fn collect(&mut self, proc: &Process) -> Self::Output {
+        proc.environ()
+    }
+}
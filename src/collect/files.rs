@@ -0,0 +1,129 @@
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    os::unix::prelude::OsStrExt,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use procfs::{
+    process::{FDPermissions, FDTarget, Process},
+    Lock, ProcResult,
+};
+
+use super::{fd_mount_id, Collector, Socket, SocketTables};
+use crate::{
+    source::DataSource,
+    util::{self, ProcessTreeEntry},
+};
+
+/// An open file descriptor, along with everything procdump could figure out about what it points to
+pub struct OpenFile {
+    pub fd: i32,
+    pub mode: FDPermissions,
+    pub target: FDTarget,
+    /// For paths, the lock held on the file (if any)
+    pub lock: Option<Lock>,
+    /// For pipes, the process on the other end of the pipe (if it could be found)
+    pub pipe_peer: Option<ProcessTreeEntry>,
+    /// For sockets, the socket table entry (if it could be found)
+    pub socket: Option<Socket>,
+    /// The ID of the mount that the file lives on (from `fdinfo`), and where that's mounted
+    pub mnt_id: Option<i32>,
+    pub mount_point: Option<PathBuf>,
+}
+
+/// Reads the open files of a process, resolving pipes to the process on the other end and sockets
+/// to their socket table entries.
+pub struct FilesCollector {
+    source: Arc<dyn DataSource>,
+    pipe_pairs: HashMap<u64, (ProcessTreeEntry, ProcessTreeEntry)>,
+    pipes_updated: Option<Instant>,
+    /// Finding the other end of a pipe requires scanning every process on the system, so it's only
+    /// done this often
+    pub pipe_refresh_interval: Duration,
+}
+
+impl FilesCollector {
+    pub fn new(source: Arc<dyn DataSource>) -> FilesCollector {
+        FilesCollector {
+            source,
+            pipe_pairs: HashMap::new(),
+            pipes_updated: None,
+            pipe_refresh_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Collector for FilesCollector {
+    type Output = ProcResult<Vec<OpenFile>>;
+
+    fn collect(&mut self, proc: &Process) -> Self::Output {
+        let fds = proc.fd()?;
+        let locks = util::get_locks_for_pid(&*self.source, proc.pid).unwrap_or_default();
+        let sockets = SocketTables::new(proc);
+        let mounts: HashMap<i32, PathBuf> = proc
+            .mountinfo()
+            .map(|mounts| mounts.into_iter().map(|m| (m.mnt_id, m.mount_point)).collect())
+            .unwrap_or_default();
+        let stale = match self.pipes_updated {
+            Some(when) => when.elapsed() > self.pipe_refresh_interval,
+            None => true,
+        };
+        if stale {
+            self.pipe_pairs = util::get_pipe_pairs(&*self.source);
+            self.pipes_updated = Some(Instant::now());
+        }
+
+        let mut files: Vec<OpenFile> = fds
+            .filter_map(|fd| fd.ok())
+            .map(|fd| {
+                let mode = util::fd_mode(&*self.source, proc, &fd);
+                let mut file = OpenFile {
+                    fd: fd.fd,
+                    mode,
+                    target: fd.target,
+                    lock: None,
+                    pipe_peer: None,
+                    socket: None,
+                    mnt_id: fd_mount_id(proc, fd.fd),
+                    mount_point: None,
+                };
+                file.mount_point = file.mnt_id.and_then(|id| mounts.get(&id).cloned());
+                match &file.target {
+                    FDTarget::Path(path) => file.lock = find_lock(&locks, path).cloned(),
+                    FDTarget::Pipe(inode) => {
+                        if let Some((rd_side, wr_side)) = self.pipe_pairs.get(inode) {
+                            if mode.contains(FDPermissions::READ) {
+                                file.pipe_peer = Some(wr_side.clone());
+                            } else if mode.contains(FDPermissions::WRITE) {
+                                file.pipe_peer = Some(rd_side.clone());
+                            }
+                        }
+                    }
+                    FDTarget::Socket(inode) => file.socket = sockets.lookup(*inode),
+                    _ => {}
+                }
+                file
+            })
+            .collect();
+        // /proc lists them in order, but a copy of it comes in whatever order the filesystem has
+        files.sort_by_key(|file| file.fd);
+        Ok(files)
+    }
+}
+
+/// Finds the lock (if any) held on the given path
+fn find_lock<'a>(locks: &'a [Lock], path: &Path) -> Option<&'a Lock> {
+    // get the inode and device for this path to see if it is locked
+    let cstr = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::stat(cstr.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    locks.iter().find(|lock| {
+        let lock_dev = libc::makedev(lock.devmaj, lock.devmin);
+        lock.inode == stat.st_ino && stat.st_dev == lock_dev
+    })
+}
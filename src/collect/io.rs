@@ -0,0 +1,81 @@
+use std::time::Instant;
+
+use procfs::{
+    process::{Io, Process},
+    ProcResult,
+};
+
+use super::{Collector, StatDelta};
+
+/// IO rates (per second), measured between the last two samples
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IoRates {
+    pub read_bytes: f32,
+    pub write_bytes: f32,
+    pub read_ops: f32,
+    pub write_ops: f32,
+    pub disk_read_bytes: f32,
+    pub disk_write_bytes: f32,
+}
+
+impl IoRates {
+    pub fn new(delta: &StatDelta<Io>) -> IoRates {
+        let io = delta.latest();
+        let prev_io = delta.previous();
+        let dur_sec = delta.duration().as_millis() as f32 / 1000.0;
+        let rate = |new: u64, old: u64| {
+            if dur_sec > 0.0 {
+                new.saturating_sub(old) as f32 / dur_sec
+            } else {
+                0.0
+            }
+        };
+        IoRates {
+            read_bytes: rate(io.rchar, prev_io.rchar),
+            write_bytes: rate(io.wchar, prev_io.wchar),
+            read_ops: rate(io.syscr, prev_io.syscr),
+            write_ops: rate(io.syscw, prev_io.syscw),
+            disk_read_bytes: rate(io.read_bytes, prev_io.read_bytes),
+            disk_write_bytes: rate(io.write_bytes, prev_io.write_bytes),
+        }
+    }
+}
+
+/// The IO counters of a process, along with their rates of change
+#[derive(Debug, Clone, Copy)]
+pub struct IoStats {
+    pub io: Io,
+    pub rates: IoRates,
+}
+
+/// Reads the IO counters of a process
+#[derive(Default)]
+pub struct IoCollector {
+    delta: Option<StatDelta<Io>>,
+}
+
+impl IoCollector {
+    /// Like [`collect`](Collector::collect), but with the time of the sample given explicitly
+    /// (see [`DataSource::now`](crate::source::DataSource::now))
+    pub fn collect_at(&mut self, proc: &Process, when: Instant) -> ProcResult<IoStats> {
+        let delta = match &mut self.delta {
+            Some(delta) => {
+                delta.update(proc, when);
+                delta
+            }
+            None => self.delta.insert(StatDelta::<Io>::new(proc, when)?),
+        };
+        Ok(IoStats {
+            io: *delta.latest(),
+            rates: IoRates::new(delta),
+        })
+    }
+}
+
+impl Collector for IoCollector {
+    type Output = ProcResult<IoStats>;
+
+    fn collect(&mut self, proc: &Process) -> Self::Output {
+        self.collect_at(proc, Instant::now())
+    }
+}
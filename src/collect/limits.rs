@@ -0,0 +1,40 @@
+use procfs::{
+    process::{Limit, Limits, Process},
+    ProcResult,
+};
+
+use super::Collector;
+
+/// Reads the resource limits of a process
+#[derive(Default)]
+pub struct LimitsCollector;
+
+impl Collector for LimitsCollector {
+    type Output = ProcResult<Limits>;
+
+    fn collect(&mut self, proc: &Process) -> Self::Output {
+        proc.limits()
+    }
+}
+
+/// Each limit, along with its display name and its units (if any)
+pub fn named_limits(limits: &Limits) -> [(&'static str, &Limit, Option<&'static str>); 16] {
+    [
+        ("Cpu Time", &limits.max_cpu_time, Some("seconds")),
+        ("File Size", &limits.max_file_size, Some("bytes")),
+        ("Data Size", &limits.max_data_size, Some("bytes")),
+        ("Stack Size", &limits.max_stack_size, Some("bytes")),
+        ("Core File Size", &limits.max_core_file_size, Some("bytes")),
+        ("Resident Set", &limits.max_resident_set, Some("bytes")),
+        ("Processes", &limits.max_processes, None),
+        ("Open Files", &limits.max_open_files, None),
+        ("Locked Memory", &limits.max_locked_memory, Some("bytes")),
+        ("Address Space", &limits.max_address_space, None),
+        ("File Locks", &limits.max_file_locks, None),
+        ("Pending Signals", &limits.max_pending_signals, None),
+        ("Msgqueue Size", &limits.max_msgqueue_size, Some("bytes")),
+        ("Nice Priority", &limits.max_nice_priority, None),
+        ("Realtime Priority", &limits.max_realtime_priority, None),
+        ("Realtime Timeout", &limits.max_realtime_timeout, Some("μseconds")),
+    ]
+}
@@ -0,0 +1,33 @@
+use procfs::{
+    process::{MemoryMap, Process},
+    ProcResult,
+};
+
+use super::Collector;
+
+/// The memory mapped regions of a process
+pub struct Mappings {
+    pub regions: Vec<MemoryMap>,
+    /// If true, the regions were read from `/proc/<pid>/smaps`, and so each region has extra info
+    /// (like `Size` and `Rss`) in its `extension` field
+    pub detailed: bool,
+}
+
+/// Reads the memory mapped regions of a process
+#[derive(Default)]
+pub struct MapsCollector {
+    /// If true, read the (more expensive) `/proc/<pid>/smaps` instead of `/proc/<pid>/maps`
+    pub detailed: bool,
+}
+
+impl Collector for MapsCollector {
+    type Output = ProcResult<Mappings>;
+
+    fn collect(&mut self, proc: &Process) -> Self::Output {
+        let maps = if self.detailed { proc.smaps()? } else { proc.maps()? };
+        Ok(Mappings {
+            regions: maps.0,
+            detailed: self.detailed,
+        })
+    }
+}
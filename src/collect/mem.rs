@@ -0,0 +1,24 @@
+use std::collections::HashMap;
+
+use procfs::{process::Process, ProcResult};
+
+use super::Collector;
+
+/// Reads the memory usage of a process, summed over all of its mapped regions (from
+/// `/proc/<pid>/smaps_rollup`).  Sizes are in bytes.
+#[derive(Default)]
+pub struct MemCollector;
+
+impl Collector for MemCollector {
+    type Output = ProcResult<HashMap<String, u64>>;
+
+    fn collect(&mut self, proc: &Process) -> Self::Output {
+        let mut rollup = proc.smaps_rollup()?;
+        Ok(rollup
+            .memory_map_rollup
+            .0
+            .pop()
+            .map(|map| map.extension.map)
+            .unwrap_or_default())
+    }
+}
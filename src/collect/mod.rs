@@ -0,0 +1,46 @@
+//! Collectors read information about a process from procfs, and return plain data structs.
+//!
+//! Collectors know nothing about how their data will be displayed.  Some collectors keep a little
+//! bit of state between calls (for example, to compute rates), so they should be kept around and
+//! called repeatedly for the same process.
+
+use procfs::process::Process;
+
+pub mod cgroup;
+pub mod changes;
+pub mod delta;
+pub mod env;
+pub mod files;
+pub mod io;
+pub mod limits;
+pub mod maps;
+pub mod mem;
+pub mod mounts;
+pub mod net;
+pub mod ns;
+pub mod security;
+pub mod signals;
+pub mod task;
+
+pub use cgroup::*;
+pub use changes::*;
+pub use delta::*;
+pub use env::*;
+pub use files::*;
+pub use io::*;
+pub use limits::*;
+pub use maps::*;
+pub use mem::*;
+pub use mounts::*;
+pub use net::*;
+pub use ns::*;
+pub use security::*;
+pub use signals::*;
+pub use task::*;
+
+pub trait Collector {
+    type Output;
+
+    /// Reads the latest data for the given process
+    fn collect(&mut self, proc: &Process) -> Self::Output;
+}
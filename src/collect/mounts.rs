@@ -0,0 +1,160 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Read,
+    sync::Arc,
+};
+
+use procfs::{
+    process::{MountInfo, MountOptFields, Process},
+    ProcResult,
+};
+
+use super::{Collector, OpenFile};
+use crate::source::DataSource;
+
+/// Reads the ID of the mount that an open file lives on, from the `mnt_id` line of
+/// `/proc/<pid>/fdinfo/<fd>` (since Linux 3.15)
+pub fn fd_mount_id(proc: &Process, fd: i32) -> Option<i32> {
+    let mut fdinfo = String::new();
+    proc.open_relative(&format!("fdinfo/{fd}"))
+        .ok()?
+        .read_to_string(&mut fdinfo)
+        .ok()?;
+    fdinfo
+        .lines()
+        .find_map(|line| line.strip_prefix("mnt_id:"))
+        .and_then(|id| id.trim().parse().ok())
+}
+
+/// Formats the mount options like they're written in `/etc/fstab`, with `rw` or `ro` first
+pub fn mount_options(mount: &MountInfo) -> String {
+    let mut options: Vec<String> = mount
+        .mount_options
+        .iter()
+        .map(|(k, v)| match v {
+            Some(v) => format!("{k}={v}"),
+            None => k.clone(),
+        })
+        .collect();
+    options.sort_by_key(|opt| (opt != "rw" && opt != "ro", opt.clone()));
+    options.join(",")
+}
+
+/// Describes how mount events propagate to and from this mount, like `shared:1 master:2`
+pub fn mount_propagation(mount: &MountInfo) -> String {
+    if mount.opt_fields.is_empty() {
+        return "private".to_owned();
+    }
+    mount
+        .opt_fields
+        .iter()
+        .map(|field| match field {
+            MountOptFields::Shared(id) => format!("shared:{id}"),
+            MountOptFields::Master(id) => format!("master:{id}"),
+            MountOptFields::PropagateFrom(id) => format!("propagate_from:{id}"),
+            MountOptFields::Unbindable => "unbindable".to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// What a mount is made of, without its ID (which is only unique within a mount namespace)
+fn mount_key(mount: &MountInfo) -> (String, String, String, Option<String>) {
+    (
+        mount.mount_point.display().to_string(),
+        mount.root.clone(),
+        mount.fs_type.clone(),
+        mount.mount_source.clone(),
+    )
+}
+
+/// A mount in the mount table of a process
+pub struct MountEntry {
+    pub info: MountInfo,
+    /// Whether PID 1 doesn't see the same filesystem mounted at the same place, or `None` if
+    /// PID 1's mount table couldn't be read
+    pub differs_from_init: Option<bool>,
+    /// How many of the process's open files live on this mount
+    pub open_files: usize,
+}
+
+/// Reads the mount table of a process, and compares it with PID 1's
+pub struct MountsCollector {
+    source: Arc<dyn DataSource>,
+    /// How many open files are on each mount, by mount ID.  Finding that out means reading the
+    /// `fdinfo` of every fd, which [`FilesCollector`](super::FilesCollector) already does, so the
+    /// counts come from there (see [`set_open_files`](MountsCollector::set_open_files))
+    open_files: HashMap<i32, usize>,
+}
+
+impl MountsCollector {
+    pub fn new(source: Arc<dyn DataSource>) -> MountsCollector {
+        MountsCollector {
+            source,
+            open_files: HashMap::new(),
+        }
+    }
+    /// Counts the open files on each mount, for the next [`collect`](Collector::collect)
+    pub fn set_open_files(&mut self, files: &[OpenFile]) {
+        self.open_files.clear();
+        for id in files.iter().filter_map(|file| file.mnt_id) {
+            *self.open_files.entry(id).or_default() += 1;
+        }
+    }
+    /// How many of the open files are on mount `id`
+    pub fn open_files(&self, id: i32) -> usize {
+        self.open_files.get(&id).copied().unwrap_or(0)
+    }
+}
+
+impl Collector for MountsCollector {
+    type Output = ProcResult<Vec<MountEntry>>;
+
+    fn collect(&mut self, proc: &Process) -> Self::Output {
+        let mounts = proc.mountinfo()?;
+        let init: Option<HashSet<_>> = self
+            .source
+            .process(1)
+            .and_then(|init| init.mountinfo())
+            .ok()
+            .map(|mounts| mounts.0.iter().map(mount_key).collect());
+        Ok(mounts
+            .into_iter()
+            .map(|info| MountEntry {
+                differs_from_init: init.as_ref().map(|init| !init.contains(&mount_key(&info))),
+                open_files: self.open_files(info.mnt_id),
+                info,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use procfs::process::MountInfo;
+
+    #[test]
+    fn test_mount_formatting() {
+        let mount = MountInfo::from_line(
+            "36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 shared:7 - ext3 /dev/root rw,errors=continue",
+        )
+        .unwrap();
+        assert_eq!(super::mount_options(&mount), "rw,noatime");
+        assert_eq!(super::mount_propagation(&mount), "master:1 shared:7");
+
+        let mount = MountInfo::from_line("40 36 0:5 / /dev/shm ro,nosuid,nodev - tmpfs tmpfs rw").unwrap();
+        assert_eq!(super::mount_options(&mount), "ro,nodev,nosuid");
+        assert_eq!(super::mount_propagation(&mount), "private");
+    }
+
+    #[test]
+    fn test_fd_mount_id() {
+        let me = procfs::process::Process::myself().unwrap();
+        let file = std::fs::File::open("/proc/self/status").unwrap();
+        let fd = std::os::fd::AsRawFd::as_raw_fd(&file);
+        let mounts = me.mountinfo().unwrap();
+        let id = super::fd_mount_id(&me, fd).unwrap();
+        let mount = mounts.0.iter().find(|m| m.mnt_id == id).unwrap();
+        assert_eq!(mount.fs_type, "proc");
+    }
+}
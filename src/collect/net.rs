@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use procfs::{
+    net::{TcpNetEntry, UdpNetEntry, UnixNetEntry},
+    process::{FDTarget, Process},
+    ProcResult,
+};
+
+use super::Collector;
+use crate::util;
+
+/// A socket, resolved from its inode
+#[derive(Debug, Clone)]
+pub enum Socket {
+    Tcp(TcpNetEntry),
+    Udp(UdpNetEntry),
+    Unix(UnixNetEntry),
+}
+
+impl Socket {
+    pub fn inode(&self) -> u64 {
+        match self {
+            Socket::Tcp(e) => e.inode,
+            Socket::Udp(e) => e.inode,
+            Socket::Unix(e) => e.inode,
+        }
+    }
+}
+
+/// The tcp, udp, and unix socket tables from the network namespace of a process, keyed by inode
+#[derive(Default)]
+pub struct SocketTables {
+    pub tcp: HashMap<u64, TcpNetEntry>,
+    pub udp: HashMap<u64, UdpNetEntry>,
+    pub unix: HashMap<u64, UnixNetEntry>,
+}
+
+impl SocketTables {
+    pub fn new(proc: &Process) -> SocketTables {
+        SocketTables {
+            tcp: util::get_tcp_table(proc),
+            udp: util::get_udp_table(proc),
+            unix: util::get_unix_table(proc),
+        }
+    }
+
+    /// Looks up a socket inode in all of the tables
+    pub fn lookup(&self, inode: u64) -> Option<Socket> {
+        if let Some(entry) = self.tcp.get(&inode) {
+            Some(Socket::Tcp(entry.clone()))
+        } else if let Some(entry) = self.udp.get(&inode) {
+            Some(Socket::Udp(entry.clone()))
+        } else {
+            self.unix.get(&inode).map(|entry| Socket::Unix(entry.clone()))
+        }
+    }
+}
+
+/// Reads all of the network sockets that a process has open
+#[derive(Default)]
+pub struct NetCollector;
+
+impl Collector for NetCollector {
+    type Output = ProcResult<Vec<Socket>>;
+
+    fn collect(&mut self, proc: &Process) -> Self::Output {
+        let fds = proc.fd()?;
+        let tables = SocketTables::new(proc);
+        Ok(fds
+            .filter_map(|fd| fd.ok())
+            .filter_map(|fd| match fd.target {
+                FDTarget::Socket(inode) => tables.lookup(inode),
+                _ => None,
+            })
+            .collect())
+    }
+}
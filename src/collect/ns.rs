@@ -0,0 +1,151 @@
+use std::{collections::HashMap, fs, sync::Arc};
+
+use procfs::{process::Process, ProcResult};
+
+use super::Collector;
+use crate::source::DataSource;
+
+/// The kinds of namespace that are shown, in the order they're shown in.  `pid_for_children` and
+/// `time_for_children` are left out, since they're almost always the same as `pid` and `time`
+pub const NAMESPACE_KINDS: &[&str] = &["mnt", "pid", "net", "uts", "ipc", "user", "cgroup", "time"];
+
+/// Another process in the same namespace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamespaceMember {
+    pub pid: i32,
+    pub comm: String,
+}
+
+/// One of the namespaces that a process is in
+pub struct NamespaceInfo {
+    /// Like `net` or `mnt`
+    pub kind: &'static str,
+    /// The inode number of the namespace, which is what tells namespaces apart
+    pub inode: u64,
+    /// Whether PID 1 is in another namespace of this kind, or `None` if PID 1 couldn't be read
+    pub differs_from_init: Option<bool>,
+    /// Every other process in this namespace, by PID
+    pub shared_with: Vec<NamespaceMember>,
+}
+
+/// The namespaces of a process, along with its PIDs as seen from inside each nested PID namespace
+pub struct ProcessNamespaces {
+    pub namespaces: Vec<NamespaceInfo>,
+    /// The `NSpid` line from `status`: the PID in each PID namespace, outermost first
+    pub nspid: Option<Vec<i32>>,
+    /// The `NStgid` line from `status`: the thread group ID in each PID namespace, outermost first
+    pub nstgid: Option<Vec<i32>>,
+}
+
+/// Reads the namespaces of a process, and finds the other processes that share them.  Like the
+/// process tree, this scans every process on the system.
+pub struct NamespaceCollector {
+    source: Arc<dyn DataSource>,
+}
+
+impl NamespaceCollector {
+    pub fn new(source: Arc<dyn DataSource>) -> NamespaceCollector {
+        NamespaceCollector { source }
+    }
+}
+
+/// The inode of each namespace of a process, keyed by kind.  The links in `ns/` point at names like
+/// `net:[4026531840]`, which are parsed rather than followed, so that this also works in a capture
+/// (where they point nowhere)
+fn inodes(source: &dyn DataSource, proc: &Process) -> ProcResult<HashMap<&'static str, u64>> {
+    let dir = source.root().join(proc.pid.to_string()).join("ns");
+    let mut inodes = HashMap::new();
+    // older kernels don't have every kind
+    for entry in fs::read_dir(&dir)?.filter_map(|e| e.ok()) {
+        let Some(kind) = NAMESPACE_KINDS.iter().find(|kind| entry.file_name() == **kind) else {
+            continue;
+        };
+        let target = fs::read_link(entry.path())?;
+        let inode = target
+            .to_str()
+            .and_then(|t| t.strip_prefix(kind)?.strip_prefix(":[")?.strip_suffix(']'))
+            .and_then(|inode| inode.parse().ok());
+        if let Some(inode) = inode {
+            inodes.insert(*kind, inode);
+        }
+    }
+    Ok(inodes)
+}
+
+impl Collector for NamespaceCollector {
+    type Output = ProcResult<ProcessNamespaces>;
+
+    fn collect(&mut self, proc: &Process) -> Self::Output {
+        let source = &*self.source;
+        let own = inodes(source, proc)?;
+        let init = source.process(1).and_then(|p| inodes(source, &p)).ok();
+
+        // the same scan as `ProcessTree::new`, keeping track of which processes are in each namespace
+        let mut members: HashMap<(&str, u64), Vec<NamespaceMember>> = HashMap::new();
+        for other in self.source.all_processes().unwrap_or_default() {
+            if other.pid == proc.pid {
+                continue;
+            }
+            let Ok(theirs) = inodes(source, &other) else {
+                continue;
+            };
+            let comm = other.stat().map(|s| s.comm).unwrap_or_default();
+            for (kind, inode) in theirs {
+                if own.get(kind) == Some(&inode) {
+                    members.entry((kind, inode)).or_default().push(NamespaceMember {
+                        pid: other.pid,
+                        comm: comm.clone(),
+                    });
+                }
+            }
+        }
+
+        let namespaces = NAMESPACE_KINDS
+            .iter()
+            .filter_map(|kind| {
+                let inode = *own.get(kind)?;
+                let mut shared_with = members.remove(&(*kind, inode)).unwrap_or_default();
+                shared_with.sort_by_key(|m| m.pid);
+                Some(NamespaceInfo {
+                    kind,
+                    inode,
+                    differs_from_init: init.as_ref().map(|init| init.get(kind) != Some(&inode)),
+                    shared_with,
+                })
+            })
+            .collect();
+
+        let status = proc.status().ok();
+        Ok(ProcessNamespaces {
+            namespaces,
+            nspid: status.as_ref().and_then(|s| s.nspid.clone()),
+            nstgid: status.and_then(|s| s.nstgid),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use procfs::process::Process;
+
+    use super::{NamespaceCollector, NAMESPACE_KINDS};
+    use crate::{collect::Collector, source::LiveSource};
+
+    #[test]
+    fn test_own_namespaces() {
+        let me = Process::myself().unwrap();
+        let Ok(ns) = NamespaceCollector::new(Arc::new(LiveSource)).collect(&me) else {
+            // namespaces can't be read in some sandboxes
+            return;
+        };
+        let kinds: Vec<&str> = ns.namespaces.iter().map(|ns| ns.kind).collect();
+        assert!(kinds.iter().all(|k| NAMESPACE_KINDS.contains(k)));
+        assert!(kinds.contains(&"net"));
+        for ns in &ns.namespaces {
+            assert!(ns.shared_with.iter().all(|m| m.pid != me.pid));
+        }
+        assert!(ns.nspid.is_some_and(|pids| !pids.is_empty()));
+    }
+}
@@ -0,0 +1,176 @@
+use std::{borrow::Cow, io::Read};
+
+use procfs::{process::Process, ProcResult};
+
+use super::Collector;
+use crate::util::{lookup_groupname, lookup_username};
+
+/// Capability names, indexed by capability number (see `capabilities(7)`)
+pub const CAPABILITIES: &[&str] = &[
+    "cap_chown",
+    "cap_dac_override",
+    "cap_dac_read_search",
+    "cap_fowner",
+    "cap_fsetid",
+    "cap_kill",
+    "cap_setgid",
+    "cap_setuid",
+    "cap_setpcap",
+    "cap_linux_immutable",
+    "cap_net_bind_service",
+    "cap_net_broadcast",
+    "cap_net_admin",
+    "cap_net_raw",
+    "cap_ipc_lock",
+    "cap_ipc_owner",
+    "cap_sys_module",
+    "cap_sys_rawio",
+    "cap_sys_chroot",
+    "cap_sys_ptrace",
+    "cap_sys_pacct",
+    "cap_sys_admin",
+    "cap_sys_boot",
+    "cap_sys_nice",
+    "cap_sys_resource",
+    "cap_sys_time",
+    "cap_sys_tty_config",
+    "cap_mknod",
+    "cap_lease",
+    "cap_audit_write",
+    "cap_audit_control",
+    "cap_setfcap",
+    "cap_mac_override",
+    "cap_mac_admin",
+    "cap_syslog",
+    "cap_wake_alarm",
+    "cap_block_suspend",
+    "cap_audit_read",
+    "cap_perfmon",
+    "cap_bpf",
+    "cap_checkpoint_restore",
+];
+
+/// The names of the capabilities in a mask, in order.  Capabilities newer than procdump are named
+/// by their number
+pub fn capability_names(mask: u64) -> Vec<Cow<'static, str>> {
+    (0..64)
+        .filter(|bit| mask & (1 << bit) != 0)
+        .map(|bit| match CAPABILITIES.get(bit) {
+            Some(name) => Cow::Borrowed(*name),
+            None => Cow::Owned(format!("cap_{bit}")),
+        })
+        .collect()
+}
+
+/// A user or group ID, along with its name (or `???` if it has none)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedId {
+    pub id: u32,
+    pub name: String,
+}
+
+impl NamedId {
+    fn user(id: u32) -> NamedId {
+        NamedId {
+            id,
+            name: lookup_username(id),
+        }
+    }
+    fn group(id: u32) -> NamedId {
+        NamedId {
+            id,
+            name: lookup_groupname(id),
+        }
+    }
+}
+
+/// The credentials of a process, and everything that limits what it's allowed to do
+pub struct SecurityInfo {
+    /// Real, effective, saved, and filesystem UIDs
+    pub uids: [NamedId; 4],
+    /// Real, effective, saved, and filesystem GIDs
+    pub gids: [NamedId; 4],
+    pub groups: Vec<NamedId>,
+    pub cap_inheritable: u64,
+    pub cap_permitted: u64,
+    pub cap_effective: u64,
+    /// Missing on very old kernels
+    pub cap_bounding: Option<u64>,
+    pub cap_ambient: Option<u64>,
+    /// 0 is disabled, 1 is strict, and 2 is filter.  Missing if the kernel doesn't support seccomp
+    pub seccomp: Option<u32>,
+    pub no_new_privs: Option<bool>,
+    /// The SELinux context or AppArmor profile, from `attr/current`.  `None` if there's no LSM
+    /// that provides one
+    pub lsm_label: Option<String>,
+}
+
+/// Each capability set, along with the name of its line in `status`
+pub fn named_capability_sets(info: &SecurityInfo) -> [(&'static str, Option<u64>); 5] {
+    [
+        ("CapInh", Some(info.cap_inheritable)),
+        ("CapPrm", Some(info.cap_permitted)),
+        ("CapEff", Some(info.cap_effective)),
+        ("CapBnd", info.cap_bounding),
+        ("CapAmb", info.cap_ambient),
+    ]
+}
+
+/// A name for a seccomp mode, as found in `status`
+pub fn seccomp_mode(mode: u32) -> &'static str {
+    match mode {
+        0 => "disabled",
+        1 => "strict",
+        2 => "filter",
+        _ => "unknown",
+    }
+}
+
+/// Reads the credentials, capabilities, and security settings of a process
+#[derive(Default)]
+pub struct SecurityCollector;
+
+impl Collector for SecurityCollector {
+    type Output = ProcResult<SecurityInfo>;
+
+    fn collect(&mut self, proc: &Process) -> Self::Output {
+        let status = proc.status()?;
+        let mut label = String::new();
+        let lsm_label = proc
+            .open_relative("attr/current")
+            .ok()
+            .and_then(|mut file| file.read_to_string(&mut label).ok())
+            .map(|_| label.trim_end_matches(['\0', '\n']))
+            .filter(|label| !label.is_empty())
+            .map(str::to_owned);
+        Ok(SecurityInfo {
+            uids: [status.ruid, status.euid, status.suid, status.fuid].map(NamedId::user),
+            gids: [status.rgid, status.egid, status.sgid, status.fgid].map(NamedId::group),
+            groups: status.groups.iter().map(|gid| NamedId::group(*gid as u32)).collect(),
+            cap_inheritable: status.capinh,
+            cap_permitted: status.capprm,
+            cap_effective: status.capeff,
+            cap_bounding: status.capbnd,
+            cap_ambient: status.capamb,
+            seccomp: status.seccomp,
+            no_new_privs: status.nonewprivs.map(|v| v != 0),
+            lsm_label,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_capability_names() {
+        assert!(super::capability_names(0).is_empty());
+        assert_eq!(
+            super::capability_names(0x2000_0000_0020_0021),
+            ["cap_chown", "cap_kill", "cap_sys_admin", "cap_61"]
+        );
+        assert_eq!(
+            super::capability_names(0x1ff_ffff_ffff).len(),
+            super::CAPABILITIES.len()
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use procfs::{process::Process, ProcResult};
+
+use super::Collector;
+use crate::util::signal_name;
+
+/// The names of the signals in a mask from `status`, where bit N stands for signal N + 1
+pub fn signal_names(mask: u64) -> Vec<String> {
+    (0..64)
+        .filter(|bit| mask & (1 << bit) != 0)
+        .map(|bit| signal_name(bit + 1))
+        .collect()
+}
+
+/// The signal masks of a single thread.  Pending and blocked signals are per thread
+pub struct ThreadSignals {
+    pub tid: i32,
+    pub comm: String,
+    /// `SigPnd`: signals sent to this thread that haven't been delivered yet
+    pub pending: u64,
+    /// `SigBlk`
+    pub blocked: u64,
+}
+
+/// The signal masks of a process and each of its threads
+pub struct ProcessSignals {
+    /// `ShdPnd`: signals sent to the process as a whole that haven't been delivered yet
+    pub shared_pending: u64,
+    /// `SigIgn`.  Signal dispositions are shared by every thread
+    pub ignored: u64,
+    /// `SigCgt`: signals that have a handler
+    pub caught: u64,
+    pub threads: Vec<ThreadSignals>,
+}
+
+impl ProcessSignals {
+    /// Describes what happens to a signal that's sent to the process right now
+    pub fn disposition(&self, signal: i32) -> &'static str {
+        let bit = 1u64 << (signal - 1);
+        if self.ignored & bit != 0 {
+            "ignored"
+        } else if !self.threads.is_empty() && self.threads.iter().all(|t| t.blocked & bit != 0) {
+            "blocked by every thread"
+        } else if self.caught & bit != 0 {
+            "caught by a handler"
+        } else {
+            "default action"
+        }
+    }
+}
+
+/// Reads the pending, blocked, ignored, and caught signals of a process and its threads
+#[derive(Default)]
+pub struct SignalsCollector;
+
+impl Collector for SignalsCollector {
+    type Output = ProcResult<ProcessSignals>;
+
+    fn collect(&mut self, proc: &Process) -> Self::Output {
+        let status = proc.status()?;
+        let threads = proc
+            .tasks()?
+            .filter_map(|t| t.ok())
+            .filter_map(|task| task.status().ok())
+            .map(|status| ThreadSignals {
+                tid: status.pid,
+                comm: status.name,
+                pending: status.sigpnd,
+                blocked: status.sigblk,
+            })
+            .collect();
+        Ok(ProcessSignals {
+            shared_pending: status.shdpnd,
+            ignored: status.sigign,
+            caught: status.sigcgt,
+            threads,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_signal_names() {
+        assert!(super::signal_names(0).is_empty());
+        // what an interactive shell ignores
+        assert_eq!(
+            super::signal_names(0x380004),
+            ["SIGQUIT", "SIGTSTP", "SIGTTIN", "SIGTTOU"]
+        );
+    }
+}
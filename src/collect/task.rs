@@ -0,0 +1,204 @@
+use std::io::Read;
+
+use indexmap::IndexMap;
+use procfs::{
+    process::{Io, Process, Stat},
+    ProcResult,
+};
+
+use super::Collector;
+
+/// A single thread of a process
+pub struct TaskInfo {
+    pub tid: i32,
+    pub stat: Stat,
+    pub io: Io,
+    /// The kernel function the thread is waiting in, or `None` if it's running (or if that's hidden,
+    /// or wasn't read)
+    pub wchan: Option<String>,
+    /// The kernel stack of the thread, innermost frame first.  Reading it needs `CAP_SYS_ADMIN` (and
+    /// a kernel built with `CONFIG_STACKTRACE`), so this is usually `None` unless running as root.  Both
+    /// are only read when [`TaskCollector::stacks`] is set
+    pub stack: Option<Vec<String>>,
+}
+
+/// Threads that are stopped in the same place in the kernel
+pub struct StackGroup<'a> {
+    pub wchan: Option<&'a str>,
+    pub stack: Option<&'a [String]>,
+    pub tids: Vec<i32>,
+}
+
+impl StackGroup<'_> {
+    /// A short description of where the threads are, like `futex_wait_queue`
+    pub fn place(&self) -> &str {
+        match (self.wchan, self.stack.and_then(|s| s.first())) {
+            (Some(wchan), _) => wchan,
+            (None, Some(frame)) => frame.split('+').next().unwrap_or(frame),
+            (None, None) => "running",
+        }
+    }
+}
+
+/// Groups threads that have the same kernel stack (or the same wchan, when the stack can't be
+/// read), with the largest groups first
+pub fn group_stacks<'a>(tasks: impl IntoIterator<Item = &'a TaskInfo>) -> Vec<StackGroup<'a>> {
+    let mut groups: IndexMap<_, StackGroup> = IndexMap::new();
+    for task in tasks {
+        let wchan = task.wchan.as_deref();
+        let stack = task.stack.as_deref();
+        groups
+            .entry((wchan, stack))
+            .or_insert_with(|| StackGroup {
+                wchan,
+                stack,
+                tids: Vec::new(),
+            })
+            .tids
+            .push(task.tid);
+    }
+    let mut groups: Vec<_> = groups.into_values().collect();
+    // a stable sort, so groups of the same size stay in TID order
+    groups.sort_by_key(|group| std::cmp::Reverse(group.tids.len()));
+    groups
+}
+
+/// Reads a file of a thread, like `wchan`
+fn read_task_file(proc: &Process, tid: i32, name: &str) -> Option<String> {
+    let mut contents = String::new();
+    proc.open_relative(&format!("task/{tid}/{name}"))
+        .ok()?
+        .read_to_string(&mut contents)
+        .ok()?;
+    Some(contents)
+}
+
+/// Parses a kernel stack, where each line looks like `[<0>] futex_wait_queue+0x60/0xa0`.  The
+/// addresses are dropped, since they're zeroed out for anyone without `CAP_SYSLOG` anyway
+fn parse_stack(stack: &str) -> Vec<String> {
+    stack
+        .lines()
+        .map(|line| match line.split_once("] ") {
+            Some((_, frame)) => frame.trim().to_owned(),
+            None => line.trim().to_owned(),
+        })
+        .filter(|frame| !frame.is_empty())
+        .collect()
+}
+
+/// Reads all of the threads of a process, keyed by TID
+#[derive(Default)]
+pub struct TaskCollector {
+    /// If true, also read the `wchan` and kernel stack of every thread, which is two more files per
+    /// thread
+    pub stacks: bool,
+}
+
+impl Collector for TaskCollector {
+    type Output = ProcResult<IndexMap<i32, TaskInfo>>;
+
+    fn collect(&mut self, proc: &Process) -> Self::Output {
+        Ok(proc
+            .tasks()?
+            .filter_map(|t| t.ok())
+            .filter_map(|task| match (task.io(), task.stat()) {
+                (Ok(io), Ok(stat)) => Some((
+                    task.tid,
+                    TaskInfo {
+                        tid: task.tid,
+                        stat,
+                        io,
+                        // a running thread has a wchan of "0"
+                        wchan: self
+                            .stacks
+                            .then(|| read_task_file(proc, task.tid, "wchan"))
+                            .flatten()
+                            .map(|wchan| wchan.trim().to_owned())
+                            .filter(|wchan| !wchan.is_empty() && wchan != "0"),
+                        stack: self
+                            .stacks
+                            .then(|| read_task_file(proc, task.tid, "stack"))
+                            .flatten()
+                            .map(|stack| parse_stack(&stack)),
+                    },
+                )),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use procfs::process::Io;
+
+    use super::TaskInfo;
+    use crate::source::fake_stat;
+
+    fn task(tid: i32, wchan: Option<&str>, stack: Option<&[&str]>) -> TaskInfo {
+        TaskInfo {
+            tid,
+            stat: fake_stat(tid, "worker", 0, 0),
+            io: Io {
+                rchar: 0,
+                wchar: 0,
+                syscr: 0,
+                syscw: 0,
+                read_bytes: 0,
+                write_bytes: 0,
+                cancelled_write_bytes: 0,
+            },
+            wchan: wchan.map(str::to_owned),
+            stack: stack.map(|frames| frames.iter().map(|f| f.to_string()).collect()),
+        }
+    }
+
+    #[test]
+    fn test_group_stacks() {
+        let futex: &[&str] = &["futex_wait_queue+0x60/0xa0", "futex_wait+0x16b/0x270"];
+        let poll: &[&str] = &["do_sys_poll+0x3e6/0x560", "__x64_sys_poll+0xb1/0x140"];
+        let tasks = [
+            task(100, Some("do_sys_poll"), Some(poll)),
+            task(101, Some("futex_wait_queue"), Some(futex)),
+            // running, so it has no wchan, and its stack is empty
+            task(102, None, Some(&[])),
+            task(103, Some("futex_wait_queue"), Some(futex)),
+            task(104, Some("futex_wait_queue"), Some(futex)),
+            // the same wchan, but somewhere else in the kernel
+            task(105, Some("futex_wait_queue"), Some(&futex[..1])),
+            // stacks that can't be read are grouped by wchan
+            task(106, Some("pipe_read"), None),
+            task(107, Some("pipe_read"), None),
+        ];
+        let groups = super::group_stacks(&tasks);
+        let summary: Vec<_> = groups.iter().map(|g| (g.place(), g.tids.as_slice())).collect();
+        assert_eq!(
+            summary,
+            [
+                ("futex_wait_queue", &[101, 103, 104][..]),
+                // groups of the same size are in the order their first thread was seen
+                ("pipe_read", &[106, 107][..]),
+                ("do_sys_poll", &[100][..]),
+                ("running", &[102][..]),
+                ("futex_wait_queue", &[105][..]),
+            ]
+        );
+        assert_eq!(groups[0].stack.unwrap().len(), 2);
+        assert!(groups[1].stack.is_none());
+        assert_eq!(groups[4].stack.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_stack() {
+        let stack = "[<0>] futex_wait_queue+0x60/0xa0\n[<0>] futex_wait+0x16b/0x270\n[<0>] do_futex+0x11c/0x1b0\n";
+        assert_eq!(
+            super::parse_stack(stack),
+            [
+                "futex_wait_queue+0x60/0xa0",
+                "futex_wait+0x16b/0x270",
+                "do_futex+0x11c/0x1b0"
+            ]
+        );
+        assert!(super::parse_stack("").is_empty());
+    }
+}
@@ -0,0 +1,633 @@
+//! Writes an ELF core file of a running process, like the one the kernel writes when a process
+//! crashes, so that it can be opened with `gdb <program> <core>`.
+//!
+//! The threads of the process are stopped with ptrace while the core is written, and carry on
+//! afterwards.  Their registers come from ptrace, the list of mappings from `/proc/<pid>/maps`, and
+//! the memory itself from `/proc/<pid>/mem`.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, ErrorKind, Read, Write},
+    os::unix::fs::FileExt,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use procfs::process::{MMPermissions, MMapPath, Process, Stat};
+
+use crate::source::{LiveSource, ProcessId};
+
+/// The ELF machine type matching the registers that ptrace hands out
+#[cfg(target_arch = "x86_64")]
+const MACHINE: Option<u16> = Some(62); // EM_X86_64
+#[cfg(target_arch = "aarch64")]
+const MACHINE: Option<u16> = Some(183); // EM_AARCH64
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+const MACHINE: Option<u16> = None;
+
+const ET_CORE: u16 = 4;
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+const NT_PRSTATUS: u32 = 1;
+const NT_PRFPREG: u32 = 2;
+const NT_PRPSINFO: u32 = 3;
+const NT_AUXV: u32 = 6;
+const NT_FILE: u32 = 0x4649_4c45;
+
+const EHDR_SIZE: u64 = 64;
+const PHDR_SIZE: u64 = 56;
+
+/// How long to wait for the threads to stop.  A thread that's stuck in the kernel (in D state)
+/// won't stop until it gets out, which might be never
+const STOP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How much memory is read at once
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// What went into a core file
+#[derive(Debug, Default)]
+pub struct CoreDump {
+    /// The size of the file
+    pub bytes: u64,
+    /// The number of threads whose registers were saved
+    pub threads: usize,
+    /// The number of memory mappings
+    pub regions: usize,
+    /// Threads that didn't stop in time, and so aren't in the core
+    pub missing_threads: Vec<i32>,
+    /// Bytes of memory that couldn't be read, and were written as zeros
+    pub unreadable: u64,
+}
+
+/// Writes a core file of process `id` to `path`, which mustn't exist yet.  If its PID now belongs to another process, this
+/// fails with [`ErrorKind::NotFound`] instead of dumping the wrong one.
+///
+/// This needs permission to ptrace the process, which usually means being root or being its parent
+/// (see `kernel.yama.ptrace_scope`).
+pub fn write_core(id: ProcessId, path: &Path) -> io::Result<CoreDump> {
+    let Some(machine) = MACHINE else {
+        return Err(io::Error::new(
+            ErrorKind::Unsupported,
+            "core dumps can only be written on x86_64 and aarch64",
+        ));
+    };
+    let path = path.to_path_buf();
+    // The kernel ties a tracee to the thread that attached to it, and lets go of any that are left
+    // when that thread exits.  Threads that never stopped can't be detached from while they're
+    // running, so the tracing happens on a thread of its own, and ending it lets go of them
+    std::thread::spawn(move || write_core_traced(id, &path, machine))
+        .join()
+        .unwrap_or_else(|_| Err(io::Error::other("the core dump thread panicked")))
+}
+
+fn write_core_traced(id: ProcessId, path: &Path, machine: u16) -> io::Result<CoreDump> {
+    let pid = id.pid;
+    let gone = || io::Error::new(ErrorKind::NotFound, format!("process {pid} has exited"));
+    let proc = id.open(&LiveSource).map_err(|_| gone())?;
+    let tracer = Tracer::attach(pid)?;
+    // it could have exited, and the PID been reused, just before it was attached to
+    if !id.is_current(&LiveSource) {
+        return Err(gone());
+    }
+    let mut dump = CoreDump::default();
+
+    let mut thread_notes = Vec::new();
+    for thread in &tracer.threads {
+        if thread.gone {
+            continue;
+        }
+        if !thread.stopped {
+            dump.missing_threads.push(thread.tid);
+            continue;
+        }
+        let Some(regs) = get_regset(thread.tid, NT_PRSTATUS, 1024) else {
+            dump.missing_threads.push(thread.tid);
+            continue;
+        };
+        let fpregs = get_regset(thread.tid, NT_PRFPREG, 4096);
+        let stat = proc.task_from_tid(thread.tid).and_then(|task| task.stat()).ok();
+        thread_notes.push(Note::new(
+            NT_PRSTATUS,
+            prstatus(thread.tid, stat.as_ref(), &regs, fpregs.is_some()),
+        ));
+        if let Some(fpregs) = fpregs {
+            thread_notes.push(Note::new(NT_PRFPREG, fpregs));
+        }
+        dump.threads += 1;
+    }
+    if dump.threads == 0 {
+        return Err(io::Error::other("none of the threads could be stopped"));
+    }
+
+    let page_size = procfs::page_size();
+    let maps = proc.maps().map_err(io::Error::other)?;
+    let regions: Vec<Region> = maps
+        .iter()
+        // the kernel's own page, which can't be read through /proc/<pid>/mem
+        .filter(|m| m.pathname != MMapPath::Vsyscall)
+        .map(|m| Region {
+            start: m.address.0,
+            end: m.address.1,
+            flags: segment_flags(m.perms),
+            dumped: m.perms.contains(MMPermissions::READ) && !is_vvar(&m.pathname),
+        })
+        .collect();
+    let files: Vec<(u64, u64, u64, &Path)> = maps
+        .iter()
+        .filter_map(|m| match &m.pathname {
+            MMapPath::Path(p) => Some((m.address.0, m.address.1, m.offset / page_size, p.as_path())),
+            _ => None,
+        })
+        .collect();
+
+    // The first NT_PRSTATUS is the thread that gdb starts out on, and the process-wide notes
+    // follow it, the same way the kernel lays them out
+    let mut notes = Vec::new();
+    let mut thread_notes = thread_notes.into_iter().peekable();
+    notes.extend(thread_notes.next());
+    if thread_notes.peek().is_some_and(|n| n.kind == NT_PRFPREG) {
+        notes.extend(thread_notes.next());
+    }
+    notes.push(Note::new(NT_PRPSINFO, prpsinfo(&proc)));
+    if let Ok(auxv) = fs::read(format!("/proc/{pid}/auxv")) {
+        notes.push(Note::new(NT_AUXV, auxv));
+    }
+    notes.push(Note::new(NT_FILE, file_note(&files, page_size)));
+    notes.extend(thread_notes);
+
+    let phnum = 1 + regions.len();
+    let phnum = u16::try_from(phnum).map_err(|_| io::Error::other(format!("too many memory mappings ({phnum})")))?;
+    let notes_offset = EHDR_SIZE + PHDR_SIZE * phnum as u64;
+    let notes_size: u64 = notes.iter().map(Note::size).sum();
+    let data_offset = (notes_offset + notes_size).next_multiple_of(page_size);
+
+    // never overwrite an earlier core
+    let mut out = BufWriter::new(File::options().write(true).create_new(true).open(path)?);
+    out.write_all(&elf_header(machine, phnum))?;
+    out.write_all(&program_header(PT_NOTE, 0, notes_offset, 0, notes_size, 0, 4))?;
+    let mut offset = data_offset;
+    for region in &regions {
+        let size = region.end - region.start;
+        let file_size = if region.dumped { size } else { 0 };
+        out.write_all(&program_header(
+            PT_LOAD,
+            region.flags,
+            offset,
+            region.start,
+            file_size,
+            size,
+            page_size,
+        ))?;
+        offset += file_size;
+    }
+    for note in &notes {
+        note.write(&mut out)?;
+    }
+    io::copy(
+        &mut io::repeat(0).take(data_offset - notes_offset - notes_size),
+        &mut out,
+    )?;
+
+    let mem = File::open(format!("/proc/{pid}/mem"))?;
+    let mut buf = vec![0; CHUNK_SIZE];
+    for region in regions.iter().filter(|r| r.dumped) {
+        dump.unreadable += copy_memory(&mem, region.start, region.end, page_size as usize, &mut buf, &mut out)?;
+    }
+    out.flush()?;
+    drop(tracer);
+
+    dump.bytes = offset;
+    dump.regions = regions.len();
+    Ok(dump)
+}
+
+/// A thread being traced
+struct Thread {
+    tid: i32,
+    stopped: bool,
+    /// The thread exited while it was being stopped
+    gone: bool,
+    /// A signal that was about to be delivered when the thread stopped, and that has to be passed
+    /// on when it's let go
+    signal: i32,
+}
+
+/// The threads of a process, stopped with ptrace.  Dropping this lets the stopped threads go
+struct Tracer {
+    threads: Vec<Thread>,
+}
+
+impl Tracer {
+    fn attach(pid: i32) -> io::Result<Tracer> {
+        let mut tracer = Tracer { threads: Vec::new() };
+        let deadline = Instant::now() + STOP_TIMEOUT;
+        // threads can start while the others are being stopped, so keep going until there are no new ones
+        loop {
+            let mut tids: Vec<i32> = fs::read_dir(format!("/proc/{pid}/task"))?
+                .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+                .filter(|tid| !tracer.threads.iter().any(|t| t.tid == *tid))
+                .collect();
+            if tids.is_empty() {
+                break;
+            }
+            // the main thread comes first
+            tids.sort_by_key(|&tid| (tid != pid, tid));
+            for tid in tids {
+                // SAFETY: none of these requests touch our memory
+                if unsafe { libc::ptrace(libc::PTRACE_SEIZE, tid, 0, 0) } == -1 {
+                    let e = io::Error::last_os_error();
+                    if e.raw_os_error() == Some(libc::ESRCH) {
+                        // the thread exited
+                        continue;
+                    }
+                    return Err(e);
+                }
+                tracer.threads.push(Thread {
+                    tid,
+                    stopped: false,
+                    gone: false,
+                    signal: 0,
+                });
+                unsafe { libc::ptrace(libc::PTRACE_INTERRUPT, tid, 0, 0) };
+            }
+            tracer.wait(deadline);
+        }
+        Ok(tracer)
+    }
+
+    /// Waits for the threads to stop, until `deadline`
+    fn wait(&mut self, deadline: Instant) {
+        loop {
+            let mut waiting = false;
+            for thread in self.threads.iter_mut().filter(|t| !t.stopped && !t.gone) {
+                let mut status = 0;
+                // SAFETY: `status` is a valid place to write to
+                let ret = unsafe { libc::waitpid(thread.tid, &mut status, libc::__WALL | libc::WNOHANG) };
+                if ret == 0 {
+                    waiting = true;
+                } else if ret == thread.tid && libc::WIFSTOPPED(status) {
+                    thread.stopped = true;
+                    // a stop with no event is a signal on its way to the thread, rather than our interrupt
+                    if status >> 16 == 0 {
+                        thread.signal = libc::WSTOPSIG(status);
+                    }
+                } else {
+                    thread.gone = true;
+                }
+            }
+            if !waiting || Instant::now() >= deadline {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+impl Drop for Tracer {
+    fn drop(&mut self) {
+        for thread in self.threads.iter().filter(|t| t.stopped && !t.gone) {
+            // SAFETY: no memory is passed in
+            unsafe { libc::ptrace(libc::PTRACE_DETACH, thread.tid, 0, thread.signal as libc::c_long) };
+        }
+    }
+}
+
+/// Reads a set of registers (like `NT_PRSTATUS`) from a stopped thread
+fn get_regset(tid: i32, kind: u32, max_size: usize) -> Option<Vec<u8>> {
+    let mut buf = vec![0u8; max_size];
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr().cast(),
+        iov_len: buf.len(),
+    };
+    // SAFETY: the kernel writes at most `iov_len` bytes into `buf`, and updates `iov_len`
+    let ret = unsafe {
+        libc::ptrace(
+            libc::PTRACE_GETREGSET,
+            tid,
+            kind as libc::c_ulong,
+            &mut iov as *mut libc::iovec,
+        )
+    };
+    if ret == -1 {
+        return None;
+    }
+    buf.truncate(iov.iov_len);
+    Some(buf)
+}
+
+/// vvar holds kernel data that can't be read through `/proc/<pid>/mem`.  Newer kernels split it
+/// into several mappings, like `[vvar_vclock]`
+fn is_vvar(path: &MMapPath) -> bool {
+    match path {
+        MMapPath::Vvar => true,
+        MMapPath::Other(name) => name.starts_with("vvar"),
+        _ => false,
+    }
+}
+
+/// A memory mapping, and the `PT_LOAD` segment for it
+struct Region {
+    start: u64,
+    end: u64,
+    flags: u32,
+    /// Whether the contents go into the core.  Mappings that can't be read are still listed, so
+    /// that the debugger knows about them
+    dumped: bool,
+}
+
+fn segment_flags(perms: MMPermissions) -> u32 {
+    let mut flags = 0;
+    if perms.contains(MMPermissions::READ) {
+        flags |= PF_R;
+    }
+    if perms.contains(MMPermissions::WRITE) {
+        flags |= PF_W;
+    }
+    if perms.contains(MMPermissions::EXECUTE) {
+        flags |= PF_X;
+    }
+    flags
+}
+
+/// Copies memory from `/proc/<pid>/mem` to `out`, with zeros in place of the pages that can't be
+/// read.  Returns the number of bytes that couldn't be read
+fn copy_memory(
+    mem: &File,
+    start: u64,
+    end: u64,
+    page_size: usize,
+    buf: &mut [u8],
+    out: &mut impl Write,
+) -> io::Result<u64> {
+    let mut unreadable = 0;
+    let mut addr = start;
+    while addr < end {
+        let chunk = &mut buf[..(end - addr).min(CHUNK_SIZE as u64) as usize];
+        if mem.read_exact_at(chunk, addr).is_err() {
+            // try again a page at a time, to save as much as possible
+            for (i, page) in chunk.chunks_mut(page_size).enumerate() {
+                if mem.read_exact_at(page, addr + (i * page_size) as u64).is_err() {
+                    page.fill(0);
+                    unreadable += page.len() as u64;
+                }
+            }
+        }
+        out.write_all(chunk)?;
+        addr += chunk.len() as u64;
+    }
+    Ok(unreadable)
+}
+
+/// An ELF note.  Every note in a core file is named "CORE"
+struct Note {
+    kind: u32,
+    desc: Vec<u8>,
+}
+
+impl Note {
+    const NAME: &'static [u8; 8] = b"CORE\0\0\0\0";
+
+    fn new(kind: u32, desc: Vec<u8>) -> Note {
+        Note { kind, desc }
+    }
+
+    fn size(&self) -> u64 {
+        12 + Note::NAME.len() as u64 + (self.desc.len() as u64).next_multiple_of(4)
+    }
+
+    fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(&5u32.to_le_bytes())?;
+        out.write_all(&(self.desc.len() as u32).to_le_bytes())?;
+        out.write_all(&self.kind.to_le_bytes())?;
+        out.write_all(Note::NAME)?;
+        out.write_all(&self.desc)?;
+        out.write_all(&[0; 3][..self.desc.len().next_multiple_of(4) - self.desc.len()])
+    }
+}
+
+fn elf_header(machine: u16, phnum: u16) -> Vec<u8> {
+    let mut b = Vec::with_capacity(EHDR_SIZE as usize);
+    // 64 bit, little endian, version 1, System V ABI
+    b.extend_from_slice(b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0");
+    b.extend_from_slice(&ET_CORE.to_le_bytes());
+    b.extend_from_slice(&machine.to_le_bytes());
+    b.extend_from_slice(&1u32.to_le_bytes()); // e_version
+    b.extend_from_slice(&0u64.to_le_bytes()); // e_entry
+    b.extend_from_slice(&EHDR_SIZE.to_le_bytes()); // e_phoff
+    b.extend_from_slice(&0u64.to_le_bytes()); // e_shoff
+    b.extend_from_slice(&0u32.to_le_bytes()); // e_flags
+    b.extend_from_slice(&(EHDR_SIZE as u16).to_le_bytes());
+    b.extend_from_slice(&(PHDR_SIZE as u16).to_le_bytes());
+    b.extend_from_slice(&phnum.to_le_bytes());
+    b.extend_from_slice(&64u16.to_le_bytes()); // e_shentsize
+    b.extend_from_slice(&0u16.to_le_bytes()); // e_shnum
+    b.extend_from_slice(&0u16.to_le_bytes()); // e_shstrndx
+    b
+}
+
+fn program_header(
+    kind: u32,
+    flags: u32,
+    offset: u64,
+    vaddr: u64,
+    file_size: u64,
+    mem_size: u64,
+    align: u64,
+) -> Vec<u8> {
+    let mut b = Vec::with_capacity(PHDR_SIZE as usize);
+    b.extend_from_slice(&kind.to_le_bytes());
+    b.extend_from_slice(&flags.to_le_bytes());
+    b.extend_from_slice(&offset.to_le_bytes());
+    b.extend_from_slice(&vaddr.to_le_bytes());
+    b.extend_from_slice(&0u64.to_le_bytes()); // p_paddr
+    b.extend_from_slice(&file_size.to_le_bytes());
+    b.extend_from_slice(&mem_size.to_le_bytes());
+    b.extend_from_slice(&align.to_le_bytes());
+    b
+}
+
+/// Turns clock ticks into a `struct timeval`
+fn timeval(ticks: u64) -> [u8; 16] {
+    let tps = procfs::ticks_per_second();
+    let mut b = [0; 16];
+    b[..8].copy_from_slice(&(ticks / tps).to_le_bytes());
+    b[8..].copy_from_slice(&((ticks % tps) * 1_000_000 / tps).to_le_bytes());
+    b
+}
+
+/// `struct elf_prstatus`, which holds the registers of one thread
+fn prstatus(tid: i32, stat: Option<&Stat>, regs: &[u8], fpvalid: bool) -> Vec<u8> {
+    let mut b = Vec::new();
+    b.extend_from_slice(&[0; 12]); // pr_info: no signal caused this dump
+    b.extend_from_slice(&[0; 4]); // pr_cursig, and padding
+    b.extend_from_slice(&stat.map_or(0, |s| s.signal).to_le_bytes());
+    b.extend_from_slice(&stat.map_or(0, |s| s.blocked).to_le_bytes());
+    b.extend_from_slice(&tid.to_le_bytes());
+    b.extend_from_slice(&stat.map_or(0, |s| s.ppid).to_le_bytes());
+    b.extend_from_slice(&stat.map_or(0, |s| s.pgrp).to_le_bytes());
+    b.extend_from_slice(&stat.map_or(0, |s| s.session).to_le_bytes());
+    b.extend_from_slice(&timeval(stat.map_or(0, |s| s.utime)));
+    b.extend_from_slice(&timeval(stat.map_or(0, |s| s.stime)));
+    b.extend_from_slice(&timeval(stat.map_or(0, |s| s.cutime.max(0) as u64)));
+    b.extend_from_slice(&timeval(stat.map_or(0, |s| s.cstime.max(0) as u64)));
+    b.extend_from_slice(regs);
+    b.extend_from_slice(&(fpvalid as i32).to_le_bytes());
+    b.resize(b.len().next_multiple_of(8), 0);
+    b
+}
+
+/// `struct elf_prpsinfo`, which describes the process
+fn prpsinfo(proc: &Process) -> Vec<u8> {
+    let stat = proc.stat().ok();
+    let (uid, gid) = proc.status().map_or((0, 0), |s| (s.ruid, s.rgid));
+    let state = stat.as_ref().map_or('R', |s| s.state);
+    let mut b = vec![
+        "RSDTZW".find(state).unwrap_or(0) as u8,
+        state as u8,
+        (state == 'Z') as u8,
+        stat.as_ref().map_or(0, |s| s.nice) as i8 as u8,
+    ];
+    b.extend_from_slice(&[0; 4]);
+    b.extend_from_slice(&stat.as_ref().map_or(0, |s| s.flags as u64).to_le_bytes());
+    b.extend_from_slice(&uid.to_le_bytes());
+    b.extend_from_slice(&gid.to_le_bytes());
+    b.extend_from_slice(&proc.pid.to_le_bytes());
+    b.extend_from_slice(&stat.as_ref().map_or(0, |s| s.ppid).to_le_bytes());
+    b.extend_from_slice(&stat.as_ref().map_or(0, |s| s.pgrp).to_le_bytes());
+    b.extend_from_slice(&stat.as_ref().map_or(0, |s| s.session).to_le_bytes());
+    let comm = stat.as_ref().map_or("", |s| s.comm.as_str());
+    b.extend_from_slice(&c_string(comm, 16));
+    let args = proc.cmdline().unwrap_or_default().join(" ");
+    b.extend_from_slice(&c_string(&args, 80));
+    b
+}
+
+/// A NUL terminated string in a fixed size field, truncated if it doesn't fit
+fn c_string(s: &str, size: usize) -> Vec<u8> {
+    let mut b: Vec<u8> = s.bytes().take(size - 1).collect();
+    b.resize(size, 0);
+    b
+}
+
+/// `NT_FILE`, which lists the mapped files so that the debugger can find the program and its
+/// libraries
+fn file_note(files: &[(u64, u64, u64, &Path)], page_size: u64) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut b = Vec::new();
+    b.extend_from_slice(&(files.len() as u64).to_le_bytes());
+    b.extend_from_slice(&page_size.to_le_bytes());
+    for (start, end, page_offset, _) in files {
+        b.extend_from_slice(&start.to_le_bytes());
+        b.extend_from_slice(&end.to_le_bytes());
+        b.extend_from_slice(&page_offset.to_le_bytes());
+    }
+    for (_, _, _, path) in files {
+        b.extend_from_slice(path.as_os_str().as_bytes());
+        b.push(0);
+    }
+    b
+}
+
+/// Where to write a core of `proc`: `<dir>/core.<name>.<pid>.<timestamp>`, with `.1`, `.2`, and so
+/// on added if needed, so that two cores written in the same second don't end up in the same place
+pub fn core_path(dir: &Path, proc: &Process) -> PathBuf {
+    let name = proc.stat().map(|s| s.comm.replace('/', "_")).unwrap_or_default();
+    let time = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    unused_path(&dir.join(format!("core.{name}.{}.{time}", proc.pid)))
+}
+
+/// `path`, or the first of `path.1`, `path.2`, and so on that doesn't exist yet
+fn unused_path(path: &Path) -> PathBuf {
+    let mut candidate = path.to_owned();
+    for n in 1.. {
+        if !candidate.exists() {
+            break;
+        }
+        candidate = PathBuf::from(format!("{}.{n}", path.display()));
+    }
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use procfs::process::Process;
+
+    use super::{unused_path, write_core, NT_PRPSINFO, NT_PRSTATUS, PT_LOAD, PT_NOTE};
+    use crate::source::ProcessId;
+
+    fn u16_at(b: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes(b[at..at + 2].try_into().unwrap())
+    }
+    fn u32_at(b: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(b[at..at + 4].try_into().unwrap())
+    }
+    fn u64_at(b: &[u8], at: usize) -> u64 {
+        u64::from_le_bytes(b[at..at + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_write_core() {
+        let mut child = std::process::Command::new("sleep").arg("10").spawn().unwrap();
+        let path = std::env::temp_dir().join(format!("procdump-core-{}", std::process::id()));
+        let id = ProcessId::of(&Process::new(child.id() as i32).unwrap()).unwrap();
+        let dump = write_core(id, &path);
+        child.kill().unwrap();
+        child.wait().unwrap();
+        let dump = match dump {
+            Ok(dump) => dump,
+            Err(e) if e.raw_os_error() == Some(libc::EPERM) => {
+                eprintln!("Skipping test_write_core, not allowed to ptrace: {e}");
+                return;
+            }
+            Err(e) => panic!("{e}"),
+        };
+        let core = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(core.len() as u64, dump.bytes);
+        assert_eq!(dump.threads, 1);
+
+        assert_eq!(&core[..4], b"\x7fELF");
+        assert_eq!(u16_at(&core, 16), 4);
+        let phnum = u16_at(&core, 56) as usize;
+        assert_eq!(phnum, dump.regions + 1);
+
+        let phdr = |i: usize| &core[64 + i * 56..64 + (i + 1) * 56];
+        assert_eq!(u32_at(phdr(0), 0), PT_NOTE);
+        let mut notes = Vec::new();
+        let (mut at, end) = (
+            u64_at(phdr(0), 8) as usize,
+            (u64_at(phdr(0), 8) + u64_at(phdr(0), 32)) as usize,
+        );
+        while at < end {
+            let (desc_len, kind) = (u32_at(&core, at + 4) as usize, u32_at(&core, at + 8));
+            assert_eq!(&core[at + 12..at + 17], b"CORE\0");
+            notes.push(kind);
+            at += 20 + desc_len.next_multiple_of(4);
+        }
+        assert_eq!(notes.iter().filter(|&&n| n == NT_PRSTATUS).count(), 1);
+        assert!(notes.contains(&NT_PRPSINFO));
+
+        // the first mapping is the start of the sleep binary, which is an ELF file too
+        assert_eq!(u32_at(phdr(1), 0), PT_LOAD);
+        let offset = u64_at(phdr(1), 8) as usize;
+        assert_eq!(&core[offset..offset + 4], b"\x7fELF");
+    }
+
+    #[test]
+    fn test_unused_path() {
+        let dir = std::env::temp_dir().join(format!("procdump-core-path-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("core.sleep.101.20240101-120000");
+        assert_eq!(unused_path(&path), path);
+        std::fs::write(&path, b"").unwrap();
+        assert_eq!(unused_path(&path), dir.join("core.sleep.101.20240101-120000.1"));
+        std::fs::write(dir.join("core.sleep.101.20240101-120000.1"), b"").unwrap();
+        assert_eq!(unused_path(&path), dir.join("core.sleep.101.20240101-120000.2"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
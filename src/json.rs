@@ -0,0 +1,636 @@
+//! The schema for the `--json` output.
+//!
+//! See `docs/json.md` for a description of every field.  Removing or renaming a field, or changing
+//! what one means, must bump [`SCHEMA_VERSION`].  New fields can be added without bumping it, since
+//! consumers are told to ignore fields they don't know about.
+
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::fmt::Display;
+
+use procfs::process::{FDPermissions, FDTarget, LimitValue, Limits, MMapPath};
+use serde::Serialize;
+
+use crate::collect;
+use crate::util::ProcessTreeEntry;
+
+/// The version of the JSON document.  This is bumped whenever a field is removed or changes meaning
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+pub struct Document {
+    pub schema_version: u32,
+    /// When this document was generated, in RFC 3339 format
+    pub generated_at: String,
+    pub pid: i32,
+    pub process: Section<ProcessInfo>,
+    pub cpu: Section<CpuInfo>,
+    pub env: Section<BTreeMap<String, String>>,
+    pub net: Section<Vec<Socket>>,
+    pub maps: Section<Vec<Map>>,
+    pub memory: Section<BTreeMap<String, u64>>,
+    pub files: Section<Vec<File>>,
+    pub limits: Section<Vec<Limit>>,
+    pub tree: Section<Vec<TreeEntry>>,
+    pub cgroups: Section<Vec<CGroup>>,
+    pub namespaces: Section<Namespaces>,
+    pub security: Section<Security>,
+    pub signals: Section<Signals>,
+    pub mounts: Section<Vec<Mount>>,
+    pub io: Section<Io>,
+    pub tasks: Section<Vec<Task>>,
+    pub events: Section<Vec<Event>>,
+}
+
+/// Every section of the document is either some data, or an error explaining why the data couldn't
+/// be collected.  Exactly one of these two fields will be present.
+#[derive(Debug, Serialize)]
+pub struct Section<T> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl<T> Section<T> {
+    pub fn data(data: T) -> Section<T> {
+        Section {
+            data: Some(data),
+            error: None,
+        }
+    }
+    pub fn error(e: impl Display) -> Section<T> {
+        Section {
+            data: None,
+            error: Some(e.to_string()),
+        }
+    }
+}
+
+impl<T, E: Display> From<Result<T, E>> for Section<T> {
+    fn from(r: Result<T, E>) -> Section<T> {
+        match r {
+            Ok(data) => Section::data(data),
+            Err(e) => Section::error(e),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProcessInfo {
+    pub pid: i32,
+    pub ppid: i32,
+    pub pgrp: i32,
+    pub session: i32,
+    pub comm: String,
+    pub cmdline: Option<Vec<String>>,
+    pub state: char,
+    pub alive: bool,
+    pub start_time: Option<String>,
+    pub uid: Option<u32>,
+    pub user: Option<String>,
+    pub threads: Option<u64>,
+    pub nice: i64,
+    /// Sizes are in bytes
+    pub vmsize: Option<u64>,
+    pub vmrss: Option<u64>,
+    pub shared: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CpuInfo {
+    pub percent: f32,
+    pub user_ms: u64,
+    pub kernel_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Socket {
+    pub inode: u64,
+    /// One of "tcp", "udp", or "unix"
+    pub protocol: &'static str,
+    pub local_address: Option<String>,
+    pub remote_address: Option<String>,
+    pub state: String,
+    /// Only for unix sockets: one of "stream", "dgram", "seqpacket", or "other"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socket_type: Option<&'static str>,
+    /// Only for unix sockets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+impl From<&collect::Socket> for Socket {
+    fn from(socket: &collect::Socket) -> Socket {
+        match socket {
+            collect::Socket::Tcp(entry) => Socket {
+                inode: entry.inode,
+                protocol: "tcp",
+                local_address: Some(entry.local_address.to_string()),
+                remote_address: Some(entry.remote_address.to_string()),
+                state: format!("{:?}", entry.state),
+                socket_type: None,
+                path: None,
+            },
+            collect::Socket::Udp(entry) => Socket {
+                inode: entry.inode,
+                protocol: "udp",
+                local_address: Some(entry.local_address.to_string()),
+                remote_address: Some(entry.remote_address.to_string()),
+                state: format!("{:?}", entry.state),
+                socket_type: None,
+                path: None,
+            },
+            collect::Socket::Unix(entry) => Socket {
+                inode: entry.inode,
+                protocol: "unix",
+                local_address: None,
+                remote_address: None,
+                state: format!("{:?}", entry.state),
+                socket_type: Some(match entry.socket_type as i32 {
+                    libc::SOCK_STREAM => "stream",
+                    libc::SOCK_DGRAM => "dgram",
+                    libc::SOCK_SEQPACKET => "seqpacket",
+                    _ => "other",
+                }),
+                path: entry.path.as_ref().map(|p| p.display().to_string()),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Map {
+    pub start: u64,
+    pub end: u64,
+    pub perms: String,
+    pub offset: u64,
+    pub dev: String,
+    pub inode: u64,
+    /// One of "path", "heap", "stack", "vdso", "vvar", "vsyscall", "anonymous", or "other"
+    pub kind: &'static str,
+    pub path: Option<String>,
+    /// Fields from `/proc/<pid>/smaps`, with sizes converted to bytes
+    pub smaps: Option<BTreeMap<String, u64>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct File {
+    pub fd: i32,
+    /// The permissions of the fd link, like "rw-"
+    pub mode: String,
+    /// One of "path", "pipe", "socket", "anon_inode", "memfd", or "other"
+    pub kind: &'static str,
+    pub target: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock: Option<String>,
+    /// For pipes, the process on the other end of the pipe
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pipe_peer: Option<PipePeer>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socket: Option<Socket>,
+    /// The ID of the mount that the file lives on, as in the `mounts` section
+    pub mnt_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PipePeer {
+    pub pid: i32,
+    pub comm: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Limit {
+    pub name: &'static str,
+    /// `null` means unlimited
+    pub soft: Option<u64>,
+    pub hard: Option<u64>,
+    pub units: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TreeEntry {
+    pub pid: i32,
+    pub ppid: i32,
+    pub cmdline: String,
+    pub children: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CGroup {
+    pub hierarchy: u32,
+    pub controllers: Vec<String>,
+    pub path: String,
+    /// The mount point of this v1 hierarchy, if procdump knows about it
+    pub mount_point: Option<String>,
+    /// Contents of some of the files in the cgroup directory, keyed by filename
+    pub files: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Namespaces {
+    pub namespaces: Vec<Namespace>,
+    /// The PID in each nested PID namespace, outermost first
+    pub nspid: Option<Vec<i32>>,
+    pub nstgid: Option<Vec<i32>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Namespace {
+    pub kind: &'static str,
+    pub inode: u64,
+    /// `null` if PID 1 couldn't be read
+    pub differs_from_init: Option<bool>,
+    /// The PIDs of the other processes in this namespace
+    pub shared_with: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Security {
+    pub uids: Ids,
+    pub gids: Ids,
+    pub groups: Vec<Id>,
+    /// Keyed by the name of the line in `status`, like `CapEff`
+    pub capabilities: BTreeMap<&'static str, Capabilities>,
+    /// `disabled`, `strict`, or `filter`
+    pub seccomp: Option<&'static str>,
+    pub no_new_privs: Option<bool>,
+    pub lsm_label: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Ids {
+    pub real: Id,
+    pub effective: Id,
+    pub saved: Id,
+    pub fs: Id,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Id {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Capabilities {
+    /// The mask in hex, as it appears in `status`
+    pub mask: String,
+    pub names: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Signals {
+    /// Signals sent to the whole process that haven't been delivered yet
+    pub shared_pending: Vec<String>,
+    pub ignored: Vec<String>,
+    pub caught: Vec<String>,
+    pub threads: Vec<ThreadSignals>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ThreadSignals {
+    pub tid: i32,
+    pub comm: String,
+    pub pending: Vec<String>,
+    pub blocked: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Mount {
+    pub mnt_id: i32,
+    pub parent_id: i32,
+    pub mount_point: String,
+    /// The directory of the filesystem that's mounted, which is `/` unless it's a bind mount
+    pub root: String,
+    pub source: Option<String>,
+    pub fs_type: String,
+    /// Like `rw,nosuid,relatime`
+    pub options: String,
+    /// Like `shared:1`, or `private`
+    pub propagation: String,
+    /// `null` if PID 1's mounts couldn't be read
+    pub differs_from_init: Option<bool>,
+    pub open_files: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Io {
+    pub rchar: u64,
+    pub wchar: u64,
+    pub syscr: u64,
+    pub syscw: u64,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    pub cancelled_write_bytes: u64,
+    pub rates: IoRates,
+}
+
+/// Rates per second, measured between the last two samples
+#[derive(Debug, Serialize)]
+pub struct IoRates {
+    pub read_bytes: f32,
+    pub write_bytes: f32,
+    pub read_ops: f32,
+    pub write_ops: f32,
+    pub disk_read_bytes: f32,
+    pub disk_write_bytes: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Task {
+    pub tid: i32,
+    pub comm: String,
+    pub state: char,
+    /// CPU time in clock ticks
+    pub utime: u64,
+    pub stime: u64,
+    /// The kernel function the thread is waiting in, or `None` while it's running
+    pub wchan: Option<String>,
+    /// The kernel stack, innermost frame first, if it could be read
+    pub stack: Option<Vec<String>>,
+}
+
+/// Something that changed while procdump was watching, from the Events tab
+#[derive(Debug, Serialize)]
+pub struct Event {
+    /// When the change was seen, in RFC 3339 format
+    pub time: String,
+    pub kind: String,
+    pub message: String,
+}
+
+/// Converts environment variables, replacing invalid UTF-8
+pub fn env(env: &HashMap<OsString, OsString>) -> BTreeMap<String, String> {
+    env.iter()
+        .map(|(k, v)| (k.to_string_lossy().into_owned(), v.to_string_lossy().into_owned()))
+        .collect()
+}
+
+pub fn maps(mappings: &collect::Mappings) -> Vec<Map> {
+    mappings
+        .regions
+        .iter()
+        .map(|map| {
+            let (kind, path) = match &map.pathname {
+                MMapPath::Path(path) => ("path", Some(path.display().to_string())),
+                MMapPath::Heap => ("heap", None),
+                MMapPath::Stack => ("stack", None),
+                MMapPath::Vdso => ("vdso", None),
+                MMapPath::Vvar => ("vvar", None),
+                MMapPath::Vsyscall => ("vsyscall", None),
+                MMapPath::Anonymous => ("anonymous", None),
+                p => ("other", Some(format!("{p:?}"))),
+            };
+            Map {
+                start: map.address.0,
+                end: map.address.1,
+                perms: map.perms.as_str(),
+                offset: map.offset,
+                dev: format!("{:02x}:{:02x}", map.dev.0, map.dev.1),
+                inode: map.inode,
+                kind,
+                path,
+                smaps: mappings
+                    .detailed
+                    .then(|| map.extension.map.iter().map(|(k, v)| (k.clone(), *v)).collect()),
+            }
+        })
+        .collect()
+}
+
+impl From<&collect::OpenFile> for File {
+    fn from(fd: &collect::OpenFile) -> File {
+        let mut file = File {
+            fd: fd.fd,
+            mode: [
+                (FDPermissions::READ, 'r'),
+                (FDPermissions::WRITE, 'w'),
+                (FDPermissions::EXECUTE, 'x'),
+            ]
+            .iter()
+            .map(|(perm, c)| if fd.mode.contains(*perm) { *c } else { '-' })
+            .collect(),
+            kind: "other",
+            target: String::new(),
+            lock: fd
+                .lock
+                .as_ref()
+                .map(|lock| format!("{:?} {:?} {:?}", lock.lock_type, lock.mode, lock.kind)),
+            pipe_peer: fd.pipe_peer.as_ref().map(|p| PipePeer {
+                pid: p.pid,
+                comm: p.cmdline.clone(),
+            }),
+            socket: fd.socket.as_ref().map(Socket::from),
+            mnt_id: fd.mnt_id,
+        };
+        match &fd.target {
+            FDTarget::Path(path) => {
+                file.kind = "path";
+                file.target = path.display().to_string();
+            }
+            FDTarget::Pipe(inode) => {
+                file.kind = "pipe";
+                file.target = inode.to_string();
+            }
+            FDTarget::Socket(inode) => {
+                file.kind = "socket";
+                file.target = inode.to_string();
+            }
+            FDTarget::AnonInode(name) => {
+                file.kind = "anon_inode";
+                file.target = name.clone();
+            }
+            FDTarget::MemFD(name) => {
+                file.kind = "memfd";
+                file.target = name.clone();
+            }
+            x => file.target = format!("{x:?}"),
+        }
+        file
+    }
+}
+
+pub fn limits(limits: &Limits) -> Vec<Limit> {
+    let value = |v: &LimitValue| match v {
+        LimitValue::Unlimited => None,
+        LimitValue::Value(v) => Some(*v),
+    };
+    collect::named_limits(limits)
+        .into_iter()
+        .map(|(name, limit, units)| Limit {
+            name,
+            soft: value(&limit.soft_limit),
+            hard: value(&limit.hard_limit),
+            units,
+        })
+        .collect()
+}
+
+impl From<&ProcessTreeEntry> for TreeEntry {
+    fn from(entry: &ProcessTreeEntry) -> TreeEntry {
+        TreeEntry {
+            pid: entry.pid,
+            ppid: entry.ppid,
+            cmdline: entry.cmdline.clone(),
+            children: entry.children.clone(),
+        }
+    }
+}
+
+impl From<&collect::CGroupInfo> for CGroup {
+    fn from(cg: &collect::CGroupInfo) -> CGroup {
+        CGroup {
+            hierarchy: cg.hierarchy,
+            controllers: cg.controllers.clone(),
+            path: cg.pathname.clone(),
+            mount_point: cg.mount_point.as_ref().map(|p| p.display().to_string()),
+            files: cg.files.iter().map(|(k, v)| (k.to_string(), v.clone())).collect(),
+        }
+    }
+}
+
+impl From<&collect::ProcessNamespaces> for Namespaces {
+    fn from(ns: &collect::ProcessNamespaces) -> Namespaces {
+        Namespaces {
+            namespaces: ns
+                .namespaces
+                .iter()
+                .map(|info| Namespace {
+                    kind: info.kind,
+                    inode: info.inode,
+                    differs_from_init: info.differs_from_init,
+                    shared_with: info.shared_with.iter().map(|m| m.pid).collect(),
+                })
+                .collect(),
+            nspid: ns.nspid.clone(),
+            nstgid: ns.nstgid.clone(),
+        }
+    }
+}
+
+impl From<&collect::SecurityInfo> for Security {
+    fn from(info: &collect::SecurityInfo) -> Security {
+        let id = |id: &collect::NamedId| Id {
+            id: id.id,
+            name: id.name.clone(),
+        };
+        let ids = |ids: &[collect::NamedId; 4]| Ids {
+            real: id(&ids[0]),
+            effective: id(&ids[1]),
+            saved: id(&ids[2]),
+            fs: id(&ids[3]),
+        };
+        Security {
+            uids: ids(&info.uids),
+            gids: ids(&info.gids),
+            groups: info.groups.iter().map(id).collect(),
+            capabilities: collect::named_capability_sets(info)
+                .into_iter()
+                .filter_map(|(name, mask)| {
+                    let mask = mask?;
+                    Some((
+                        name,
+                        Capabilities {
+                            mask: format!("{mask:016x}"),
+                            names: collect::capability_names(mask).into_iter().map(String::from).collect(),
+                        },
+                    ))
+                })
+                .collect(),
+            seccomp: info.seccomp.map(collect::seccomp_mode),
+            no_new_privs: info.no_new_privs,
+            lsm_label: info.lsm_label.clone(),
+        }
+    }
+}
+
+impl From<&collect::ProcessSignals> for Signals {
+    fn from(signals: &collect::ProcessSignals) -> Signals {
+        Signals {
+            shared_pending: collect::signal_names(signals.shared_pending),
+            ignored: collect::signal_names(signals.ignored),
+            caught: collect::signal_names(signals.caught),
+            threads: signals
+                .threads
+                .iter()
+                .map(|t| ThreadSignals {
+                    tid: t.tid,
+                    comm: t.comm.clone(),
+                    pending: collect::signal_names(t.pending),
+                    blocked: collect::signal_names(t.blocked),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&collect::MountEntry> for Mount {
+    fn from(mount: &collect::MountEntry) -> Mount {
+        let info = &mount.info;
+        Mount {
+            mnt_id: info.mnt_id,
+            parent_id: info.pid,
+            mount_point: info.mount_point.display().to_string(),
+            root: info.root.clone(),
+            source: info.mount_source.clone(),
+            fs_type: info.fs_type.clone(),
+            options: collect::mount_options(info),
+            propagation: collect::mount_propagation(info),
+            differs_from_init: mount.differs_from_init,
+            open_files: mount.open_files,
+        }
+    }
+}
+
+impl From<&collect::IoStats> for Io {
+    fn from(stats: &collect::IoStats) -> Io {
+        let io = &stats.io;
+        let rates = &stats.rates;
+        Io {
+            rchar: io.rchar,
+            wchar: io.wchar,
+            syscr: io.syscr,
+            syscw: io.syscw,
+            read_bytes: io.read_bytes,
+            write_bytes: io.write_bytes,
+            cancelled_write_bytes: io.cancelled_write_bytes,
+            rates: IoRates {
+                read_bytes: rates.read_bytes,
+                write_bytes: rates.write_bytes,
+                read_ops: rates.read_ops,
+                write_ops: rates.write_ops,
+                disk_read_bytes: rates.disk_read_bytes,
+                disk_write_bytes: rates.disk_write_bytes,
+            },
+        }
+    }
+}
+
+impl From<&collect::TaskInfo> for Task {
+    fn from(task: &collect::TaskInfo) -> Task {
+        Task {
+            tid: task.tid,
+            comm: task.stat.comm.clone(),
+            state: task.stat.state,
+            utime: task.stat.utime,
+            stime: task.stat.stime,
+            wchan: task.wchan.clone(),
+            stack: task.stack.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Section;
+
+    #[test]
+    fn test_section() {
+        let ok: Section<u32> = Ok::<_, String>(5).into();
+        assert_eq!(serde_json::to_string(&ok).unwrap(), r#"{"data":5}"#);
+
+        let err: Section<u32> = Err::<u32, _>("Permission Denied").into();
+        assert_eq!(serde_json::to_string(&err).unwrap(), r#"{"error":"Permission Denied"}"#);
+    }
+}
@@ -0,0 +1,18 @@
+//! The data collection half of procdump.
+//!
+//! The `procdump` binary is a terminal UI built on top of this library.  Each
+//! [`Collector`](collect::Collector) reads one kind of information about a process (its
+//! environment, its open files, its memory maps, ...) and returns plain data, which can then be
+//! rendered, exported as JSON, or used by other tools.
+
+pub mod baseline;
+pub mod capture;
+pub mod collect;
+pub mod coredump;
+pub mod json;
+pub mod pidfd;
+pub mod record;
+pub mod select;
+pub mod source;
+pub mod trigger;
+pub mod util;
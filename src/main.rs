@@ -1,10 +1,11 @@
-use std::time::{Duration, Instant};
+use std::sync::Arc;
+use std::time::Duration;
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use crossterm::execute;
 use crossterm::terminal::{ClearType, EnterAlternateScreen, LeaveAlternateScreen};
 use procfs::process::{self, Process};
-use procfs::WithSystemInfo;
+use procfs::{ProcResult, WithSystemInfo};
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::*;
 use ratatui::terminal::{Frame, Terminal};
@@ -16,10 +17,22 @@ use ratatui::{
 
 // pub const ERROR_STYLE: Style = Style::default().fg(Color::Red).bg(Color::Reset);
 
-mod util;
+mod cli;
+use anyhow::Context;
+use procdump::collect::{Change, ChangeKind, StatDelta};
+use procdump::json;
+use procdump::pidfd::PidFd;
+use procdump::record::{Recorder, Replay};
+use procdump::select::{self, Filter, NamePattern};
+use procdump::source::{DataSource, FixtureSource, LiveSource, ProcessId};
+use procdump::util::*;
+use ui::events::{Event, Events};
+use ui::replay::{ReplayAction, ReplayControl};
 use ui::widgets::AppWidget;
-use util::*;
+use watch::Watcher;
+mod report;
 mod ui;
+mod watch;
 
 use std::fmt::Debug;
 
@@ -41,7 +54,6 @@ pub fn set_panic_handler() {
 
         // log this panic to disk:
         if let Ok(mut file) = std::fs::OpenOptions::new()
-            .write(true)
             .append(true)
             .create(true)
             .truncate(false)
@@ -68,119 +80,72 @@ pub fn set_panic_handler() {
 }
 
 struct TabState<'a> {
-    pub labels: &'a [&'a str],
+    /// The label of each tab, and the key (with shift) that selects it
+    tabs: &'a [(&'a str, char)],
     current_idx: usize,
 }
 
 impl<'a> TabState<'a> {
-    fn new(labels: &'a [&'a str]) -> TabState<'a> {
-        TabState { labels, current_idx: 0 }
+    fn new(tabs: &'a [(&'a str, char)]) -> TabState<'a> {
+        TabState { tabs, current_idx: 0 }
+    }
+    fn labels(&self) -> impl Iterator<Item = &'a str> {
+        self.tabs.iter().map(|(label, _)| *label)
     }
     fn current(&self) -> usize {
         self.current_idx
     }
     fn current_label(&self) -> &'a str {
-        self.labels[self.current_idx]
+        self.tabs[self.current_idx].0
     }
     fn select_next(&mut self) {
-        self.current_idx = (self.current_idx + 1) % self.labels.len();
+        self.current_idx = (self.current_idx + 1) % self.tabs.len();
     }
     fn select_prev(&mut self) {
         if self.current_idx == 0 {
-            self.current_idx = self.labels.len() - 1;
+            self.current_idx = self.tabs.len() - 1;
         } else {
             self.current_idx -= 1;
         }
     }
     fn select_by_char(&mut self, c: char) -> ui::InputResult {
-        for (idx, label) in self.labels.iter().enumerate() {
-            if label.starts_with(c) {
+        match self.tabs.iter().position(|(_, key)| *key == c) {
+            Some(idx) => {
                 self.current_idx = idx;
-                return ui::InputResult::NeedsRedraw;
+                ui::InputResult::NeedsRedraw
             }
+            None => ui::InputResult::None,
         }
-        ui::InputResult::None
-    }
-}
-
-struct StatDelta<T> {
-    tps: u64,
-    old: T,
-    old_when: Instant,
-    new: T,
-    new_when: Instant,
-}
-
-impl StatDelta<procfs::process::Io> {
-    fn new(proc: &Process) -> anyhow::Result<StatDelta<procfs::process::Io>> {
-        let s = proc.io()?;
-        let now = Instant::now();
-        Ok(StatDelta {
-            old: s,
-            new: s,
-            old_when: now,
-            new_when: now,
-            tps: procfs::ticks_per_second(),
-        })
-    }
-    fn update(&mut self, proc: &Process) {
-        if let Ok(io) = proc.io() {
-            std::mem::swap(&mut self.old, &mut self.new);
-            self.old_when = self.new_when;
-            self.new = io;
-            self.new_when = Instant::now();
-        }
-    }
-}
-
-impl StatDelta<procfs::process::Stat> {
-    fn new(proc: &Process) -> StatDelta<procfs::process::Stat> {
-        let s = proc.stat().unwrap();
-        let now = Instant::now();
-        StatDelta {
-            old: s.clone(),
-            new: s,
-            old_when: now,
-            new_when: now,
-            tps: procfs::ticks_per_second(),
-        }
-    }
-    fn update(&mut self, proc: &Process) {
-        if let Ok(new_stat) = proc.stat() {
-            std::mem::swap(&mut self.old, &mut self.new);
-            self.old_when = self.new_when;
-            self.new = new_stat;
-            self.new_when = Instant::now();
-        }
-    }
-    fn cpu_percentage(&self) -> f32 {
-        let d = self.duration();
-        if d < Duration::from_millis(100) {
-            return 0.0;
-        }
-        let cputime_delta =
-            ((self.new.utime - self.old.utime) + (self.new.stime - self.old.stime)) as f32 / self.tps as f32;
-        let usage = cputime_delta / (d.as_millis() as f32 / 1000.0);
-
-        usage * 100.0
     }
-}
-
-impl<T> StatDelta<T> {
-    fn latest(&self) -> &T {
-        &self.new
-    }
-    fn previous(&self) -> &T {
-        &self.old
-    }
-    fn duration(&self) -> Duration {
-        self.new_when - self.old_when
+    /// The label of each tab, with the letter of its key underlined
+    fn titles(&self) -> Vec<Line<'a>> {
+        self.tabs
+            .iter()
+            .map(
+                |(label, key)| match label.find(*key).or_else(|| label.find(key.to_ascii_lowercase())) {
+                    Some(pos) => {
+                        let end = pos + key.len_utf8();
+                        Line::from(vec![
+                            Span::raw(&label[..pos]),
+                            Span::styled(&label[pos..end], Style::default().add_modifier(Modifier::UNDERLINED)),
+                            Span::raw(&label[end..]),
+                        ])
+                    }
+                    None => Line::from(*label),
+                },
+            )
+            .collect()
     }
 }
 
 struct SparklineData {
     data: Vec<u64>,
     max_len: usize,
+    /// How many values have ever been pushed, so that markers stay with their values as old ones
+    /// are dropped
+    pushed: usize,
+    /// Where something happened (like a restart), as the number of values pushed before it
+    markers: Vec<usize>,
 }
 
 impl SparklineData {
@@ -189,24 +154,55 @@ impl SparklineData {
         SparklineData {
             data: Vec::with_capacity(max_len),
             max_len,
+            pushed: 0,
+            markers: Vec::new(),
         }
     }
     fn push(&mut self, val: u64) {
         self.data.push(val);
+        self.pushed += 1;
         if self.data.len() > self.max_len {
             self.data.remove(0);
+            let first = self.pushed - self.data.len();
+            self.markers.retain(|&m| m >= first);
         }
     }
+    /// Puts a marker before the next value
+    fn mark(&mut self) {
+        self.markers.push(self.pushed);
+    }
 
     fn as_slice(&self) -> &[u64] {
         //let s = std::cmp::max(0, self.data.len() as i32 - num_elems as i32) as usize;
         self.data.as_slice()
     }
+
+    /// Draws the markers over a sparkline that was drawn in `area`, starting from `as_slice()[start]`
+    fn draw_markers(&self, f: &mut Frame, area: Rect, start: usize) {
+        let first = self.pushed - self.data.len() + start;
+        for &m in self.markers.iter().filter(|&&m| m >= first) {
+            let x = area.x + (m - first).min(u16::MAX as usize) as u16;
+            if x >= area.right() {
+                continue;
+            }
+            for y in area.top()..area.bottom() {
+                f.buffer_mut()
+                    .get_mut(x, y)
+                    .set_symbol("\u{250A}")
+                    .set_fg(Color::Yellow);
+            }
+        }
+    }
 }
 
 pub struct App<'a> {
+    source: Arc<dyn DataSource>,
     tps: u64,
     proc: Process,
+    /// Which process `proc` is, so that a new process with the same PID isn't mistaken for it
+    id: ProcessId,
+    /// What it's running, to notice when it calls `execve`
+    image: ExecImage,
     proc_stat: process::Stat,
     env_widget: ui::widgets::EnvWidget,
     net_widget: ui::widgets::NetWidget,
@@ -216,68 +212,202 @@ pub struct App<'a> {
     limit_widget: ui::widgets::LimitWidget,
     tree_widget: ui::widgets::TreeWidget,
     cgroup_widget: ui::widgets::CGroupWidget,
+    ns_widget: ui::widgets::NamespacesWidget,
+    security_widget: ui::widgets::SecurityWidget,
+    signals_widget: ui::widgets::SignalsWidget,
+    mounts_widget: ui::widgets::MountsWidget,
     io_widget: ui::widgets::IOWidget,
     task_widget: ui::widgets::TaskWidget,
+    events_widget: ui::widgets::EventsWidget,
     tab: TabState<'a>,
     stat_d: StatDelta<procfs::process::Stat>,
     cpu_spark: SparklineData,
+    /// Records every tick, for `--record`
+    recorder: Option<Recorder>,
+    /// Shown on the top line, after the command line
+    status: Option<String>,
+    /// The time between refreshes
+    interval: Duration,
+    /// Set when updates are paused, so that a fast-changing tab can be read
+    frozen: bool,
+    /// The last command line and status that could be read, so that the header still has
+    /// something to show once the process is gone
+    cmdline: Option<Vec<String>>,
+    proc_status: Option<process::Status>,
+    /// How the process ended, if it was started by procdump
+    exit_status: Option<std::process::ExitStatus>,
+    /// When the process was seen to exit.  From then on, everything on screen is the last data that
+    /// was collected
+    died_at: Option<chrono::DateTime<chrono::Local>>,
+    /// The highest resident memory seen, in bytes
+    peak_rss: u64,
+    /// Things that happened to the process, like being restarted, shown under the tabs
+    log: Vec<(chrono::DateTime<chrono::Local>, String)>,
+    /// What the tabs showed when `b` was last pressed
+    baseline: Option<ui::baseline::Baseline>,
+    /// Set when the tabs show how they differ from the baseline, instead of their usual contents
+    show_diff: bool,
+    diff_view: ui::baseline::DiffView,
+}
+
+/// The highest resident memory that `status` knows of, in bytes
+fn peak_rss(status: &process::Status) -> u64 {
+    status.vmhwm.or(status.vmrss).unwrap_or(0) * 1024
 }
 
 impl<'a> App<'a> {
-    fn new(proc: Process) -> App<'a> {
-        App {
+    fn new(source: Arc<dyn DataSource>, proc: Process) -> ProcResult<App<'a>> {
+        let tps = source.system_info().ticks_per_second();
+        let proc_stat = proc.stat()?;
+        let proc_status = proc.status().ok();
+        let files_widget = ui::widgets::FilesWidget::new(&proc, source.clone());
+        Ok(App {
+            id: ProcessId {
+                pid: proc.pid,
+                starttime: proc_stat.starttime,
+            },
             env_widget: ui::widgets::EnvWidget::new(&proc),
             net_widget: ui::widgets::NetWidget::new(&proc),
             maps_widget: ui::widgets::MapsWidget::new(&proc),
             mem_widget: ui::widgets::MemWidget::new(&proc),
-            files_widget: ui::widgets::FilesWidget::new(&proc),
+            mounts_widget: ui::widgets::MountsWidget::new(&proc, source.clone(), files_widget.files()),
+            files_widget,
             limit_widget: ui::widgets::LimitWidget::new(&proc),
-            tree_widget: ui::widgets::TreeWidget::new(&proc),
-            cgroup_widget: ui::widgets::CGroupWidget::new(&proc),
+            tree_widget: ui::widgets::TreeWidget::new(&proc, source.clone()),
+            cgroup_widget: ui::widgets::CGroupWidget::new(&proc, &*source),
+            ns_widget: ui::widgets::NamespacesWidget::new(&proc, source.clone()),
+            security_widget: ui::widgets::SecurityWidget::new(&proc),
+            signals_widget: ui::widgets::SignalsWidget::new(&proc, &*source),
             io_widget: ui::widgets::IOWidget::new(&proc),
-            task_widget: ui::widgets::TaskWidget::new(&proc),
-            tps: procfs::ticks_per_second(),
-            stat_d: StatDelta::<procfs::process::Stat>::new(&proc),
+            task_widget: ui::widgets::TaskWidget::new(&proc, tps),
+            events_widget: ui::widgets::EventsWidget::new(),
+            tps,
+            stat_d: StatDelta::from_sample(proc_stat.clone(), source.now(), tps),
             tab: TabState::new(&[
-                ui::widgets::EnvWidget::TITLE,
-                ui::widgets::NetWidget::TITLE,
-                ui::widgets::MapsWidget::TITLE,
-                ui::widgets::MemWidget::TITLE,
-                ui::widgets::FilesWidget::TITLE,
-                ui::widgets::LimitWidget::TITLE,
-                ui::widgets::TreeWidget::TITLE,
-                ui::widgets::CGroupWidget::TITLE,
-                ui::widgets::IOWidget::TITLE,
-                ui::widgets::TaskWidget::TITLE,
+                (ui::widgets::EnvWidget::TITLE, 'E'),
+                (ui::widgets::NetWidget::TITLE, 'N'),
+                (ui::widgets::MapsWidget::TITLE, 'M'),
+                (ui::widgets::MemWidget::TITLE, 'R'),
+                (ui::widgets::FilesWidget::TITLE, 'F'),
+                (ui::widgets::LimitWidget::TITLE, 'L'),
+                (ui::widgets::TreeWidget::TITLE, 'T'),
+                (ui::widgets::CGroupWidget::TITLE, 'C'),
+                (ui::widgets::NamespacesWidget::TITLE, 'A'),
+                (ui::widgets::SecurityWidget::TITLE, 'S'),
+                (ui::widgets::SignalsWidget::TITLE, 'G'),
+                (ui::widgets::MountsWidget::TITLE, 'U'),
+                (ui::widgets::IOWidget::TITLE, 'I'),
+                (ui::widgets::TaskWidget::TITLE, 'K'),
+                (ui::widgets::EventsWidget::TITLE, 'V'),
             ]),
             cpu_spark: SparklineData::new(),
-            proc_stat: proc.stat().unwrap(),
+            proc_stat,
+            image: ExecImage::of(&proc),
+            cmdline: proc.cmdline().ok(),
+            peak_rss: proc_status.as_ref().map_or(0, peak_rss),
+            proc_status,
             proc,
-        }
+            source,
+            recorder: None,
+            status: None,
+            interval: ui::DEFAULT_INTERVAL,
+            frozen: false,
+            exit_status: None,
+            died_at: None,
+            log: Vec::new(),
+            baseline: None,
+            show_diff: false,
+            diff_view: ui::baseline::DiffView::new(),
+        })
     }
 
     /// Called when we need to switch to a new process
-    fn switch_to(&mut self, new_pid: i32) {
-        if let Ok(proc) = Process::new(new_pid) {
-            self.env_widget = ui::widgets::EnvWidget::new(&proc);
-            self.net_widget = ui::widgets::NetWidget::new(&proc);
-            self.maps_widget = ui::widgets::MapsWidget::new(&proc);
-            self.mem_widget = ui::widgets::MemWidget::new(&proc);
-            self.files_widget = ui::widgets::FilesWidget::new(&proc);
-            self.limit_widget = ui::widgets::LimitWidget::new(&proc);
-            self.tree_widget = ui::widgets::TreeWidget::new(&proc);
-            self.cgroup_widget = ui::widgets::CGroupWidget::new(&proc);
-            self.task_widget = ui::widgets::TaskWidget::new(&proc);
-            self.io_widget = ui::widgets::IOWidget::new(&proc);
-            self.stat_d = StatDelta::<procfs::process::Stat>::new(&proc);
+    fn switch_to(&mut self, id: ProcessId) {
+        self.attach(id, false);
+    }
+
+    /// For `--follow`: moves over to the process that took the place of this one.  Unlike
+    /// `switch_to`, the CPU and IO graphs go on where they left off, with a marker at the switch.
+    /// Returns false if the new process is already gone
+    fn follow_to(&mut self, id: ProcessId) -> bool {
+        self.attach(id, true)
+    }
+
+    fn attach(&mut self, id: ProcessId, keep_history: bool) -> bool {
+        // the process can be gone by the time it's picked, and its PID might even belong to another
+        // process by then
+        let Ok(proc) = id.open(&*self.source) else {
+            return false;
+        };
+        let Ok(proc_stat) = proc.stat() else {
+            return false;
+        };
+        self.env_widget = ui::widgets::EnvWidget::new(&proc);
+        self.net_widget = ui::widgets::NetWidget::new(&proc);
+        self.maps_widget = ui::widgets::MapsWidget::new(&proc);
+        self.mem_widget = ui::widgets::MemWidget::new(&proc);
+        self.files_widget = ui::widgets::FilesWidget::new(&proc, self.source.clone());
+        self.limit_widget = ui::widgets::LimitWidget::new(&proc);
+        self.tree_widget = ui::widgets::TreeWidget::new(&proc, self.source.clone());
+        self.cgroup_widget = ui::widgets::CGroupWidget::new(&proc, &*self.source);
+        self.ns_widget = ui::widgets::NamespacesWidget::new(&proc, self.source.clone());
+        self.security_widget = ui::widgets::SecurityWidget::new(&proc);
+        self.signals_widget = ui::widgets::SignalsWidget::new(&proc, &*self.source);
+        self.mounts_widget = ui::widgets::MountsWidget::new(&proc, self.source.clone(), self.files_widget.files());
+        self.task_widget = ui::widgets::TaskWidget::new(&proc, self.tps);
+        let io_widget = std::mem::replace(&mut self.io_widget, ui::widgets::IOWidget::new(&proc));
+        self.stat_d = StatDelta::from_sample(proc_stat.clone(), self.source.now(), self.tps);
+        if keep_history {
+            self.io_widget.carry_history(io_widget);
+            self.cpu_spark.mark();
+        } else {
             self.cpu_spark = SparklineData::new();
-            self.proc_stat = proc.stat().unwrap();
-            self.proc = proc;
+            self.events_widget.clear();
+            // a baseline of another process isn't much use.  After an exec or a restart it's still
+            // the same program (or service), so the baseline stays
+            self.baseline = None;
+            self.show_diff = false;
         }
+        self.proc_stat = proc_stat;
+        self.image = ExecImage::of(&proc);
+        self.cmdline = proc.cmdline().ok();
+        self.proc_status = proc.status().ok();
+        self.peak_rss = self.proc_status.as_ref().map_or(0, peak_rss);
+        self.died_at = None;
+        self.id = id;
+        self.proc = proc;
+        true
     }
 
     fn handle_input(&mut self, input: KeyEvent, height: u16) -> ui::InputResult {
+        // while a filter or a signal is being typed in, keys don't switch tabs
+        if self.events_widget.editing() {
+            return self.events_widget.handle_input(input, height);
+        }
+        if self.mounts_widget.editing() {
+            return self.mounts_widget.handle_input(input, height);
+        }
+        if self.signals_widget.editing() {
+            return self.signals_widget.handle_input(input, height);
+        }
+        match input.code {
+            KeyCode::Char('b') => {
+                self.take_baseline();
+                return ui::InputResult::NeedsRedraw;
+            }
+            KeyCode::Char('v') if self.baseline.is_some() => {
+                self.show_diff = !self.show_diff;
+                return ui::InputResult::NeedsRedraw;
+            }
+            _ => {}
+        }
+        let in_diff = self.show_diff
+            && self
+                .baseline
+                .as_ref()
+                .is_some_and(|b| b.get(self.tab.current_label()).is_some());
         let widget_redraw = match self.tab.current_label() {
+            _ if in_diff => self.diff_view.handle_input(input, height),
             ui::widgets::EnvWidget::TITLE => self.env_widget.handle_input(input, height),
             ui::widgets::NetWidget::TITLE => self.net_widget.handle_input(input, height),
             ui::widgets::MapsWidget::TITLE => self.maps_widget.handle_input(input, height),
@@ -285,13 +415,23 @@ impl<'a> App<'a> {
             ui::widgets::FilesWidget::TITLE => self.files_widget.handle_input(input, height),
             ui::widgets::LimitWidget::TITLE => self.limit_widget.handle_input(input, height),
             ui::widgets::CGroupWidget::TITLE => self.cgroup_widget.handle_input(input, height),
+            ui::widgets::NamespacesWidget::TITLE => self.ns_widget.handle_input(input, height),
+            ui::widgets::SecurityWidget::TITLE => self.security_widget.handle_input(input, height),
+            ui::widgets::SignalsWidget::TITLE => self.signals_widget.handle_input(input, height),
+            ui::widgets::MountsWidget::TITLE => self.mounts_widget.handle_input(input, height),
             ui::widgets::IOWidget::TITLE => self.io_widget.handle_input(input, height),
             ui::widgets::TaskWidget::TITLE => self.task_widget.handle_input(input, height),
+            ui::widgets::EventsWidget::TITLE => {
+                if input.code == KeyCode::Char('s') {
+                    self.export_events();
+                    return ui::InputResult::NeedsRedraw;
+                }
+                self.events_widget.handle_input(input, height)
+            }
             ui::widgets::TreeWidget::TITLE => {
                 if input.code == KeyCode::Enter {
-                    let new_pid = self.tree_widget.get_selected_pid();
-                    if new_pid != self.proc_stat.pid {
-                        self.switch_to(new_pid);
+                    if let Some(id) = self.tree_widget.get_selected().filter(|&id| id != self.id) {
+                        self.switch_to(id);
                         return ui::InputResult::NeedsUpdate;
                     }
                 }
@@ -315,41 +455,287 @@ impl<'a> App<'a> {
     }
 
     fn tick(&mut self) {
-        if self.proc.is_alive() {
-            self.env_widget.update(&self.proc);
-            self.net_widget.update(&self.proc);
-            self.maps_widget.update(&self.proc);
-            self.mem_widget.update(&self.proc);
-            self.files_widget.update(&self.proc);
-            self.limit_widget.update(&self.proc);
-            self.tree_widget.update(&self.proc);
-            self.cgroup_widget.update(&self.proc);
-            self.io_widget.update(&self.proc);
-            self.task_widget.update(&self.proc);
-            self.stat_d.update(&self.proc);
+        let now = self.source.now();
+        // this is how the exit is noticed when there's no pidfd to watch (like during a replay).  A
+        // PID that now belongs to another process means that this one is long gone
+        if !self.proc.is_alive() || !self.id.is_current(&*self.source) {
+            self.mark_exited();
+        }
+        if self.died_at.is_none() {
+            let image = ExecImage::of(&self.proc);
+            if self.image.changed(&image) {
+                let old = self.command();
+                // everything that was collected belongs to the old program, so start over, but keep
+                // the history of the graphs
+                if self.attach(self.id, true) {
+                    self.log(format!("exec: {old} \u{2192} {}", self.command()));
+                }
+            }
+            self.env_widget.update(&self.proc, now);
+            self.net_widget.update(&self.proc, now);
+            self.maps_widget.update(&self.proc, now);
+            self.mem_widget.update(&self.proc, now);
+            self.files_widget.update(&self.proc, now);
+            self.limit_widget.update(&self.proc, now);
+            self.tree_widget.update(&self.proc, now);
+            self.cgroup_widget.update(&self.proc, now);
+            self.ns_widget.update(&self.proc, now);
+            self.security_widget.update(&self.proc, now);
+            self.signals_widget.update(&self.proc, now);
+            self.mounts_widget.update(&self.proc, now);
+            self.mounts_widget.set_open_files(self.files_widget.files());
+            self.io_widget.update(&self.proc, now);
+            self.task_widget.update(&self.proc, now);
+            self.stat_d.update(&self.proc, now);
+
+            let changes: Vec<Change> = [
+                self.task_widget.take_changes(),
+                self.files_widget.take_changes(),
+                self.net_widget.take_changes(),
+                self.tree_widget.take_changes(),
+            ]
+            .concat();
+            let when = self.source.wall_clock();
+            for change in changes {
+                self.events_widget.push(when, change);
+            }
 
             let cpu_usage = self.stat_d.cpu_percentage();
             self.cpu_spark.push(cpu_usage.round() as u64);
+
+            self.proc_stat = self.stat_d.latest().clone();
+            // a zombie doesn't have a command line anymore
+            if let Some(cmdline) = self.proc.cmdline().ok().filter(|c| !c.is_empty()) {
+                self.cmdline = Some(cmdline);
+            }
+            if let Ok(status) = self.proc.status() {
+                self.peak_rss = self.peak_rss.max(peak_rss(&status));
+                self.proc_status = Some(status);
+            }
+        }
+    }
+
+    /// Freezes everything at the moment the process was found to have exited.  Until its parent
+    /// reaps it, a zombie still has its CPU times, so they're read one last time for the totals
+    fn mark_exited(&mut self) {
+        if self.died_at.is_some() {
+            return;
+        }
+        self.stat_d.update(&self.proc, self.source.now());
+        self.proc_stat = self.stat_d.latest().clone();
+        let died_at = self.source.wall_clock();
+        self.died_at = Some(died_at);
+        self.events_widget.push(
+            died_at,
+            Change::new(ChangeKind::Process, format!("process {} exited", self.proc.pid)),
+        );
+    }
+
+    /// Once the process has exited, describes how it ended, and the resources it used over its whole
+    /// life.  The first line is when and how it ended, and the second has the totals
+    fn exit_summary(&self) -> Option<[String; 2]> {
+        let died_at = self.died_at?;
+        let mut ended = format!("process {} exited at {}", self.proc_stat.pid, died_at.format("%T"));
+        if let Ok(started) = self.proc_stat.starttime().with_system_info(self.source.system_info()) {
+            if let Ok(ran_for) = (died_at - started).to_std() {
+                ended.push_str(&format!(" after running for {}", fmt_duration(ran_for)));
+            }
+        }
+        if let Some(status) = self.exit_status {
+            ended.push_str(&format!(" ({})", fmt_exit_status(status)));
+        }
+
+        let stat = self.stat_d.latest();
+        let u_time = Duration::from_millis(stat.utime * (1000.0 / self.tps as f32) as u64);
+        let s_time = Duration::from_millis(stat.stime * (1000.0 / self.tps as f32) as u64);
+        let mut totals = vec![format!(
+            "cpu time:{:?} (user:{u_time:?} kernel:{s_time:?})",
+            u_time + s_time
+        )];
+        if self.peak_rss > 0 {
+            totals.push(format!("peak rss:{}", fmt_bytes(self.peak_rss, "B")));
+        }
+        if let Some(io) = self.io_widget.totals() {
+            totals.push(format!(
+                "io read:{} io write:{}",
+                fmt_bytes(io.rchar, "B"),
+                fmt_bytes(io.wchar, "B")
+            ));
+        }
+        Some([ended, totals.join(" ")])
+    }
+
+    /// The command line, or the process name if there isn't one
+    fn command(&self) -> String {
+        match &self.cmdline {
+            Some(cmdline) if !cmdline.is_empty() => cmdline.join(" "),
+            _ => self.proc_stat.comm.clone(),
         }
     }
 
+    /// Adds a line to the event log, which also goes into the Events tab
+    fn log(&mut self, message: String) {
+        let when = self.source.wall_clock();
+        self.events_widget
+            .push(when, Change::new(ChangeKind::Process, message.clone()));
+        self.log.push((when, message));
+    }
+
+    /// Every tab's widget, in the order of the tabs
+    fn widgets(&self) -> [&dyn ui::widgets::AnyWidget; 15] {
+        [
+            &self.env_widget,
+            &self.net_widget,
+            &self.maps_widget,
+            &self.mem_widget,
+            &self.files_widget,
+            &self.limit_widget,
+            &self.tree_widget,
+            &self.cgroup_widget,
+            &self.ns_widget,
+            &self.security_widget,
+            &self.signals_widget,
+            &self.mounts_widget,
+            &self.io_widget,
+            &self.task_widget,
+            &self.events_widget,
+        ]
+    }
+
+    /// What a tab shows, in a form that can be compared with a baseline.  `None` for the tabs that
+    /// can't be compared, or that had an error
+    fn items(&self, tab: &str) -> Option<Vec<procdump::baseline::Item>> {
+        self.widgets().into_iter().find(|widget| widget.title() == tab)?.items()
+    }
+
+    /// True while some text is being typed into a tab, so that keys like `q` don't do their usual thing
+    fn editing(&self) -> bool {
+        self.events_widget.editing() || self.signals_widget.editing() || self.mounts_widget.editing()
+    }
+
+    /// Remembers what every tab shows right now, to compare with later
+    fn take_baseline(&mut self) {
+        let mut baseline = ui::baseline::Baseline::new(self.source.wall_clock());
+        for label in self.tab.labels() {
+            if let Some(items) = self.items(label) {
+                baseline.insert(label, items);
+            }
+        }
+        self.status = Some(format!(
+            "[took a baseline at {}, press v to compare with it] ",
+            baseline.taken_at.format("%T")
+        ));
+        self.baseline = Some(baseline);
+    }
+
+    /// Saves the events that the Events tab shows into a file in the current directory
+    fn export_events(&mut self) {
+        let path = format!(
+            "events.{}.{}.{}.txt",
+            self.proc_stat.comm.replace('/', "_"),
+            self.proc.pid,
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        );
+        self.status = Some(match self.events_widget.export(path.as_ref()) {
+            Ok(n) => format!("[saved {n} events into {path}] "),
+            Err(e) => format!("[unable to write {path}: {e}] "),
+        });
+    }
+
+    /// Writes a sample to the recording, if there is one.  This is separate from `tick` so that
+    /// recording goes on while the display is frozen
+    fn record(&mut self) {
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(e) = recorder.record() {
+                self.status = Some(format!("[recording stopped: {e}] "));
+                self.recorder = None;
+            }
+        }
+    }
+
+    /// Changes the refresh interval by one step (faster if `faster` is set), within the limits
+    fn step_interval(&mut self, faster: bool) {
+        self.interval = ui::step_interval(self.interval, faster);
+    }
+
+    /// Draws the whole screen.  `extra_help` is added to the help text of the current tab.  Returns
+    /// the height of the tab body, which scrolling needs
+    fn draw(&mut self, f: &mut Frame, extra_help: Option<&str>) -> u16 {
+        // vertical layout has 7 sections:
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(0)
+            .constraints(
+                [
+                    Constraint::Length(1),                                              // very top line
+                    Constraint::Length(4 + 2),                                          // top fixed-sized info box
+                    Constraint::Length(if self.died_at.is_some() { 2 + 2 } else { 0 }), // exit banner
+                    Constraint::Length(1 + 2),                                          // tab selector
+                    Constraint::Min(0),                                                 // tab body
+                    Constraint::Length(if self.log.is_empty() {
+                        0
+                    } else {
+                        self.log.len().min(3) as u16 + 2
+                    }), // event log
+                    Constraint::Length(5),                                              // cpu sparkline
+                ]
+                .as_ref(),
+            )
+            .split(f.size());
+
+        let mut help_text = Text::default();
+
+        self.draw_exit_banner(f, chunks[2]);
+        self.draw_tab_selector(f, chunks[3]);
+        self.draw_tab_body(f, chunks[4], &mut help_text);
+        if let Some(extra) = extra_help {
+            help_text.extend(Text::from(extra));
+        }
+        if !self.log.is_empty() {
+            self.draw_log(f, chunks[5]);
+        }
+        self.draw_cpu_spark(f, chunks[6]);
+
+        self.draw_top(f, chunks[0], chunks[1], help_text);
+        chunks[4].height
+    }
+
     fn draw_top(&self, f: &mut Frame, top_area: Rect, area: Rect, help_text: Text) {
         // first first line is the pid and process name
         let mut text = Vec::new();
-        if let Ok(cmdline) = self.proc.cmdline() {
-            let mut i = cmdline.into_iter();
+        if let Some(cmdline) = &self.cmdline {
+            let mut i = cmdline.iter();
             if let Some(exe) = i.next() {
                 text.push(Span::raw("\u{2500} "));
-                text.push(Span::styled(exe, Style::default().fg(Color::Magenta)));
+                text.push(Span::styled(exe.clone(), Style::default().fg(Color::Magenta)));
                 text.push(Span::raw(" "));
             }
             for arg in i {
-                text.push(Span::raw(arg));
+                text.push(Span::raw(arg.clone()));
                 text.push(Span::raw(" "));
             }
         } else {
             text.push(Span::raw(format!("\u{2500} {} ", self.proc_stat.comm)));
         }
+        if self.recorder.is_some() {
+            text.push(Span::styled("[rec] ", Style::default().fg(Color::Red)));
+        }
+        if let Some(status) = &self.status {
+            text.push(Span::styled(status.clone(), Style::default().fg(Color::Yellow)));
+        }
+        let rate = if self.interval < Duration::from_secs(1) {
+            format!("every {}ms", self.interval.as_millis())
+        } else {
+            format!("every {:.1}s", self.interval.as_secs_f32())
+        };
+        if self.frozen {
+            text.push(Span::styled(
+                format!("[paused, {rate}] "),
+                Style::default().fg(Color::Black).bg(Color::Yellow),
+            ));
+        } else {
+            text.push(Span::styled(format!("[{rate}] "), Style::default().fg(Color::Cyan)));
+        }
 
         text.push(Span::raw("\u{2500}".repeat(top_area.width as usize)));
         f.render_widget(Paragraph::new(Line::from(text)), top_area);
@@ -393,20 +779,20 @@ impl<'a> App<'a> {
         text.extend(vec![
             Span::styled("state:", s),
             if self.proc.is_alive() {
-                Span::raw(format!(
-                    "{}\u{00A0}({:?}) ",
-                    self.proc_stat.state,
-                    self.proc_stat.state().unwrap()
-                ))
+                match self.proc_stat.state() {
+                    Ok(state) => Span::raw(format!("{}\u{00A0}({state:?}) ", self.proc_stat.state)),
+                    Err(..) => Span::raw(format!("{} ", self.proc_stat.state)),
+                }
+            } else if let Some(exit_status) = self.exit_status {
+                Span::styled(
+                    format!("X\u{00A0}({}) ", fmt_exit_status(exit_status).replace(' ', "\u{00A0}")),
+                    Style::default().fg(Color::Red),
+                )
             } else {
                 Span::raw("X\u{00A0}(Dead) ".to_string())
             },
             Span::styled("started:", s),
-            if let Ok(dt) = self
-                .proc_stat
-                .starttime()
-                .with_system_info(procfs::current_system_info())
-            {
+            if let Ok(dt) = self.proc_stat.starttime().with_system_info(self.source.system_info()) {
                 Span::raw(format!("{} ", fmt_time(dt)))
             } else {
                 Span::styled("(unknown) ", Style::default().fg(Color::Red).bg(Color::Reset))
@@ -416,8 +802,8 @@ impl<'a> App<'a> {
         // third line:
         // owner:achin(1000) threads:
 
-        let status = self.proc.status();
-        if let Ok(ref status) = status {
+        let status = &self.proc_status;
+        if let Some(status) = status {
             text.extend(vec![
                 Span::styled("owner:", s),
                 Span::raw(format!("{}({}) ", lookup_username(status.ruid), status.ruid)),
@@ -474,7 +860,7 @@ impl<'a> App<'a> {
 
         let mut line: Vec<Span> = Vec::new();
 
-        if let Ok(ref status) = status {
+        if let Some(status) = status {
             // get some memory stats
             if let Some(vmsize) = status.vmsize {
                 line.push(Span::styled("virt:", s));
@@ -501,10 +887,213 @@ impl<'a> App<'a> {
         f.render_widget(widget, chunks[2]);
     }
 
+    /// Writes the header info (the same info that `draw_top` displays) as plain text
+    fn snapshot_top(&self, out: &mut String) -> std::fmt::Result {
+        use std::fmt::Write;
+
+        writeln!(out, "cmdline: {}", self.command())?;
+        writeln!(
+            out,
+            "pid:{} ppid:{} pgrp:{} session:{}",
+            self.proc_stat.pid, self.proc_stat.ppid, self.proc_stat.pgrp, self.proc_stat.session
+        )?;
+        if self.proc.is_alive() {
+            write!(out, "state:{} ", self.proc_stat.state)?;
+            if let Ok(state) = self.proc_stat.state() {
+                write!(out, "({state:?}) ")?;
+            }
+        } else if let Some(exit_status) = self.exit_status {
+            write!(out, "state:X ({}) ", fmt_exit_status(exit_status))?;
+        } else {
+            write!(out, "state:X (Dead) ")?;
+        }
+        match self.proc_stat.starttime().with_system_info(self.source.system_info()) {
+            Ok(dt) => writeln!(out, "started:{}", dt.format("%Y-%m-%d %T"))?,
+            Err(..) => writeln!(out, "started:(unknown)")?,
+        }
+        if let Some([ended, totals]) = self.exit_summary() {
+            writeln!(out, "{ended}\n{totals}")?;
+        }
+        for (when, message) in &self.log {
+            writeln!(out, "{} {message}", when.format("%T"))?;
+        }
+
+        let status = self.proc.status();
+        if let Ok(ref status) = status {
+            write!(
+                out,
+                "owner:{}({}) threads:{} ",
+                lookup_username(status.ruid),
+                status.ruid,
+                status.threads
+            )?;
+        }
+        writeln!(out, "nice:{}", self.proc_stat.nice)?;
+
+        let stat = self.stat_d.latest();
+        let u_time = Duration::from_millis(stat.utime * (1000.0 / self.tps as f32) as u64);
+        let s_time = Duration::from_millis(stat.stime * (1000.0 / self.tps as f32) as u64);
+        writeln!(out, "cpu usage:{:.2}%", self.stat_d.cpu_percentage())?;
+        writeln!(out, "user time:{u_time:?} kernel time:{s_time:?}")?;
+
+        if let Ok(ref status) = status {
+            let mut mem = Vec::new();
+            if let Some(vmsize) = status.vmsize {
+                mem.push(format!("virt:{}", fmt_bytes(vmsize * 1024, "B")));
+            }
+            if let Some(rss) = status.vmrss {
+                mem.push(format!("rss:{}", fmt_bytes(rss * 1024, "B")));
+            }
+            if let (Some(shr), Some(rss)) = (status.rssshmem, status.rssfile) {
+                mem.push(format!("shr:{}", fmt_bytes((shr + rss) * 1024, "B")));
+            }
+            writeln!(out, "{}", mem.join(" "))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the contents of every tab as plain text, one section per tab
+    fn snapshot(&self) -> Result<String, std::fmt::Error> {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        writeln!(out, "=== procdump snapshot ===")?;
+        self.snapshot_top(&mut out)?;
+
+        for label in self.tab.labels() {
+            writeln!(out, "\n=== {label} ===")?;
+            match label {
+                ui::widgets::EnvWidget::TITLE => self.env_widget.snapshot(&mut out)?,
+                ui::widgets::NetWidget::TITLE => self.net_widget.snapshot(&mut out)?,
+                ui::widgets::MapsWidget::TITLE => self.maps_widget.snapshot(&mut out)?,
+                ui::widgets::MemWidget::TITLE => self.mem_widget.snapshot(&mut out)?,
+                ui::widgets::FilesWidget::TITLE => self.files_widget.snapshot(&mut out)?,
+                ui::widgets::LimitWidget::TITLE => self.limit_widget.snapshot(&mut out)?,
+                ui::widgets::TreeWidget::TITLE => self.tree_widget.snapshot(&mut out)?,
+                ui::widgets::CGroupWidget::TITLE => self.cgroup_widget.snapshot(&mut out)?,
+                ui::widgets::NamespacesWidget::TITLE => self.ns_widget.snapshot(&mut out)?,
+                ui::widgets::SecurityWidget::TITLE => self.security_widget.snapshot(&mut out)?,
+                ui::widgets::SignalsWidget::TITLE => self.signals_widget.snapshot(&mut out)?,
+                ui::widgets::MountsWidget::TITLE => self.mounts_widget.snapshot(&mut out)?,
+                ui::widgets::IOWidget::TITLE => self.io_widget.snapshot(&mut out)?,
+                ui::widgets::TaskWidget::TITLE => self.task_widget.snapshot(&mut out)?,
+                ui::widgets::EventsWidget::TITLE => self.events_widget.snapshot(&mut out)?,
+                t => panic!("Unhandled tab {t}"),
+            }
+        }
+
+        // the TUI uses non-breaking spaces to control wrapping, which aren't wanted in plain text
+        Ok(out.replace('\u{00A0}', " "))
+    }
+
+    /// Gathers what goes into a report about the current process
+    fn report<'r>(&'r self, reason: &'r str) -> report::Report<'r> {
+        let mut sparklines = vec![("cpu_percent", self.cpu_spark.as_slice())];
+        sparklines.extend(self.io_widget.sparklines());
+        report::Report {
+            proc: &self.proc,
+            comm: &self.proc_stat.comm,
+            reason,
+            exit_status: self.exit_status,
+            snapshot: self.snapshot().unwrap_or_default(),
+            json: self.json(),
+            cgroups: self.cgroup_widget.groups(),
+            sparklines,
+            interval: self.interval,
+        }
+    }
+
+    /// Collects everything that procdump knows about the process into a JSON document
+    fn json(&self) -> json::Document {
+        let status = self.proc.status();
+        let process = json::ProcessInfo {
+            pid: self.proc_stat.pid,
+            ppid: self.proc_stat.ppid,
+            pgrp: self.proc_stat.pgrp,
+            session: self.proc_stat.session,
+            comm: self.proc_stat.comm.clone(),
+            cmdline: self.proc.cmdline().ok(),
+            state: self.proc_stat.state,
+            alive: self.proc.is_alive(),
+            start_time: self
+                .proc_stat
+                .starttime()
+                .with_system_info(self.source.system_info())
+                .ok()
+                .map(|dt| dt.to_rfc3339()),
+            uid: status.as_ref().ok().map(|s| s.ruid),
+            user: status.as_ref().ok().map(|s| lookup_username(s.ruid)),
+            threads: status.as_ref().ok().map(|s| s.threads),
+            nice: self.proc_stat.nice,
+            vmsize: status.as_ref().ok().and_then(|s| s.vmsize).map(|kb| kb * 1024),
+            vmrss: status.as_ref().ok().and_then(|s| s.vmrss).map(|kb| kb * 1024),
+            shared: status
+                .as_ref()
+                .ok()
+                .and_then(|s| Some((s.rssshmem? + s.rssfile?) * 1024)),
+        };
+
+        let stat = self.stat_d.latest();
+        let cpu = json::CpuInfo {
+            percent: self.stat_d.cpu_percentage(),
+            user_ms: stat.utime * 1000 / self.tps,
+            kernel_ms: stat.stime * 1000 / self.tps,
+        };
+
+        json::Document {
+            schema_version: json::SCHEMA_VERSION,
+            generated_at: chrono::Local::now().to_rfc3339(),
+            pid: self.proc.pid,
+            process: json::Section::data(process),
+            cpu: json::Section::data(cpu),
+            env: self.env_widget.json(),
+            net: self.net_widget.json(),
+            maps: self.maps_widget.json(),
+            memory: self.mem_widget.json(),
+            files: self.files_widget.json(),
+            limits: self.limit_widget.json(),
+            tree: self.tree_widget.json(),
+            cgroups: self.cgroup_widget.json(),
+            namespaces: self.ns_widget.json(),
+            security: self.security_widget.json(),
+            signals: self.signals_widget.json(),
+            mounts: self.mounts_widget.json(),
+            io: self.io_widget.json(),
+            tasks: self.task_widget.json(),
+            events: self.events_widget.json(),
+        }
+    }
+
+    /// Draws the banner that says the process has exited, if it has
+    fn draw_exit_banner(&self, f: &mut Frame, area: Rect) {
+        let Some([ended, totals]) = self.exit_summary() else {
+            return;
+        };
+        let red = Style::default().fg(Color::Red);
+        let widget = Paragraph::new(vec![
+            Line::from(Span::styled(ended, red.add_modifier(Modifier::BOLD))),
+            Line::from(totals),
+        ])
+        .block(Block::default().borders(Borders::ALL).border_style(red));
+        f.render_widget(widget, area);
+    }
     fn draw_tab_selector(&self, f: &mut Frame, area: Rect) {
-        let titles = self.tab.labels.iter().cloned().map(Line::from);
+        let titles = self.tab.titles();
+        let mut block = Block::default().borders(Borders::TOP | Borders::BOTTOM);
+        if let Some(baseline) = self.baseline.as_ref().filter(|_| self.show_diff) {
+            block = block.title(Span::styled(
+                format!(" differences from {} ", baseline.describe(self.source.wall_clock())),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        if let Some(died_at) = self.died_at {
+            block = block.title(Span::styled(
+                format!(" stale: nothing has been updated since {} ", died_at.format("%T")),
+                Style::default().fg(Color::Red),
+            ));
+        }
         let widget = Tabs::new(titles)
-            .block(Block::default().borders(Borders::TOP | Borders::BOTTOM))
+            .block(block)
             // .titles(self.tab.labels)
             .select(self.tab.current())
             .style(Style::default().fg(Color::Cyan))
@@ -519,7 +1108,16 @@ impl<'a> App<'a> {
             .constraints([Constraint::Min(1), Constraint::Length(1)].as_ref())
             .split(area);
 
-        match self.tab.current_label() {
+        let label = self.tab.current_label();
+        if let Some(baseline) = self.baseline.as_ref().filter(|_| self.show_diff) {
+            if let (Some(base), Some(items)) = (baseline.get(label), self.items(label)) {
+                let described = baseline.describe(self.source.wall_clock());
+                self.diff_view.draw(f, chunks[0], &described, base, &items, help_text);
+                self.diff_view.draw_scrollbar(f, chunks[1]);
+                return;
+            }
+        }
+        match label {
             ui::widgets::EnvWidget::TITLE => {
                 self.env_widget.draw(f, chunks[0], help_text);
                 self.env_widget.draw_scrollbar(f, chunks[1]);
@@ -548,6 +1146,21 @@ impl<'a> App<'a> {
             ui::widgets::CGroupWidget::TITLE => {
                 self.cgroup_widget.draw(f, area, help_text);
             }
+            ui::widgets::NamespacesWidget::TITLE => {
+                self.ns_widget.draw(f, chunks[0], help_text);
+                self.ns_widget.draw_scrollbar(f, chunks[1]);
+            }
+            ui::widgets::SecurityWidget::TITLE => {
+                self.security_widget.draw(f, chunks[0], help_text);
+                self.security_widget.draw_scrollbar(f, chunks[1]);
+            }
+            ui::widgets::SignalsWidget::TITLE => {
+                self.signals_widget.draw(f, area, help_text);
+            }
+            ui::widgets::MountsWidget::TITLE => {
+                self.mounts_widget.draw(f, chunks[0], help_text);
+                self.mounts_widget.draw_scrollbar(f, chunks[1]);
+            }
             ui::widgets::IOWidget::TITLE => {
                 self.io_widget.draw(f, area, help_text);
             }
@@ -555,6 +1168,10 @@ impl<'a> App<'a> {
                 self.task_widget.draw(f, area, help_text);
                 self.task_widget.draw_scrollbar(f, chunks[1]);
             }
+            ui::widgets::EventsWidget::TITLE => {
+                self.events_widget.draw(f, chunks[0], help_text);
+                self.events_widget.draw_scrollbar(f, chunks[1]);
+            }
             t => {
                 panic!("Unhandled tab {t}");
             }
@@ -573,9 +1190,300 @@ impl<'a> App<'a> {
             .data(&data[s..])
             .max(100);
         f.render_widget(widget, area);
+        let inner = Rect {
+            y: area.y + 1,
+            height: area.height.saturating_sub(2),
+            ..area
+        };
+        self.cpu_spark.draw_markers(f, inner, s);
+    }
+    /// Draws the most recent lines of the event log
+    fn draw_log(&self, f: &mut Frame, area: Rect) {
+        let shown = area.height.saturating_sub(2) as usize;
+        let lines: Vec<Line> = self.log[self.log.len().saturating_sub(shown)..]
+            .iter()
+            .map(|(when, message)| {
+                Line::from(vec![
+                    Span::styled(format!("{} ", when.format("%T")), Style::default().fg(Color::Yellow)),
+                    Span::raw(message.clone()),
+                ])
+            })
+            .collect();
+        let widget = Paragraph::new(lines).block(
+            Block::default()
+                .title("Events:")
+                .borders(Borders::TOP | Borders::BOTTOM),
+        );
+        f.render_widget(widget, area);
+    }
+}
+
+/// How many frames before the target a replay starts over from when seeking, so that the graphs
+/// have some history to show.  This is as much as a sparkline holds
+const REPLAY_HISTORY: usize = 400;
+
+/// Moves a replay to another frame.  Moving one frame forward is a normal tick, but anything else
+/// rebuilds the app a little before the new frame and plays forward from there
+fn seek_replay(app: &mut App, replay: &mut Replay, pos: usize) -> std::io::Result<()> {
+    if pos == replay.position() + 1 {
+        replay.seek(pos)?;
+        app.tick();
+        return Ok(());
+    }
+    let start = pos.saturating_sub(REPLAY_HISTORY);
+    replay.seek(start)?;
+    replay.reset_clock();
+    app.switch_to(app.id);
+    for p in start + 1..=pos {
+        replay.seek(p)?;
+        app.tick();
+    }
+    Ok(())
+}
+
+/// Collects the info for every tab once, without touching the terminal
+fn collect_once(source: Arc<dyn DataSource>, prc: Process) -> anyhow::Result<App<'static>> {
+    let mut app = App::new(source, prc)?;
+    app.maps_widget.set_want_smaps(true);
+    app.task_widget.set_want_stacks(true);
+    // wait a moment and refresh, so that the CPU and IO rates have something to compare against
+    std::thread::sleep(ui::ONE_SECONDS + Duration::from_millis(100));
+    app.tick();
+    Ok(app)
+}
+
+/// Prints every tab as plain text to stdout
+fn run_snapshot(source: Arc<dyn DataSource>, prc: Process) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let text = collect_once(source, prc)?.snapshot()?;
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(text.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Prints everything procdump knows about the process as a JSON document to stdout
+fn run_json(source: Arc<dyn DataSource>, prc: Process) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let doc = collect_once(source, prc)?.json();
+    let mut stdout = std::io::stdout().lock();
+    serde_json::to_writer_pretty(&mut stdout, &doc)?;
+    writeln!(stdout)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Watches the process without a UI, and writes core dumps and reports whenever the triggers fire.
+/// If procdump started the process, this goes on until it exits, and then exits with its status.  If
+/// watching stops early on an error, that process is killed, rather than left running unwatched
+fn run_watch(
+    source: Arc<dyn DataSource>,
+    prc: Process,
+    mut watcher: Watcher,
+    mut child: Option<std::process::Child>,
+    interval: Duration,
+) -> anyhow::Result<()> {
+    let comm = prc.stat().map(|s| s.comm).unwrap_or_default();
+    println!("Watching process {} ({comm}), {}", prc.pid, watcher.describe());
+    ui::set_refresh_interval(interval);
+    // only reports need everything that the UI collects
+    let mut app = if watcher.wants_reports() {
+        let mut app = App::new(source.clone(), ProcessId::of(&prc)?.open(&*source)?)?;
+        app.interval = interval;
+        Some(app)
+    } else {
+        None
+    };
+
+    let mut watch = || -> anyhow::Result<Option<std::process::ExitStatus>> {
+        let mut exit_status = None;
+        loop {
+            std::thread::sleep(interval);
+            if let Some(c) = &mut child {
+                exit_status = c.try_wait()?;
+            }
+            if let Some(app) = &mut app {
+                app.tick();
+                app.exit_status = exit_status;
+            }
+            if !watcher.done() {
+                for line in watcher.check(&prc, source.now(), app.as_ref()) {
+                    if line.starts_with("error: ") {
+                        eprintln!("{line}");
+                    } else {
+                        println!("{line}");
+                    }
+                }
+            }
+            if !prc.is_alive() {
+                match exit_status {
+                    Some(status) => println!("Process {} {}", prc.pid, fmt_exit_status(status)),
+                    None => println!("Process {} exited", prc.pid),
+                }
+                return Ok(exit_status);
+            }
+            if watcher.done() && child.is_none() {
+                return Ok(None);
+            }
+        }
+    };
+    let exit_status = watch();
+    if exit_status.is_err() {
+        kill_child(&mut child);
+    }
+    if let Some(status) = exit_status? {
+        std::process::exit(exit_code(status));
+    }
+    Ok(())
+}
+
+/// Finds the process to look at, from the PID or one of the selectors on the command line.  If
+/// more than one process matches and `can_pick` is set, the user is asked to pick one; `None` means
+/// they didn't
+fn select_process(opts: &cli::Options, source: &dyn DataSource, can_pick: bool) -> anyhow::Result<Option<Process>> {
+    if let Some(pid) = opts.pid {
+        let prc = source
+            .process(pid)
+            .with_context(|| format!("Unable to read process {pid}"))?;
+        return Ok(Some(prc));
+    }
+    if let Some(path) = &opts.pidfile {
+        let pid =
+            select::read_pidfile(path).with_context(|| format!("Unable to read a PID from {}", path.display()))?;
+        let prc = source
+            .process(pid)
+            .with_context(|| format!("Unable to read process {pid} (from {})", path.display()))?;
+        return Ok(Some(prc));
+    }
+
+    let (filter, wanted) = process_filter(opts)?;
+    if filter.is_empty() {
+        return Ok(Some(source.myself()?));
+    }
+
+    let mut procs = find_processes(opts, source, &filter)?;
+    match procs.len() {
+        0 => anyhow::bail!("No process matches {wanted}"),
+        1 => Ok(procs.pop()),
+        _ if can_pick => {
+            let pids: Vec<i32> = procs.iter().map(|p| p.pid).collect();
+            // the processes that were found are still open, so the one that's picked can't have been
+            // replaced by a new process with the same PID in the meantime
+            let picked = ui::picker::pick(source, &pids)?;
+            Ok(picked.and_then(|pid| procs.into_iter().find(|p| p.pid == pid)))
+        }
+        n => {
+            let list: Vec<String> = procs
+                .iter()
+                .map(|p| match p.cmdline() {
+                    Ok(cmdline) if !cmdline.is_empty() => {
+                        // long command lines would bury the PIDs
+                        let mut cmdline = cmdline.join(" ");
+                        if let Some((idx, _)) = cmdline.char_indices().nth(100) {
+                            cmdline.truncate(idx);
+                            cmdline.push('\u{2026}');
+                        }
+                        format!("  {} {cmdline}", p.pid)
+                    }
+                    _ => format!("  {}", p.pid),
+                })
+                .collect();
+            anyhow::bail!(
+                "{n} processes match {wanted}:\n{}\nPick one by giving its PID",
+                list.join("\n")
+            )
+        }
+    }
+}
+
+/// Turns `--name`, `--unit`, `--user`, and `--cgroup` into a filter, along with a description of what it looks
+/// for (for error messages)
+fn process_filter(opts: &cli::Options) -> anyhow::Result<(Filter, String)> {
+    let mut filter = Filter::default();
+    let mut wanted = Vec::new();
+    if let Some(name) = &opts.name {
+        filter.name = Some(NamePattern::new(name).with_context(|| format!("Invalid --name pattern {name:?}"))?);
+        wanted.push(format!("--name {name}"));
+    }
+    if let Some(unit) = &opts.unit {
+        let unit = select::unit_name(unit);
+        wanted.push(format!("--unit {unit}"));
+        filter.unit = Some(unit);
+    }
+    if let Some(user) = &opts.user {
+        let uid = match user.parse() {
+            Ok(uid) => uid,
+            Err(..) => lookup_uid(user).with_context(|| format!("No such user: {user}"))?,
+        };
+        filter.uid = Some(uid);
+        wanted.push(format!("--user {user}"));
+    }
+    if let Some(cgroup) = &opts.cgroup {
+        filter.cgroup = Some(cgroup.clone());
+        wanted.push(format!("--cgroup {cgroup}"));
+    }
+    Ok((filter, wanted.join(" ")))
+}
+
+/// Returns every process that matches `filter`, except for procdump itself
+fn find_processes(opts: &cli::Options, source: &dyn DataSource, filter: &Filter) -> anyhow::Result<Vec<Process>> {
+    let mut procs = filter.find(source).context("Unable to list processes")?;
+    if opts.proc_root.is_none() {
+        // don't match ourselves (our command line contains the pattern, after all)
+        procs.retain(|p| p.pid != std::process::id() as i32);
+    }
+    Ok(procs)
+}
+
+/// Starts a command for `procdump -- <COMMAND>`.  Its output would scribble over the UI, so it
+/// goes nowhere
+fn spawn_command(command: &[String]) -> anyhow::Result<std::process::Child> {
+    use std::process::{Command, Stdio};
+
+    Command::new(&command[0])
+        .args(&command[1..])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Unable to run {}", command[0]))
+}
+
+/// Reaps the command that procdump started, if it has exited.  Its exit status is shown if it's
+/// the process on screen
+fn check_child(
+    app: &mut App,
+    child: &mut Option<std::process::Child>,
+    status: &mut Option<std::process::ExitStatus>,
+) -> std::io::Result<()> {
+    if let Some(c) = child {
+        if status.is_none() {
+            *status = c.try_wait()?;
+        }
+        let launched = c.id() as i32 == app.proc.pid;
+        app.exit_status = status.filter(|_| launched);
+    }
+    Ok(())
+}
+
+/// Kills and reaps the command that procdump started, when procdump is about to stop early on an
+/// error
+fn kill_child(child: &mut Option<std::process::Child>) {
+    if let Some(c) = child {
+        let _ = c.kill();
+        let _ = c.wait();
     }
 }
 
+/// The exit code that a shell would report for a process that ended with `status`
+fn exit_code(status: std::process::ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+
+    status.code().unwrap_or_else(|| 128 + status.signal().unwrap_or(0))
+}
+
 /// Dedicated input testing mode, to debug terminals that don't report key presses in an expected way
 fn run_keyboard_input_test() -> Result<(), anyhow::Error> {
     use crossterm::event::{read, Event};
@@ -604,110 +1512,336 @@ fn run_keyboard_input_test() -> Result<(), anyhow::Error> {
 }
 
 fn main() -> anyhow::Result<()> {
-    let args: Vec<_> = std::env::args().collect();
+    let opts = cli::Options::parse(std::env::args().skip(1))?;
+
+    if opts.help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
 
-    if args.iter().any(|a| a == "--keytest") {
+    if opts.keytest {
         return run_keyboard_input_test();
     }
 
-    let pid = args.get(1).and_then(|s| s.parse::<i32>().ok());
+    let mut replay = None;
+    let source: Arc<dyn DataSource> = match (&opts.replay, &opts.proc_root) {
+        (Some(path), _) => {
+            let r = Replay::open(path).with_context(|| format!("Unable to replay {}", path.display()))?;
+            let source = r.source();
+            replay = Some(ReplayControl::new(r));
+            source
+        }
+        (None, Some(root)) => {
+            if !root.is_dir() {
+                anyhow::bail!("--proc-root {} is not a directory", root.display());
+            }
+            Arc::new(FixtureSource::new(root))
+        }
+        (None, None) => Arc::new(LiveSource),
+    };
+    let proc_root = opts.proc_root.clone().unwrap_or_else(|| "/proc".into());
+    if let Some(pids) = opts.compare {
+        return ui::compare::run(source, pids, &opts);
+    }
+    if opts.list {
+        return ui::list::run(source, &opts);
+    }
 
-    let prc = if let Some(pid) = pid {
-        procfs::process::Process::new(pid).unwrap()
+    let headless = opts.snapshot || opts.json || opts.capture.is_some() || (!opts.triggers.is_empty() && !opts.ui);
+    let mut child = None;
+    let prc = if opts.command.is_empty() {
+        let Some(prc) = select_process(&opts, &*source, !headless)? else {
+            return Ok(());
+        };
+        prc
     } else {
-        procfs::process::Process::myself().unwrap()
+        let c = spawn_command(&opts.command)?;
+        let prc = source.process(c.id() as i32)?;
+        child = Some(c);
+        prc
+    };
+    // make sure we can actually read this process before going any further
+    prc.stat()
+        .with_context(|| format!("Unable to read process {}", prc.pid))?;
+
+    if let Some(dest) = &opts.capture {
+        let capture = procdump::capture::capture(&proc_root, prc.pid, dest)
+            .with_context(|| format!("Unable to capture process {} into {}", prc.pid, dest.display()))?;
+        for (path, e) in &capture.errors {
+            eprintln!("Warning: unable to read {}: {e}", path.display());
+        }
+        println!(
+            "Captured process {} into {} ({} files)",
+            prc.pid,
+            dest.display(),
+            capture.copied
+        );
+        return Ok(());
+    }
+    if opts.snapshot {
+        return run_snapshot(source, prc);
+    }
+    if opts.json {
+        return run_json(source, prc);
+    }
+    let interval = opts.interval.unwrap_or(ui::DEFAULT_INTERVAL);
+    let mut watcher = match Watcher::new(&opts, &*source, &prc) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            kill_child(&mut child);
+            return Err(e);
+        }
+    };
+    if !opts.ui {
+        if let Some(watcher) = watcher {
+            return run_watch(source, prc, watcher, child, interval);
+        }
+    }
+
+    let recorder = match &opts.record {
+        Some(path) => Some(
+            Recorder::create(path, &proc_root, prc.pid, source.system_info())
+                .with_context(|| format!("Unable to record into {}", path.display()))?,
+        ),
+        None => None,
     };
 
     set_panic_handler();
 
-    let events = util::Events::new();
+    // widgets refresh at the pace of the recording during a replay, no matter how fast it's played
+    if replay.is_none() {
+        ui::set_refresh_interval(interval);
+    }
+    let events = Events::new(interval);
 
     let mut stdout = std::io::stdout();
 
     crossterm::execute!(stdout, EnterAlternateScreen)?;
     crossterm::terminal::enable_raw_mode()?;
 
-    {
+    let mut child_status = None;
+    let result = (|| -> anyhow::Result<()> {
         let backend = CrosstermBackend::new(&mut stdout);
         let mut terminal = Terminal::new(backend)?;
         terminal.hide_cursor()?;
         terminal.clear()?;
 
-        let mut app = App::new(prc);
+        let mut app = App::new(source, prc)?;
+        app.recorder = recorder;
+        app.interval = interval;
+
+        // a pidfd shows the exit right away, instead of on the next tick.  There's nothing to watch
+        // during a replay or in a copy of /proc
+        let live = replay.is_none() && opts.proc_root.is_none();
+        let mut watching_exit = None;
+        let mut follower = opts.follow.map(select::Follower::new);
+        if let Some(follower) = &mut follower {
+            follower.watch(&app.proc);
+        }
+        // set while waiting for a followed process to come back
+        let mut waiting = false;
 
         let mut need_redraw = true;
         let mut tab_body_height = 0;
         loop {
+            if live && watching_exit != Some(app.id) {
+                watching_exit = Some(app.id);
+                if let Ok(pidfd) = PidFd::open_process(app.id) {
+                    events.watch_exit(app.id, pidfd);
+                }
+            }
             if need_redraw {
-                // vertical layout has 5 sections:
+                if let Some(ctrl) = &replay {
+                    app.status = Some(ctrl.status());
+                }
                 terminal.draw(|f| {
-                    let chunks = Layout::default()
-                        .direction(Direction::Vertical)
-                        .margin(0)
-                        .constraints(
-                            [
-                                Constraint::Length(1),     // very top line
-                                Constraint::Length(4 + 2), // top fixed-sized info box
-                                Constraint::Length(1 + 2), // tab selector
-                                Constraint::Min(0),        // tab body
-                                Constraint::Length(5),     // cpu sparkline
-                                                           // Constraint::Length(5),     // cpu sparkline
-                            ]
-                            .as_ref(),
-                        )
-                        .split(f.size());
-
-                    tab_body_height = chunks[3].height;
-
-                    let mut help_text = Text::default();
-
-                    app.draw_tab_selector(f, chunks[2]);
-                    app.draw_tab_body(f, chunks[3], &mut help_text);
-                    app.draw_cpu_spark(f, chunks[4]);
-
-                    app.draw_top(f, chunks[0], chunks[1], help_text);
+                    tab_body_height = app.draw(f, replay.as_ref().map(|ctrl| ctrl.help()));
                 })?;
                 need_redraw = false;
             }
 
             match events.rx.recv() {
                 Err(..) => break,
+                Ok(Event::Key(KeyEvent {
+                    code: KeyCode::Char('c'),
+                    modifiers,
+                    ..
+                })) if modifiers.contains(KeyModifiers::CONTROL) => break,
+                // a filter or a signal is being typed in, so keys like q are just text
+                Ok(Event::Key(k)) if app.editing() => {
+                    need_redraw = app.handle_input(k, tab_body_height) != ui::InputResult::None;
+                }
                 Ok(Event::Key(KeyEvent { code: KeyCode::Esc, .. }))
                 | Ok(Event::Key(KeyEvent {
                     code: KeyCode::Char('q'),
                     ..
                 })) => break,
+
                 Ok(Event::Key(KeyEvent {
-                    code: KeyCode::Char('c'),
-                    modifiers,
+                    code: KeyCode::Char(c @ ('+' | '=' | '-')),
                     ..
-                })) if modifiers.contains(KeyModifiers::CONTROL) => break,
-
-                Ok(Event::Key(k)) => match app.handle_input(k, tab_body_height) {
-                    ui::InputResult::NeedsUpdate => {
-                        need_redraw = true;
-                        app.tick();
+                })) => {
+                    app.step_interval(c != '-');
+                    events.set_interval(app.interval);
+                    if replay.is_none() {
+                        ui::set_refresh_interval(app.interval);
                     }
-                    ui::InputResult::NeedsRedraw => {
-                        need_redraw = true;
+                    need_redraw = true;
+                }
+                Ok(Event::Key(KeyEvent {
+                    code: KeyCode::Char('p'),
+                    ..
+                })) => {
+                    match &mut replay {
+                        Some(ctrl) => ctrl.paused = !ctrl.paused,
+                        None => app.frozen = !app.frozen,
                     }
-                    _ => {}
-                },
+                    need_redraw = true;
+                }
+                Ok(Event::Key(k)) => {
+                    let action = match &mut replay {
+                        Some(ctrl) => ctrl.handle_input(k),
+                        None => ReplayAction::None,
+                    };
+                    match action {
+                        ReplayAction::Seek(pos) => {
+                            if let Some(ctrl) = &mut replay {
+                                seek_replay(&mut app, &mut ctrl.replay, pos)?;
+                            }
+                            need_redraw = true;
+                        }
+                        ReplayAction::Redraw => need_redraw = true,
+                        ReplayAction::None => match app.handle_input(k, tab_body_height) {
+                            ui::InputResult::NeedsUpdate => {
+                                need_redraw = true;
+                                // a replay only has new data when it moves to another frame, and
+                                // while paused, the widgets catch up on the next tick after it
+                                if replay.is_none() && !app.frozen {
+                                    app.tick();
+                                }
+                            }
+                            ui::InputResult::NeedsRedraw => {
+                                need_redraw = true;
+                            }
+                            _ => {}
+                        },
+                    }
+                }
                 Ok(Event::Tick) => {
                     need_redraw = true;
-                    app.tick();
+                    match &mut replay {
+                        Some(ctrl) => {
+                            if let Some(pos) = ctrl.next_frame() {
+                                seek_replay(&mut app, &mut ctrl.replay, pos)?;
+                            }
+                        }
+                        None if app.frozen => {}
+                        None => app.tick(),
+                    }
+                    app.record();
+                    // this also reaps the child, so it has to come after the last tick
+                    check_child(&mut app, &mut child, &mut child_status)?;
+                    if let Some(follower) = &mut follower {
+                        if app.died_at.is_none() {
+                            follower.watch(&app.proc);
+                        } else {
+                            let old_pid = app.proc.pid;
+                            if !waiting {
+                                waiting = true;
+                                app.log(format!(
+                                    "process {old_pid} exited, waiting for a new one with {}",
+                                    follower.by()
+                                ));
+                            }
+                            if let Some(new) = follower.find(&*app.source) {
+                                let new_id = ProcessId::of(&new).ok();
+                                if new_id.is_some_and(|id| app.follow_to(id)) {
+                                    waiting = false;
+                                    app.log(format!("following process {} in place of {old_pid}", new.pid));
+                                }
+                            }
+                        }
+                    }
+                    if let Some(w) = watcher.as_mut().filter(|w| !w.done()) {
+                        let lines = w.check(&app.proc, app.source.now(), Some(&app));
+                        if !lines.is_empty() {
+                            app.status = Some(format!("[{}] ", lines.join("; ")));
+                        }
+                    }
+                }
+                Ok(Event::Exited(id)) if id == app.id => {
+                    app.mark_exited();
+                    check_child(&mut app, &mut child, &mut child_status)?;
+                    need_redraw = true;
                 }
 
                 _ => {}
             }
         }
-    }
+        Ok(())
+    })();
 
     crossterm::execute!(stdout, LeaveAlternateScreen)?;
     crossterm::terminal::disable_raw_mode()?;
+    if result.is_err() {
+        kill_child(&mut child);
+    }
+    result?;
 
     //println!("\n-----");
     //println!("{:?}", prc);
 
+    if let Some(mut c) = child {
+        let status = match child_status {
+            Some(status) => status,
+            None => {
+                // the command is still running, but nobody is watching it anymore
+                let _ = c.kill();
+                c.wait()?
+            }
+        };
+        std::process::exit(exit_code(status));
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use procdump::source::DataSource;
+    use ratatui::{layout::Rect, text::Text};
+
+    use crate::{
+        ui::widgets::{buffer_lines, fixture, render},
+        App,
+    };
+
+    #[test]
+    fn test_draw_top() {
+        let source = fixture();
+        let mut app = App::new(source.clone(), source.process(101).unwrap()).unwrap();
+        // half a second of CPU time in the last second
+        let mut stat = app.stat_d.latest().clone();
+        let then = Instant::now();
+        app.stat_d.push(stat.clone(), then);
+        stat.utime += 30;
+        stat.stime += 20;
+        app.stat_d.push(stat, then + Duration::from_secs(1));
+
+        let buffer = render(90, 5, |f| {
+            app.draw_top(f, Rect::new(0, 0, 90, 1), Rect::new(0, 1, 90, 4), Text::default())
+        });
+        // the start time is left out, since it's shown in the local time zone
+        assert_eq!(
+            buffer_lines(&buffer)[..4],
+            [
+                "─ sleep 1000 [every 1.5s] ────────────────────────────────────────────────────────────────",
+                "pid:101 ppid:100 pgrp:100    │cpu usage:50.00%             │                              ",
+                "session:100                  │user\u{a0}time:300ms              │                              ",
+                "state:S\u{a0}(Sleeping)           │kernel\u{a0}time:210ms u/k:58.82% │                              ",
+            ]
+        );
+    }
+}
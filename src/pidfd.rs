@@ -0,0 +1,139 @@
+//! Process file descriptors (`pidfd_open(2)`), which become readable when the process exits.
+//!
+//! Unlike a PID, a pidfd always refers to the same process, even after the PID is reused.  It's
+//! also the only way to find out about the exit of a process that isn't our child without polling
+//! `/proc`.
+
+use std::{
+    io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    time::Duration,
+};
+
+use crate::source::{LiveSource, ProcessId};
+
+/// A handle on a running process, from `pidfd_open`
+#[derive(Debug)]
+pub struct PidFd(OwnedFd);
+
+impl PidFd {
+    /// Opens the process with the given PID.  This needs Linux 5.3 or newer
+    pub fn open(pid: i32) -> io::Result<PidFd> {
+        let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(PidFd(unsafe { OwnedFd::from_raw_fd(fd as i32) }))
+    }
+
+    /// Opens the process `id`, and makes sure that it's the right one: if its PID has been reused
+    /// by another process, this fails with `ESRCH`, as if it had already been reaped.  Once the
+    /// pidfd is open, it can't change which process it refers to, so checking afterwards is enough
+    pub fn open_process(id: ProcessId) -> io::Result<PidFd> {
+        let pidfd = PidFd::open(id.pid)?;
+        if !id.is_current(&LiveSource) {
+            return Err(io::Error::from_raw_os_error(libc::ESRCH));
+        }
+        Ok(pidfd)
+    }
+
+    /// Waits for the process to exit, for at most `timeout` (or forever if it's `None`).  Returns
+    /// true if it has exited
+    pub fn wait_exit(&self, timeout: Option<Duration>) -> io::Result<bool> {
+        let mut fds = libc::pollfd {
+            fd: self.0.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout.map_or(-1, |t| t.as_millis().min(i32::MAX as u128) as i32);
+        loop {
+            let ret = unsafe { libc::poll(&mut fds, 1, timeout) };
+            if ret >= 0 {
+                return Ok(ret > 0);
+            }
+            let e = io::Error::last_os_error();
+            if e.kind() != io::ErrorKind::Interrupted {
+                return Err(e);
+            }
+        }
+    }
+
+    /// Sends a signal to the process (`pidfd_send_signal(2)`)
+    pub fn send_signal(&self, signal: i32) -> io::Result<()> {
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_pidfd_send_signal,
+                self.0.as_raw_fd(),
+                signal,
+                std::ptr::null::<libc::siginfo_t>(),
+                0,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// True if the process has exited.  A process that has exited but hasn't been reaped by its
+    /// parent yet (a zombie) counts as exited
+    pub fn has_exited(&self) -> bool {
+        self.wait_exit(Some(Duration::ZERO)).unwrap_or(false)
+    }
+}
+
+/// Sends a signal to process `id`, or to one of its threads (with `tgkill(2)`).  This fails with
+/// `ESRCH` if the PID now belongs to another process, so a signal never goes to the wrong one
+pub fn send_signal(id: ProcessId, tid: Option<i32>, signal: i32) -> io::Result<()> {
+    let pidfd = match PidFd::open_process(id) {
+        Ok(pidfd) => Some(pidfd),
+        // without pidfds, checking the start time right before sending the signal has to do
+        Err(e) if e.raw_os_error() == Some(libc::ENOSYS) => {
+            if !id.is_current(&LiveSource) {
+                return Err(io::Error::from_raw_os_error(libc::ESRCH));
+            }
+            None
+        }
+        Err(e) => return Err(e),
+    };
+    let ret = match (tid, pidfd) {
+        (Some(tid), _) => unsafe { libc::syscall(libc::SYS_tgkill, id.pid, tid, signal) },
+        (None, Some(pidfd)) => return pidfd.send_signal(signal),
+        (None, None) => unsafe { libc::kill(id.pid, signal) as libc::c_long },
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::PidFd;
+
+    #[test]
+    fn test_pidfd() {
+        let mut child = std::process::Command::new("sleep").arg("10").spawn().unwrap();
+        let pidfd = match PidFd::open(child.id() as i32) {
+            Ok(pidfd) => pidfd,
+            // pidfd_open isn't available before Linux 5.3, or in some sandboxes
+            Err(e) if e.raw_os_error() == Some(libc::ENOSYS) || e.raw_os_error() == Some(libc::EPERM) => {
+                child.kill().unwrap();
+                child.wait().unwrap();
+                return;
+            }
+            Err(e) => panic!("{e}"),
+        };
+        assert!(!pidfd.has_exited());
+        assert!(!pidfd.wait_exit(Some(Duration::from_millis(50))).unwrap());
+
+        child.kill().unwrap();
+        assert!(pidfd.wait_exit(Some(Duration::from_secs(5))).unwrap());
+        // a zombie has exited too
+        assert!(pidfd.has_exited());
+        child.wait().unwrap();
+        assert!(pidfd.has_exited());
+    }
+}
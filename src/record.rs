@@ -0,0 +1,594 @@
+//! Records the samples that procdump takes of a process into a file (`--record`), and plays them
+//! back later (`--replay`), for looking at an incident after the process is gone.
+//!
+//! A recording is a text file with one JSON object per line.  The first line is a [`Header`], and
+//! every line after that is a [`Frame`], written once per tick.  To keep the file small, a frame
+//! only holds the files under `/proc/<pid>` that changed since the previous frame, and the network
+//! tables only keep the sockets that belong to the process.
+//!
+//! A [`Replay`] writes the frames back out into a temporary directory that is laid out like
+//! `/proc`, and [`ReplaySource`] reads from it, so every part of procdump that works on a live
+//! process also works on a replay.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use procfs::{process::Process, CGroupController, Lock, ProcResult, SystemInfo};
+use serde::{Deserialize, Serialize};
+
+use crate::source::{DataSource, FixtureSource};
+
+/// Identifies a procdump recording, in [`Header::format`]
+pub const FORMAT: &str = "procdump-recording";
+
+/// The version of the recording format.  Recordings with a different version can't be replayed
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Files that are recorded for the process, relative to `/proc/<pid>`
+const PROCESS_FILES: &[&str] = &["stat", "status", "cmdline", "io"];
+
+/// Network tables, relative to `/proc/<pid>`, along with the column that holds the socket inode
+const NET_FILES: &[(&str, usize)] = &[
+    ("net/tcp", 9),
+    ("net/tcp6", 9),
+    ("net/udp", 9),
+    ("net/udp6", 9),
+    ("net/unix", 6),
+];
+
+/// Files that are recorded for each thread, relative to `/proc/<pid>/task/<tid>`
+const TASK_FILES: &[&str] = &["stat", "io"];
+
+/// The first line of a recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Header {
+    /// Always [`FORMAT`]
+    pub format: String,
+    /// Always [`FORMAT_VERSION`] for recordings made by this version of procdump
+    pub version: u32,
+    pub pid: i32,
+    /// When the recording was started (RFC 3339)
+    pub started_at: String,
+    pub boot_time_secs: u64,
+    pub ticks_per_second: u64,
+    pub page_size: u64,
+}
+
+/// One sample of the process
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    /// Milliseconds since the start of the recording
+    pub t: u64,
+    /// The contents of files that changed since the previous frame, keyed by their path relative to
+    /// `/proc/<pid>`.  `None` means the file went away
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub files: BTreeMap<String, Option<String>>,
+    /// Like `files`, but for symlinks (the entries in `fd/`), holding the link target
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub links: BTreeMap<String, Option<String>>,
+}
+
+/// Everything that is known about the process at one point in time
+#[derive(Debug, Default, Clone, PartialEq)]
+struct State {
+    files: BTreeMap<String, String>,
+    links: BTreeMap<String, String>,
+}
+
+impl State {
+    /// Reads the current state of the process in `dir` (like `/proc/1234`)
+    fn read(dir: &Path) -> State {
+        let mut state = State::default();
+        let read = |path: PathBuf| {
+            fs::read(path)
+                .ok()
+                .map(|data| String::from_utf8_lossy(&data).into_owned())
+        };
+
+        for name in PROCESS_FILES {
+            if let Some(data) = read(dir.join(name)) {
+                state.files.insert(name.to_string(), data);
+            }
+        }
+        if let Ok(tasks) = fs::read_dir(dir.join("task")) {
+            for task in tasks.filter_map(|t| t.ok()) {
+                let tid = task.file_name().to_string_lossy().into_owned();
+                for name in TASK_FILES {
+                    if let Some(data) = read(task.path().join(name)) {
+                        state.files.insert(format!("task/{tid}/{name}"), data);
+                    }
+                }
+            }
+        }
+        if let Ok(fds) = fs::read_dir(dir.join("fd")) {
+            for fd in fds.filter_map(|f| f.ok()) {
+                // fds can be closed while we're looking at them
+                if let Ok(target) = fs::read_link(fd.path()) {
+                    let name = format!("fd/{}", fd.file_name().to_string_lossy());
+                    state.links.insert(name, target.to_string_lossy().into_owned());
+                }
+            }
+        }
+
+        // the network tables cover every socket in the namespace, but only the ones that this
+        // process has open are interesting
+        let inodes: BTreeSet<&str> = state
+            .links
+            .values()
+            .filter_map(|target| target.strip_prefix("socket:[")?.strip_suffix(']'))
+            .collect();
+        let mut tables = Vec::new();
+        for (name, column) in NET_FILES {
+            if let Some(table) = read(dir.join(name)) {
+                tables.push((name.to_string(), filter_net_table(&table, &inodes, *column)));
+            }
+        }
+        state.files.extend(tables);
+        state
+    }
+
+    /// Returns the changes needed to get from `self` to `new`
+    fn diff(&self, new: &State, t: u64) -> Frame {
+        Frame {
+            t,
+            files: diff_maps(&self.files, &new.files),
+            links: diff_maps(&self.links, &new.links),
+        }
+    }
+
+    fn apply(&mut self, frame: &Frame) {
+        apply_map(&mut self.files, &frame.files);
+        apply_map(&mut self.links, &frame.links);
+    }
+}
+
+fn diff_maps(old: &BTreeMap<String, String>, new: &BTreeMap<String, String>) -> BTreeMap<String, Option<String>> {
+    let mut changes = BTreeMap::new();
+    for (key, value) in new {
+        if old.get(key) != Some(value) {
+            changes.insert(key.clone(), Some(value.clone()));
+        }
+    }
+    for key in old.keys() {
+        if !new.contains_key(key) {
+            changes.insert(key.clone(), None);
+        }
+    }
+    changes
+}
+
+fn apply_map(map: &mut BTreeMap<String, String>, changes: &BTreeMap<String, Option<String>>) {
+    for (key, value) in changes {
+        match value {
+            Some(value) => map.insert(key.clone(), value.clone()),
+            None => map.remove(key),
+        };
+    }
+}
+
+/// Keeps the header line of a table from `/proc/net`, and the lines for the given socket inodes
+fn filter_net_table(table: &str, inodes: &BTreeSet<&str>, column: usize) -> String {
+    let mut lines = table.lines();
+    let mut out = String::new();
+    if let Some(header) = lines.next() {
+        out.push_str(header);
+        out.push('\n');
+    }
+    for line in lines {
+        if line
+            .split_whitespace()
+            .nth(column)
+            .is_some_and(|inode| inodes.contains(inode))
+        {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn invalid_data(line: usize, msg: impl std::fmt::Display) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("line {line}: {msg}"))
+}
+
+/// Appends a frame to a recording every time [`record`](Recorder::record) is called
+pub struct Recorder {
+    out: BufWriter<File>,
+    dir: PathBuf,
+    started: Instant,
+    last: State,
+    finished: bool,
+}
+
+impl Recorder {
+    /// Starts recording process `pid` from `proc_root` (usually `/proc`) into the file at `path`,
+    /// replacing it if it already exists.  The first frame is written right away
+    pub fn create(path: &Path, proc_root: &Path, pid: i32, system_info: &SystemInfo) -> io::Result<Recorder> {
+        let header = Header {
+            format: FORMAT.to_string(),
+            version: FORMAT_VERSION,
+            pid,
+            started_at: chrono::Local::now().to_rfc3339(),
+            boot_time_secs: system_info.boot_time_secs().unwrap_or(0),
+            ticks_per_second: system_info.ticks_per_second(),
+            page_size: system_info.page_size(),
+        };
+        let mut out = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut out, &header)?;
+        out.write_all(b"\n")?;
+
+        let mut recorder = Recorder {
+            out,
+            dir: proc_root.join(pid.to_string()),
+            started: Instant::now(),
+            last: State::default(),
+            finished: false,
+        };
+        recorder.record()?;
+        Ok(recorder)
+    }
+
+    /// Samples the process and writes a frame.  Once the process has gone away, one last frame
+    /// recording that is written, and after that this does nothing
+    pub fn record(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        let state = State::read(&self.dir);
+        self.finished = !state.files.contains_key("stat");
+
+        let t = self.started.elapsed().as_millis() as u64;
+        serde_json::to_writer(&mut self.out, &self.last.diff(&state, t))?;
+        self.out.write_all(b"\n")?;
+        // flush every frame, so that the recording is usable even if procdump is killed
+        self.out.flush()?;
+        self.last = state;
+        Ok(())
+    }
+}
+
+/// The current time of a replay, as seen by [`ReplaySource::now`]
+#[derive(Debug)]
+struct Clock {
+    /// The real time that corresponds to `base_t`
+    base: Instant,
+    base_t: u64,
+    /// The time of the current frame, in milliseconds since the start of the recording
+    t: u64,
+}
+
+/// Reads a replay as if it were `/proc`, at the current frame of the [`Replay`] that created it
+#[derive(Debug)]
+pub struct ReplaySource {
+    inner: FixtureSource,
+    clock: Mutex<Clock>,
+    /// When the recording started, from its header
+    started_at: Option<chrono::DateTime<chrono::Local>>,
+}
+
+impl DataSource for ReplaySource {
+    fn process(&self, pid: i32) -> ProcResult<Process> {
+        self.inner.process(pid)
+    }
+    fn myself(&self) -> ProcResult<Process> {
+        self.inner.myself()
+    }
+    fn all_processes(&self) -> ProcResult<Vec<Process>> {
+        self.inner.all_processes()
+    }
+    fn locks(&self) -> ProcResult<Vec<Lock>> {
+        // locks aren't recorded
+        Ok(Vec::new())
+    }
+    fn cgroups(&self) -> ProcResult<Vec<CGroupController>> {
+        self.inner.cgroups()
+    }
+    fn system_info(&self) -> &SystemInfo {
+        self.inner.system_info()
+    }
+    fn root(&self) -> &Path {
+        self.inner.root()
+    }
+    fn now(&self) -> Instant {
+        let clock = self.clock.lock().unwrap();
+        // time can go backwards when seeking, but an Instant can't go before `base`
+        clock.base + Duration::from_millis(clock.t.saturating_sub(clock.base_t))
+    }
+    fn wall_clock(&self) -> chrono::DateTime<chrono::Local> {
+        let t = self.clock.lock().unwrap().t;
+        match self.started_at {
+            Some(start) => start + chrono::Duration::milliseconds(t as i64),
+            None => chrono::Local::now(),
+        }
+    }
+}
+
+/// A recording that is being played back
+pub struct Replay {
+    pub header: Header,
+    frames: Vec<Frame>,
+    pos: usize,
+    /// The state at `pos`
+    state: State,
+    /// What is currently written to the replay directory
+    written: State,
+    source: Arc<ReplaySource>,
+}
+
+impl Replay {
+    /// Reads a recording, and sets up the replay directory at the first frame
+    pub fn open(path: &Path) -> io::Result<Replay> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let header: Header = match lines.next() {
+            Some(line) => serde_json::from_str(&line?).map_err(|e| invalid_data(1, e))?,
+            None => return Err(io::Error::new(ErrorKind::InvalidData, "the recording is empty")),
+        };
+        if header.format != FORMAT || header.version != FORMAT_VERSION {
+            return Err(invalid_data(
+                1,
+                format!(
+                    "not a version {FORMAT_VERSION} procdump recording (format {:?}, version {})",
+                    header.format, header.version
+                ),
+            ));
+        }
+        let mut frames = Vec::new();
+        for (idx, line) in lines.enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(frame) => frames.push(frame),
+                // the last line is cut short if procdump was killed while writing it
+                Err(e) if e.is_eof() => break,
+                Err(e) => return Err(invalid_data(idx + 2, e)),
+            }
+        }
+        if frames.is_empty() {
+            return Err(io::Error::new(ErrorKind::InvalidData, "the recording has no samples"));
+        }
+
+        static REPLAYS: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "procdump-replay-{}-{}",
+            std::process::id(),
+            REPLAYS.fetch_add(1, Ordering::Relaxed)
+        ));
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(dir.join(header.pid.to_string()))?;
+        std::os::unix::fs::symlink(header.pid.to_string(), dir.join("self"))?;
+
+        let mut inner = FixtureSource::new(dir);
+        inner.system_info.boot_time_secs = header.boot_time_secs;
+        inner.system_info.ticks_per_second = header.ticks_per_second;
+        inner.system_info.page_size = header.page_size;
+
+        let mut state = State::default();
+        state.apply(&frames[0]);
+        let mut replay = Replay {
+            source: Arc::new(ReplaySource {
+                inner,
+                clock: Mutex::new(Clock {
+                    base: Instant::now(),
+                    base_t: frames[0].t,
+                    t: frames[0].t,
+                }),
+                started_at: chrono::DateTime::parse_from_rfc3339(&header.started_at)
+                    .ok()
+                    .map(|start| start.with_timezone(&chrono::Local)),
+            }),
+            header,
+            frames,
+            pos: 0,
+            state,
+            written: State::default(),
+        };
+        replay.write()?;
+        Ok(replay)
+    }
+
+    /// The source that reads the current frame.  It stays valid for as long as the `Replay` exists
+    pub fn source(&self) -> Arc<ReplaySource> {
+        self.source.clone()
+    }
+
+    /// The number of frames in the recording
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Always false, since a recording without any frames can't be opened
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// The index of the current frame
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// How far into the recording the current frame is
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_millis(self.frames[self.pos].t)
+    }
+
+    /// How long the recording is
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.frames[self.frames.len() - 1].t)
+    }
+
+    /// The index of the first frame at or after `elapsed` into the recording (or the last frame)
+    pub fn position_at(&self, elapsed: Duration) -> usize {
+        let t = elapsed.as_millis() as u64;
+        self.frames.partition_point(|f| f.t < t).min(self.frames.len() - 1)
+    }
+
+    /// Moves to the given frame (clamped to the last one), and writes it to the replay directory
+    pub fn seek(&mut self, pos: usize) -> io::Result<()> {
+        let pos = pos.min(self.frames.len() - 1);
+        if pos < self.pos {
+            // frames only hold changes, so going backwards means starting over
+            self.state = State::default();
+            self.state.apply(&self.frames[0]);
+            self.pos = 0;
+        }
+        for frame in &self.frames[self.pos + 1..=pos] {
+            self.state.apply(frame);
+        }
+        self.pos = pos;
+        self.source.clock.lock().unwrap().t = self.frames[pos].t;
+        self.write()
+    }
+
+    /// Makes [`ReplaySource::now`] return the current real time for the current frame.  This
+    /// needs to be called before creating anything that compares the time of the replay with
+    /// [`Instant::now`], since time in the replay can go backwards
+    pub fn reset_clock(&self) {
+        let mut clock = self.source.clock.lock().unwrap();
+        clock.base = Instant::now();
+        clock.base_t = clock.t;
+    }
+
+    /// Brings the replay directory up to date with `state`
+    fn write(&mut self) -> io::Result<()> {
+        let dir = self.source.inner.root().join(self.header.pid.to_string());
+        for (name, data) in &self.state.files {
+            if self.written.files.get(name) != Some(data) {
+                let path = dir.join(name);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, data)?;
+            }
+        }
+        for name in self.written.files.keys() {
+            if !self.state.files.contains_key(name) {
+                let path = dir.join(name);
+                fs::remove_file(&path)?;
+                if name.starts_with("task/") {
+                    // this only works once every file for the thread is gone, which is the point
+                    let _ = fs::remove_dir(path.parent().unwrap());
+                }
+            }
+        }
+        for (name, target) in &self.state.links {
+            if self.written.links.get(name) != Some(target) {
+                let path = dir.join(name);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                if self.written.links.contains_key(name) {
+                    fs::remove_file(&path)?;
+                }
+                std::os::unix::fs::symlink(target, path)?;
+            }
+        }
+        for name in self.written.links.keys() {
+            if !self.state.links.contains_key(name) {
+                fs::remove_file(dir.join(name))?;
+            }
+        }
+        self.written = self.state.clone();
+        Ok(())
+    }
+}
+
+impl Drop for Replay {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(self.source.inner.root());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, os::unix::fs::symlink, time::Duration};
+
+    use crate::source::{fake_stat_line, DataSource};
+
+    use super::{Recorder, Replay};
+
+    fn stat(utime: u64) -> String {
+        fake_stat_line(42, "app", utime, 0)
+    }
+
+    #[test]
+    fn test_record_replay() {
+        let root = std::env::temp_dir().join(format!("procdump-record-{}", std::process::id()));
+        let dir = root.join("42");
+        fs::create_dir_all(dir.join("fd")).unwrap();
+        fs::create_dir_all(dir.join("net")).unwrap();
+        fs::create_dir_all(dir.join("task/43")).unwrap();
+        fs::write(dir.join("stat"), stat(10)).unwrap();
+        fs::write(dir.join("task/43/stat"), stat(5)).unwrap();
+        symlink("socket:[1000]", dir.join("fd/3")).unwrap();
+        fs::write(
+            dir.join("net/tcp"),
+            "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n   \
+             0: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 1000 1\n   \
+             1: 0100007F:1F91 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 2000 1\n",
+        )
+        .unwrap();
+
+        let path = root.join("recording");
+        let source = crate::source::FixtureSource::new(&root);
+        let mut recorder = Recorder::create(&path, &root, 42, source.system_info()).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        fs::write(dir.join("stat"), stat(20)).unwrap();
+        fs::remove_dir_all(dir.join("task/43")).unwrap();
+        recorder.record().unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        fs::remove_dir_all(&dir).unwrap();
+        recorder.record().unwrap();
+        // nothing more is written once the process is gone
+        recorder.record().unwrap();
+        drop(recorder);
+
+        let recording = fs::read_to_string(&path).unwrap();
+        assert_eq!(recording.lines().count(), 4);
+        // only the socket that belongs to the process is kept
+        assert!(recording.contains(":1F90"));
+        assert!(!recording.contains(":1F91"));
+
+        let mut replay = Replay::open(&path).unwrap();
+        assert_eq!(replay.len(), 3);
+        let source = replay.source();
+        let proc = source.myself().unwrap();
+        assert_eq!(proc.pid, 42);
+        assert_eq!(proc.stat().unwrap().utime, 10);
+        assert_eq!(proc.tasks().unwrap().count(), 1);
+        assert_eq!(proc.fd().unwrap().count(), 1);
+        assert_eq!(proc.tcp().unwrap().len(), 1);
+        let start = source.now();
+
+        replay.seek(1).unwrap();
+        assert_eq!(proc.stat().unwrap().utime, 20);
+        assert_eq!(proc.tasks().unwrap().count(), 0);
+        assert!(source.now() - start >= Duration::from_millis(20));
+        assert_eq!(replay.position_at(replay.duration()), 2);
+
+        replay.seek(2).unwrap();
+        assert!(!proc.is_alive());
+
+        // going backwards starts over from the first frame
+        replay.seek(0).unwrap();
+        assert_eq!(proc.stat().unwrap().utime, 10);
+        assert_eq!(proc.tasks().unwrap().count(), 1);
+
+        let replay_dir = source.inner.root().to_owned();
+        drop(replay);
+        assert!(!replay_dir.exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
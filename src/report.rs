@@ -0,0 +1,178 @@
+//! Incident reports: a directory with everything procdump knows about a process at the moment a
+//! trigger fired, as plain text and JSON.  Unlike a core dump, writing one doesn't need ptrace.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Context;
+use procdump::{collect::CGroupInfo, json, source::DataSource, util};
+use procfs::process::Process;
+
+/// The raw socket tables that are copied, relative to `/proc/<pid>`
+const NET_FILES: &[&str] = &["net/tcp", "net/tcp6", "net/udp", "net/udp6", "net/unix"];
+
+/// What goes into a report, besides the things that are read on the spot
+pub(crate) struct Report<'a> {
+    pub proc: &'a Process,
+    pub comm: &'a str,
+    pub reason: &'a str,
+    /// How the process ended, if it was started by procdump and has exited
+    pub exit_status: Option<std::process::ExitStatus>,
+    /// Every tab as plain text, like `--snapshot`
+    pub snapshot: String,
+    /// Every tab as JSON, like `--json`
+    pub json: json::Document,
+    pub cgroups: Option<&'a [CGroupInfo]>,
+    /// The recent history of each sparkline, oldest first
+    pub sparklines: Vec<(&'static str, &'a [u64])>,
+    pub interval: Duration,
+}
+
+/// Creates a directory that didn't exist before, adding `.1`, `.2`, and so on to `path` if needed,
+/// so that two reports written in the same second don't end up in the same place
+fn create_new_dir(path: &Path) -> anyhow::Result<PathBuf> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Unable to create {}", parent.display()))?;
+    }
+    let mut candidate = path.to_owned();
+    for n in 1.. {
+        match fs::create_dir(&candidate) {
+            Ok(()) => return Ok(candidate),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                candidate = PathBuf::from(format!("{}.{n}", path.display()));
+            }
+            Err(e) => return Err(e).with_context(|| format!("Unable to create {}", candidate.display())),
+        }
+    }
+    unreachable!()
+}
+
+impl Report<'_> {
+    /// Writes the report into a new directory under `dir`, and returns the path of that directory
+    pub fn write(&self, source: &dyn DataSource, dir: &Path) -> anyhow::Result<PathBuf> {
+        let now = chrono::Local::now();
+        let name = format!(
+            "report.{}.{}.{}",
+            self.comm.replace('/', "_"),
+            self.proc.pid,
+            now.format("%Y%m%d-%H%M%S")
+        );
+        let path = create_new_dir(&dir.join(name))?;
+        let write = |name: &str, contents: &str| {
+            let file = path.join(name);
+            fs::write(&file, contents).with_context(|| format!("Unable to write {}", file.display()))
+        };
+
+        let mut reason = format!(
+            "reason: {}\ntime: {}\npid: {}\nprocdump: {}\n",
+            self.reason,
+            now.to_rfc3339(),
+            self.proc.pid,
+            env!("CARGO_PKG_VERSION")
+        );
+        if let Some(status) = self.exit_status {
+            writeln!(reason, "exit status: {}", util::fmt_exit_status(status))?;
+        }
+        write("reason.txt", &reason)?;
+        write("snapshot.txt", &self.snapshot)?;
+        write("procdump.json", &serde_json::to_string_pretty(&self.json)?)?;
+        write("tree.txt", &process_tree(source))?;
+        write("pipes.txt", &pipes(source))?;
+        write("sparklines.txt", &self.sparklines())?;
+
+        // these are gone along with the process, so they're only there if it's still running.  They're
+        // read through `proc`, which can't end up reading another process that reused the PID
+        fs::create_dir_all(path.join("net"))?;
+        for name in NET_FILES {
+            let mut table = Vec::new();
+            if let Ok(mut file) = self.proc.open_relative(name) {
+                if file.read_to_end(&mut table).is_ok() {
+                    fs::write(path.join(name), table)?;
+                }
+            }
+        }
+
+        if let Some(cgroups) = self.cgroups {
+            for cg in cgroups {
+                let mut controllers = cg.controllers.join(",");
+                if controllers.is_empty() {
+                    controllers = "unified".to_string();
+                }
+                let cg_dir = path.join("cgroups").join(format!("{}-{controllers}", cg.hierarchy));
+                fs::create_dir_all(&cg_dir)?;
+                let mut info = format!("path: {}\n", cg.pathname);
+                if let Some(mount_point) = &cg.mount_point {
+                    writeln!(info, "mounted at: {}", mount_point.display())?;
+                }
+                fs::write(cg_dir.join("cgroup.txt"), info)?;
+                for (name, contents) in &cg.files {
+                    fs::write(cg_dir.join(name), contents)?;
+                }
+            }
+        }
+
+        Ok(path)
+    }
+
+    fn sparklines(&self) -> String {
+        let mut out = format!("# one sample every {:.1}s, oldest first\n", self.interval.as_secs_f32());
+        for (name, data) in &self.sparklines {
+            let data: Vec<String> = data.iter().map(|v| v.to_string()).collect();
+            let _ = writeln!(out, "{name}: {}", data.join(" "));
+        }
+        out
+    }
+}
+
+/// Every process on the system, indented by its depth in the tree
+fn process_tree(source: &dyn DataSource) -> String {
+    let tree = match util::ProcessTree::new(source, None) {
+        Ok(tree) => tree,
+        Err(e) => return format!("Unable to read the process tree: {e}\n"),
+    };
+    let mut out = String::new();
+    for (depth, entry) in tree.flatten() {
+        let indent = "  ".repeat(depth.saturating_sub(1) as usize);
+        let _ = writeln!(out, "{indent}{} {}", entry.pid, entry.cmdline);
+    }
+    out
+}
+
+/// Every pipe on the system that's open at both ends, and the processes at each end
+fn pipes(source: &dyn DataSource) -> String {
+    let mut pairs: Vec<_> = util::get_pipe_pairs(source).into_iter().collect();
+    pairs.sort_by_key(|(inode, _)| *inode);
+    let mut out = String::new();
+    for (inode, (read, write)) in pairs {
+        let _ = writeln!(
+            out,
+            "pipe:[{inode}] written by {} ({}), read by {} ({})",
+            write.pid, write.cmdline, read.pid, read.cmdline
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_create_new_dir() {
+        let dir = std::env::temp_dir().join(format!("procdump-report-{}", std::process::id()));
+        let path = dir.join("report.sleep.101.20240101-120000");
+        assert_eq!(super::create_new_dir(&path).unwrap(), path);
+        assert_eq!(
+            super::create_new_dir(&path).unwrap(),
+            dir.join("report.sleep.101.20240101-120000.1")
+        );
+        assert_eq!(
+            super::create_new_dir(&path).unwrap(),
+            dir.join("report.sleep.101.20240101-120000.2")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,367 @@
+//! Finds processes by something other than their PID: their name or command line (`--name`), a
+//! pidfile (`--pidfile`), the systemd unit or cgroup they belong to (`--unit` and `--cgroup`), or
+//! their owner (`--user`).
+//! Also finds a process again after it has been restarted with a new PID (`--follow`).
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use procfs::{process::Process, ProcResult};
+use regex::Regex;
+
+use crate::source::DataSource;
+
+/// Matches a process by name
+#[derive(Debug, Clone)]
+pub struct NamePattern {
+    name: String,
+    regex: Regex,
+}
+
+impl NamePattern {
+    /// `pattern` matches a process if it's exactly the process name (`comm`), or if it's a regex
+    /// that matches somewhere in the command line (with the arguments joined by spaces)
+    pub fn new(pattern: &str) -> Result<NamePattern, regex::Error> {
+        Ok(NamePattern {
+            name: pattern.to_string(),
+            regex: Regex::new(pattern)?,
+        })
+    }
+
+    fn matches(&self, proc: &Process) -> bool {
+        if proc.stat().is_ok_and(|stat| stat.comm == self.name) {
+            return true;
+        }
+        // kernel threads don't have a command line, so they can only match by name
+        proc.cmdline()
+            .is_ok_and(|cmdline| !cmdline.is_empty() && self.regex.is_match(&cmdline.join(" ")))
+    }
+}
+
+/// Which processes to look for.  A process has to match everything that is set
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub name: Option<NamePattern>,
+    /// A systemd unit, like `nginx.service`
+    pub unit: Option<String>,
+    /// The real UID of the process
+    pub uid: Option<u32>,
+    /// A cgroup path, like `/system.slice/nginx.service`.  Processes in the cgroups below it match
+    /// too
+    pub cgroup: Option<String>,
+}
+
+impl Filter {
+    /// True if nothing is set, in which case every process matches
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.unit.is_none() && self.uid.is_none() && self.cgroup.is_none()
+    }
+
+    pub fn matches(&self, proc: &Process) -> bool {
+        if let Some(name) = &self.name {
+            if !name.matches(proc) {
+                return false;
+            }
+        }
+        if let Some(unit) = &self.unit {
+            if !in_unit(proc, unit) {
+                return false;
+            }
+        }
+        if let Some(uid) = self.uid {
+            if !proc.status().is_ok_and(|status| status.ruid == uid) {
+                return false;
+            }
+        }
+        if let Some(cgroup) = &self.cgroup {
+            if !in_cgroup(proc, cgroup) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Returns every process that matches, sorted by PID
+    pub fn find(&self, source: &dyn DataSource) -> ProcResult<Vec<Process>> {
+        let mut procs: Vec<Process> = source
+            .all_processes()?
+            .into_iter()
+            .filter(|p| self.matches(p))
+            .collect();
+        procs.sort_by_key(|p| p.pid);
+        Ok(procs)
+    }
+}
+
+/// Adds `.service` to a unit name that doesn't have a type, the same way `systemctl` does
+pub fn unit_name(unit: &str) -> String {
+    if unit.contains('.') {
+        unit.to_string()
+    } else {
+        format!("{unit}.service")
+    }
+}
+
+/// Checks if a process is in the cgroup of a systemd unit.  systemd names a unit's cgroup after the
+/// unit (like `/system.slice/nginx.service`), and a service can also create cgroups below its own
+fn in_unit(proc: &Process, unit: &str) -> bool {
+    let Ok(cgroups) = proc.cgroups() else {
+        return false;
+    };
+    cgroups
+        .0
+        .iter()
+        // the unified hierarchy (cgroup v2), or the one that systemd manages on cgroup v1
+        .filter(|cg| cg.hierarchy == 0 || cg.controllers.iter().any(|c| c == "name=systemd"))
+        .any(|cg| cg.pathname.split('/').any(|part| part == unit))
+}
+
+/// Checks if a process is in a cgroup (on any hierarchy), or in one of the cgroups below it
+fn in_cgroup(proc: &Process, path: &str) -> bool {
+    let Ok(cgroups) = proc.cgroups() else {
+        return false;
+    };
+    let path = path.trim_end_matches('/');
+    cgroups.0.iter().any(|cg| {
+        cg.pathname
+            .strip_prefix(path)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    })
+}
+
+/// What makes a restarted process the same as the one that exited, for `--follow`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FollowBy {
+    /// The same executable and command line
+    #[default]
+    Command,
+    /// The same cgroups
+    Cgroup,
+    /// The same parent (usually the supervisor that restarts it), and the same name
+    Parent,
+}
+
+impl FromStr for FollowBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<FollowBy, String> {
+        match s {
+            "command" => Ok(FollowBy::Command),
+            "cgroup" => Ok(FollowBy::Cgroup),
+            "parent" => Ok(FollowBy::Parent),
+            _ => Err(format!("expected command, cgroup, or parent, not {s:?}")),
+        }
+    }
+}
+
+impl std::fmt::Display for FollowBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FollowBy::Command => "the same command",
+            FollowBy::Cgroup => "the same cgroup",
+            FollowBy::Parent => "the same parent and name",
+        })
+    }
+}
+
+/// The things that [`FollowBy`] compares
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Identity {
+    Command {
+        /// Only readable with permission to ptrace the process
+        exe: Option<PathBuf>,
+        cmdline: Vec<String>,
+    },
+    Cgroup(Vec<String>),
+    Parent {
+        ppid: i32,
+        comm: String,
+    },
+}
+
+impl Identity {
+    fn of(proc: &Process, by: FollowBy) -> Option<Identity> {
+        match by {
+            FollowBy::Command => {
+                // a zombie doesn't have a command line anymore, and kernel threads never do
+                let cmdline = proc.cmdline().ok().filter(|c| !c.is_empty())?;
+                Some(Identity::Command {
+                    exe: proc.exe().ok(),
+                    cmdline,
+                })
+            }
+            FollowBy::Cgroup => {
+                let cgroups = proc.cgroups().ok()?;
+                Some(Identity::Cgroup(
+                    cgroups
+                        .0
+                        .iter()
+                        .map(|cg| format!("{}:{}:{}", cg.hierarchy, cg.controllers.join(","), cg.pathname))
+                        .collect(),
+                ))
+            }
+            FollowBy::Parent => {
+                let stat = proc.stat().ok()?;
+                Some(Identity::Parent {
+                    ppid: stat.ppid,
+                    comm: stat.comm,
+                })
+            }
+        }
+    }
+}
+
+/// Remembers who a process is, so that it can be found again once it has been restarted with a new
+/// PID
+#[derive(Debug, Clone)]
+pub struct Follower {
+    by: FollowBy,
+    pid: i32,
+    /// When the process started, in clock ticks since boot.  Its replacement has to be younger
+    starttime: u64,
+    identity: Option<Identity>,
+}
+
+impl Follower {
+    pub fn new(by: FollowBy) -> Follower {
+        Follower {
+            by,
+            pid: 0,
+            starttime: 0,
+            identity: None,
+        }
+    }
+
+    pub fn by(&self) -> FollowBy {
+        self.by
+    }
+
+    /// Learns who `proc` is, while it's still running.  This only reads anything when `proc` is a
+    /// different process than last time, so it's cheap to call often
+    pub fn watch(&mut self, proc: &Process) {
+        if proc.pid == self.pid && self.identity.is_some() {
+            return;
+        }
+        let Ok(stat) = proc.stat() else {
+            return;
+        };
+        self.pid = proc.pid;
+        self.starttime = stat.starttime;
+        self.identity = Identity::of(proc, self.by);
+    }
+
+    /// Looks for a process that took the place of the one being watched: one with the same
+    /// identity, that started after it.  If there's more than one, the oldest is picked
+    pub fn find(&self, source: &dyn DataSource) -> Option<Process> {
+        let identity = self.identity.as_ref()?;
+        let mut found: Vec<(u64, Process)> = source
+            .all_processes()
+            .ok()?
+            .into_iter()
+            .filter_map(|proc| {
+                let stat = proc.stat().ok()?;
+                let young = stat.starttime > self.starttime && stat.state != 'Z';
+                let same = young && Identity::of(&proc, self.by).as_ref() == Some(identity);
+                same.then_some((stat.starttime, proc))
+            })
+            .collect();
+        found.sort_by_key(|(starttime, proc)| (*starttime, proc.pid));
+        found.into_iter().next().map(|(_, proc)| proc)
+    }
+}
+
+/// Reads the PID from a pidfile, which holds a PID on its first line
+pub fn read_pidfile(path: &Path) -> io::Result<i32> {
+    let contents = fs::read_to_string(path)?;
+    let first = contents.lines().next().unwrap_or("").trim();
+    match first.parse() {
+        Ok(pid) if pid > 0 => Ok(pid),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("expected a PID, found {first:?}"),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source::{fixture, DataSource};
+
+    use super::{read_pidfile, unit_name, Filter, FollowBy, Follower, Identity, NamePattern};
+
+    fn find(filter: Filter) -> Vec<i32> {
+        filter.find(&fixture()).unwrap().iter().map(|p| p.pid).collect()
+    }
+
+    #[test]
+    fn test_filter() {
+        // the process name matches exactly, and the command line as a regex
+        let name = |pattern| Filter {
+            name: Some(NamePattern::new(pattern).unwrap()),
+            ..Filter::default()
+        };
+        assert_eq!(find(name("sleep")), [100, 101]);
+        assert_eq!(find(name("^sleep")), [101]);
+        assert_eq!(find(name("^(sleep|cat)$")), [102]);
+        assert_eq!(find(name("nothing")), Vec::<i32>::new());
+
+        assert_eq!(unit_name("demo"), "demo.service");
+        assert_eq!(unit_name("demo.scope"), "demo.scope");
+        let unit = Filter {
+            unit: Some(unit_name("demo")),
+            ..Filter::default()
+        };
+        assert_eq!(find(unit), [101, 102]);
+
+        let both = Filter {
+            unit: Some("demo.service".to_string()),
+            ..name("cat")
+        };
+        assert_eq!(find(both), [102]);
+
+        let cgroup = |path: &str| Filter {
+            cgroup: Some(path.to_string()),
+            ..Filter::default()
+        };
+        assert_eq!(find(cgroup("/system.slice/demo.service")), [101, 102]);
+        assert_eq!(find(cgroup("/system.slice/")), [101, 102]);
+        assert_eq!(find(cgroup("/system.slice/demo")), Vec::<i32>::new());
+        assert_eq!(find(cgroup("/")), [1, 100, 101, 102]);
+        assert!(Filter::default().is_empty());
+    }
+
+    #[test]
+    fn test_follow() {
+        let source = fixture();
+        let proc = |pid| source.process(pid).unwrap();
+        let id = |pid, by| Identity::of(&proc(pid), by);
+        assert_eq!(id(101, FollowBy::Cgroup), id(102, FollowBy::Cgroup));
+        assert_ne!(id(100, FollowBy::Cgroup), id(101, FollowBy::Cgroup));
+        assert_ne!(id(101, FollowBy::Parent), id(102, FollowBy::Parent));
+        assert_ne!(id(101, FollowBy::Command), id(102, FollowBy::Command));
+        assert_eq!("parent".parse(), Ok(FollowBy::Parent));
+        assert!("pid".parse::<FollowBy>().is_err());
+
+        // 102 is in the same cgroup as 101, but it isn't any younger
+        let mut follower = Follower::new(FollowBy::Cgroup);
+        follower.watch(&proc(101));
+        assert!(follower.find(&source).is_none());
+        // if the watched process had started earlier, both would qualify, and the lower PID wins
+        follower.starttime = 5000;
+        assert_eq!(follower.find(&source).map(|p| p.pid), Some(101));
+    }
+
+    #[test]
+    fn test_pidfile() {
+        let path = std::env::temp_dir().join(format!("procdump-pidfile-{}", std::process::id()));
+        std::fs::write(&path, "1234\n").unwrap();
+        assert_eq!(read_pidfile(&path).unwrap(), 1234);
+        std::fs::write(&path, "\n").unwrap();
+        assert!(read_pidfile(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+        assert!(read_pidfile(&path).is_err());
+    }
+}
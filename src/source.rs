@@ -0,0 +1,220 @@
+//! Where process information comes from.
+//!
+//! Most information is read through a [`Process`] handle, which already knows which directory it
+//! was opened from.  A [`DataSource`] covers everything else: finding processes in the first place,
+//! and the handful of system-wide files (like `/proc/locks`) that aren't tied to a single process.
+
+use std::{
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use procfs::{
+    process::{all_processes, all_processes_with_root, Process},
+    CGroupController, ExplicitSystemInfo, FromRead, Lock, Locks, ProcError, ProcResult, SystemInfo,
+};
+
+pub trait DataSource: Send + Sync {
+    /// Opens the process with the given PID
+    fn process(&self, pid: i32) -> ProcResult<Process>;
+    /// Opens the process that `self` points to.  For the live system, this is procdump itself
+    fn myself(&self) -> ProcResult<Process>;
+    /// Opens every process.  Processes that can't be opened are skipped
+    fn all_processes(&self) -> ProcResult<Vec<Process>>;
+    /// All of the file locks on the system (from `/proc/locks`)
+    fn locks(&self) -> ProcResult<Vec<Lock>>;
+    /// The cgroup controllers that are compiled into the kernel (from `/proc/cgroups`)
+    fn cgroups(&self) -> ProcResult<Vec<CGroupController>>;
+    /// Boot time, clock ticks, and page size, which are needed to make sense of some fields
+    fn system_info(&self) -> &SystemInfo;
+    /// The directory that processes are read from, like `/proc`
+    fn root(&self) -> &Path;
+    /// The time that a sample read right now was taken at.  This is only different from
+    /// [`Instant::now`] when replaying a recording, where time follows the recording instead
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+    /// The wall-clock time that goes with [`now`](DataSource::now)
+    fn wall_clock(&self) -> chrono::DateTime<chrono::Local> {
+        chrono::Local::now()
+    }
+    /// Whether the processes are running right now on this system, as opposed to being read from a
+    /// recording or a copy of `/proc`.  Only then can something be done to them, like sending a signal
+    fn is_live(&self) -> bool {
+        false
+    }
+}
+
+/// A process, told apart from any later process that reuses its PID by the time it started.
+///
+/// A [`Process`] keeps reading the same process for as long as it's open, but a PID can't be
+/// trusted on its own once the process might have exited: opening it again can find an unrelated
+/// process.  Anything that holds on to a PID to open it later should hold one of these instead
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProcessId {
+    pub pid: i32,
+    /// In clock ticks since boot, like `Stat::starttime`
+    pub starttime: u64,
+}
+
+impl ProcessId {
+    pub fn of(proc: &Process) -> ProcResult<ProcessId> {
+        Ok(ProcessId {
+            pid: proc.pid,
+            starttime: proc.stat()?.starttime,
+        })
+    }
+
+    /// Opens the process.  If its PID now belongs to another process, this fails the same way as
+    /// if the PID didn't exist
+    pub fn open(&self, source: &dyn DataSource) -> ProcResult<Process> {
+        let proc = source.process(self.pid)?;
+        if ProcessId::of(&proc)? != *self {
+            return Err(ProcError::NotFound(None));
+        }
+        Ok(proc)
+    }
+
+    /// True if the PID still belongs to this process (which might be a zombie)
+    pub fn is_current(&self, source: &dyn DataSource) -> bool {
+        self.open(source).is_ok()
+    }
+}
+
+/// Reads from the live `/proc` of the running system
+#[derive(Debug, Default)]
+pub struct LiveSource;
+
+impl DataSource for LiveSource {
+    fn process(&self, pid: i32) -> ProcResult<Process> {
+        Process::new(pid)
+    }
+    fn myself(&self) -> ProcResult<Process> {
+        Process::myself()
+    }
+    fn all_processes(&self) -> ProcResult<Vec<Process>> {
+        Ok(all_processes()?.filter_map(|p| p.ok()).collect())
+    }
+    fn locks(&self) -> ProcResult<Vec<Lock>> {
+        procfs::locks()
+    }
+    fn cgroups(&self) -> ProcResult<Vec<CGroupController>> {
+        procfs::cgroups().map(|c| c.0)
+    }
+    fn system_info(&self) -> &SystemInfo {
+        procfs::current_system_info()
+    }
+    fn root(&self) -> &Path {
+        Path::new("/proc")
+    }
+    fn is_live(&self) -> bool {
+        true
+    }
+}
+
+/// Reads from a directory laid out like `/proc`, such as a capture made by
+/// [`capture`](crate::capture::capture) (`--capture`), or the test fixtures in `tests/fixtures`.
+///
+/// Files that weren't captured just show up as errors when they're read.
+///
+/// Clock ticks and page size can't be read from such a directory, so they default to the values
+/// used by almost every Linux system (100 and 4096).  The boot time is read from the `btime` line
+/// of `<root>/stat`, if it exists.
+#[derive(Debug)]
+pub struct FixtureSource {
+    root: PathBuf,
+    pub system_info: ExplicitSystemInfo,
+}
+
+impl FixtureSource {
+    pub fn new(root: impl Into<PathBuf>) -> FixtureSource {
+        let root = root.into();
+        let boot_time_secs = read_to_string(root.join("stat"))
+            .ok()
+            .and_then(|stat| {
+                stat.lines()
+                    .find_map(|line| line.strip_prefix("btime "))
+                    .and_then(|btime| btime.trim().parse().ok())
+            })
+            .unwrap_or(0);
+        FixtureSource {
+            root,
+            system_info: ExplicitSystemInfo {
+                boot_time_secs,
+                ticks_per_second: 100,
+                page_size: 4096,
+                is_little_endian: cfg!(target_endian = "little"),
+            },
+        }
+    }
+}
+
+impl DataSource for FixtureSource {
+    fn process(&self, pid: i32) -> ProcResult<Process> {
+        Process::new_with_root(self.root.join(pid.to_string()))
+    }
+    fn myself(&self) -> ProcResult<Process> {
+        Process::new_with_root(self.root.join("self"))
+    }
+    fn all_processes(&self) -> ProcResult<Vec<Process>> {
+        Ok(all_processes_with_root(&self.root)?.filter_map(|p| p.ok()).collect())
+    }
+    fn locks(&self) -> ProcResult<Vec<Lock>> {
+        Locks::from_file(self.root.join("locks")).map(|l| l.0)
+    }
+    fn cgroups(&self) -> ProcResult<Vec<CGroupController>> {
+        procfs::CGroupControllers::from_file(self.root.join("cgroups")).map(|c| c.0)
+    }
+    fn system_info(&self) -> &SystemInfo {
+        &self.system_info
+    }
+    fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+/// The directory of test fixtures that ships with the source
+#[cfg(test)]
+pub(crate) fn fixture() -> FixtureSource {
+    FixtureSource::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/proc"))
+}
+
+/// A line of `/proc/<pid>/stat` for a sleeping process that has used the given CPU time (in clock
+/// ticks)
+#[cfg(test)]
+pub(crate) fn fake_stat_line(pid: i32, comm: &str, utime: u64, stime: u64) -> String {
+    format!(
+        "{pid} ({comm}) S 1 {pid} {pid} 0 -1 4194304 80 0 0 0 {utime} {stime} 0 0 20 0 1 0 5000 2703360 284 \
+         18446744073709551615 1 1 1 0 0 0 0 0 0 0 0 0 17 0 0 0 0 0 0 1 1 1 1 1 1 1 0\n"
+    )
+}
+
+/// [`fake_stat_line`], parsed
+#[cfg(test)]
+pub(crate) fn fake_stat(pid: i32, comm: &str, utime: u64, stime: u64) -> procfs::process::Stat {
+    procfs::process::Stat::from_read(fake_stat_line(pid, comm, utime, stime).as_bytes()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fixture, DataSource, ProcessId};
+
+    #[test]
+    fn test_process_id() {
+        let source = fixture();
+        let id = ProcessId::of(&source.process(101).unwrap()).unwrap();
+        assert_eq!(
+            id,
+            ProcessId {
+                pid: 101,
+                starttime: 5001
+            }
+        );
+        assert_eq!(id.open(&source).unwrap().pid, 101);
+        // the same PID, but a process that started at another time
+        let reused = ProcessId { starttime: 9000, ..id };
+        assert!(!reused.is_current(&source));
+        assert!(id.is_current(&source));
+    }
+}
@@ -0,0 +1,278 @@
+//! Decides when to write a core dump or a report, from conditions like "CPU above 90% for 10
+//! seconds".
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use procfs::process::Process;
+
+use crate::{collect::StatDelta, util::fmt_bytes};
+
+/// The conditions that trigger a dump.  A dump is written when any of them is met
+#[derive(Debug, Clone)]
+pub struct Triggers {
+    /// CPU usage, in percent of one core
+    pub cpu: Option<f32>,
+    /// How long the CPU usage has to stay above `cpu`
+    pub cpu_for: Duration,
+    /// Resident memory, in bytes
+    pub rss: Option<u64>,
+    pub fds: Option<usize>,
+    pub threads: Option<u64>,
+    /// How long a thread has to be in uninterruptible sleep (D state)
+    pub stuck_for: Option<Duration>,
+    /// When the process exits
+    pub on_exit: bool,
+}
+
+impl Default for Triggers {
+    fn default() -> Triggers {
+        Triggers {
+            cpu: None,
+            cpu_for: Duration::from_secs(10),
+            rss: None,
+            fds: None,
+            threads: None,
+            stuck_for: None,
+            on_exit: false,
+        }
+    }
+}
+
+impl Triggers {
+    /// True if there's nothing to trigger on
+    pub fn is_empty(&self) -> bool {
+        self.cpu.is_none()
+            && self.rss.is_none()
+            && self.fds.is_none()
+            && self.threads.is_none()
+            && self.stuck_for.is_none()
+            && !self.on_exit
+    }
+}
+
+/// What the triggers look at, read from the process at one point in time
+#[derive(Debug, Clone, Default)]
+pub struct Sample {
+    pub cpu: f32,
+    pub rss: u64,
+    pub fds: usize,
+    pub threads: u64,
+    /// The threads that are in D state
+    pub stuck: Vec<i32>,
+}
+
+impl Sample {
+    /// Reads a sample from `proc`.  `stat_d` has to have been updated just before
+    pub fn read(proc: &Process, stat_d: &StatDelta<procfs::process::Stat>) -> Sample {
+        let stat = stat_d.latest();
+        let stuck = proc
+            .tasks()
+            .map(|tasks| {
+                tasks
+                    .flatten()
+                    .filter(|task| task.stat().is_ok_and(|s| s.state == 'D'))
+                    .map(|task| task.tid)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Sample {
+            cpu: stat_d.cpu_percentage(),
+            rss: stat.rss * procfs::page_size(),
+            // not fd_count(), which relies on the size of the fd directory
+            fds: proc.fd().map_or(0, |fds| fds.count()),
+            threads: stat.num_threads.max(0) as u64,
+            stuck,
+        }
+    }
+}
+
+/// Watches the samples of a process, and says when a dump is due
+pub struct Monitor {
+    triggers: Triggers,
+    max_dumps: usize,
+    cooldown: Duration,
+    dumps: usize,
+    last_dump: Option<Instant>,
+    /// When the CPU usage went above the limit
+    cpu_since: Option<Instant>,
+    /// When each thread was first seen in D state
+    stuck_since: HashMap<i32, Instant>,
+}
+
+impl Monitor {
+    /// Writes at most `max_dumps` dumps, at least `cooldown` apart
+    pub fn new(triggers: Triggers, max_dumps: usize, cooldown: Duration) -> Monitor {
+        Monitor {
+            triggers,
+            max_dumps,
+            cooldown,
+            dumps: 0,
+            last_dump: None,
+            cpu_since: None,
+            stuck_since: HashMap::new(),
+        }
+    }
+
+    /// Looks at a new sample, taken at `now`.  If a dump should be written, this returns the
+    /// reason for it, and counts it as written
+    pub fn check(&mut self, sample: &Sample, now: Instant) -> Option<String> {
+        let reason = self.reason(sample, now)?;
+        let cooling = self
+            .last_dump
+            .is_some_and(|last| now.saturating_duration_since(last) < self.cooldown);
+        if self.done() || cooling {
+            return None;
+        }
+        self.dumps += 1;
+        self.last_dump = Some(now);
+        Some(reason)
+    }
+
+    /// Called once the process has exited.  Returns the reason for a dump, if one is wanted
+    pub fn exited(&mut self) -> Option<String> {
+        if !self.triggers.on_exit || self.done() {
+            return None;
+        }
+        self.dumps += 1;
+        Some("the process exited".to_string())
+    }
+
+    /// Takes back the last dump that [`check`](Monitor::check) asked for, because it couldn't be
+    /// written.  The cooldown still applies, so a dump that keeps failing isn't retried on every sample
+    pub fn dump_failed(&mut self) {
+        self.dumps = self.dumps.saturating_sub(1);
+    }
+
+    /// True once every dump has been written
+    pub fn done(&self) -> bool {
+        self.dumps >= self.max_dumps
+    }
+
+    pub fn dumps(&self) -> usize {
+        self.dumps
+    }
+
+    fn reason(&mut self, sample: &Sample, now: Instant) -> Option<String> {
+        let t = &self.triggers;
+        // this has to keep track of time whether or not another trigger fires
+        self.stuck_since.retain(|tid, _| sample.stuck.contains(tid));
+        for &tid in &sample.stuck {
+            self.stuck_since.entry(tid).or_insert(now);
+        }
+        let mut reasons = Vec::new();
+
+        match t.cpu {
+            Some(limit) if sample.cpu > limit => {
+                let since = *self.cpu_since.get_or_insert(now);
+                let over = now.saturating_duration_since(since);
+                if over >= t.cpu_for {
+                    reasons.push(format!(
+                        "CPU usage has been above {limit}% for {}s (now {:.1}%)",
+                        over.as_secs(),
+                        sample.cpu
+                    ));
+                }
+            }
+            _ => self.cpu_since = None,
+        }
+        if let Some(limit) = t.rss.filter(|&limit| sample.rss > limit) {
+            reasons.push(format!(
+                "RSS is {} (limit {})",
+                fmt_bytes(sample.rss, "B"),
+                fmt_bytes(limit, "B")
+            ));
+        }
+        if let Some(limit) = t.fds.filter(|&limit| sample.fds > limit) {
+            reasons.push(format!("{} open files (limit {limit})", sample.fds));
+        }
+        if let Some(limit) = t.threads.filter(|&limit| sample.threads > limit) {
+            reasons.push(format!("{} threads (limit {limit})", sample.threads));
+        }
+        if let Some(limit) = t.stuck_for {
+            let mut stuck: Vec<(i32, Duration)> = self
+                .stuck_since
+                .iter()
+                .map(|(&tid, &since)| (tid, now.saturating_duration_since(since)))
+                .filter(|(_, d)| *d >= limit)
+                .collect();
+            stuck.sort();
+            if let Some((tid, d)) = stuck.first() {
+                reasons.push(format!("thread {tid} has been in D state for {}s", d.as_secs()));
+            }
+        }
+
+        if reasons.is_empty() {
+            None
+        } else {
+            Some(reasons.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{Monitor, Sample, Triggers};
+
+    #[test]
+    fn test_monitor() {
+        let triggers = Triggers {
+            cpu: Some(80.0),
+            cpu_for: Duration::from_secs(10),
+            fds: Some(100),
+            stuck_for: Some(Duration::from_secs(5)),
+            ..Triggers::default()
+        };
+        let mut monitor = Monitor::new(triggers, 2, Duration::from_secs(60));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let busy = Sample {
+            cpu: 95.0,
+            ..Sample::default()
+        };
+
+        // the CPU has to stay busy for 10 seconds in a row
+        assert_eq!(monitor.check(&busy, at(0)), None);
+        assert_eq!(monitor.check(&busy, at(5)), None);
+        assert_eq!(monitor.check(&Sample::default(), at(6)), None);
+        assert_eq!(monitor.check(&busy, at(7)), None);
+        let reason = monitor.check(&busy, at(17)).unwrap();
+        assert!(reason.starts_with("CPU usage has been above 80% for 10s"), "{reason}");
+        assert_eq!(monitor.dumps(), 1);
+
+        // nothing more until the cooldown is over
+        let files = Sample {
+            fds: 101,
+            ..Sample::default()
+        };
+        assert_eq!(monitor.check(&files, at(30)), None);
+        assert_eq!(monitor.check(&files, at(77)).unwrap(), "101 open files (limit 100)");
+        assert!(monitor.done());
+        assert_eq!(monitor.check(&files, at(200)), None);
+        assert_eq!(monitor.exited(), None);
+
+        // a thread in D state, timed from when it was first seen there
+        let triggers = Triggers {
+            on_exit: true,
+            ..monitor.triggers.clone()
+        };
+        let mut monitor = Monitor::new(triggers, 2, Duration::ZERO);
+        let stuck = Sample {
+            stuck: vec![42],
+            ..Sample::default()
+        };
+        assert_eq!(monitor.check(&stuck, at(0)), None);
+        assert_eq!(monitor.check(&Sample::default(), at(3)), None);
+        assert_eq!(monitor.check(&stuck, at(4)), None);
+        assert_eq!(
+            monitor.check(&stuck, at(9)).unwrap(),
+            "thread 42 has been in D state for 5s"
+        );
+        assert_eq!(monitor.exited().unwrap(), "the process exited");
+        assert!(monitor.done());
+    }
+}
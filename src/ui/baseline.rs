@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local};
+use crossterm::event::KeyEvent;
+use procdump::{
+    baseline::{self, Diff, Item},
+    util::{fmt_bytes, fmt_duration},
+};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+use super::{get_numlines_from_spans, InputResult, ScrollController};
+
+/// What the tabs showed at one moment, keyed by tab title.  Only the tabs that can be compared are
+/// in here
+pub struct Baseline {
+    pub taken_at: DateTime<Local>,
+    tabs: HashMap<String, Vec<Item>>,
+}
+
+impl Baseline {
+    pub fn new(taken_at: DateTime<Local>) -> Baseline {
+        Baseline {
+            taken_at,
+            tabs: HashMap::new(),
+        }
+    }
+    pub fn insert(&mut self, tab: &str, items: Vec<Item>) {
+        self.tabs.insert(tab.to_string(), items);
+    }
+    pub fn get(&self, tab: &str) -> Option<&[Item]> {
+        self.tabs.get(tab).map(|items| items.as_slice())
+    }
+    /// Says when the baseline was taken, and how long before `now` that was
+    pub fn describe(&self, now: DateTime<Local>) -> String {
+        let mut text = format!("the baseline from {}", self.taken_at.format("%T"));
+        if let Ok(age) = (now - self.taken_at).to_std() {
+            text.push_str(&format!(" ({} ago)", fmt_duration(age)));
+        }
+        text
+    }
+}
+
+/// Shows how the current tab differs from the baseline, in place of the tab's usual contents
+pub struct DiffView {
+    scroll: ScrollController,
+}
+
+impl DiffView {
+    pub fn new() -> DiffView {
+        DiffView {
+            scroll: ScrollController::new(),
+        }
+    }
+    pub fn draw_scrollbar(&self, f: &mut Frame, area: Rect) {
+        self.scroll.draw_scrollbar(f, area)
+    }
+    /// Draws the differences between the items in the baseline and the current ones.  `described`
+    /// is what [`Baseline::describe`] says about the baseline
+    pub fn draw(
+        &mut self,
+        f: &mut Frame,
+        area: Rect,
+        described: &str,
+        base: &[Item],
+        items: &[Item],
+        help_text: &mut Text,
+    ) {
+        help_text.extend(Text::from(Line::from(vec![
+            Span::raw("This shows what changed since the baseline was taken. Press "),
+            Span::styled("v", Style::default().fg(Color::Green)),
+            Span::raw(" to go back to the usual view, and "),
+            Span::styled("b", Style::default().fg(Color::Green)),
+            Span::raw(" to take a new baseline."),
+        ])));
+
+        let diffs = baseline::diff(base, items);
+        let mut text = vec![Line::from(Span::styled(
+            summary(described, &diffs, items.len()),
+            Style::default().add_modifier(Modifier::BOLD),
+        ))];
+        let width = diffs
+            .iter()
+            .map(|d| key_of(d).chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(40);
+        text.extend(diffs.iter().map(|d| diff_line(d, width)));
+
+        let max_scroll = get_numlines_from_spans(text.iter(), area.width as usize) as i32 - area.height as i32;
+        self.scroll.set_max_scroll(max_scroll);
+        let widget = Paragraph::new(text)
+            .block(Block::default().borders(Borders::NONE))
+            .scroll((self.scroll.scroll_offset, 0));
+        f.render_widget(widget, area);
+    }
+    pub fn handle_input(&mut self, input: KeyEvent, height: u16) -> InputResult {
+        self.scroll.handle_input(input, height)
+    }
+}
+
+fn summary(described: &str, diffs: &[Diff], total: usize) -> String {
+    let count = |f: fn(&Diff) -> bool| diffs.iter().filter(|d| f(d)).count();
+    let added = count(|d| matches!(d, Diff::Added(..)));
+    let removed = count(|d| matches!(d, Diff::Removed(..)));
+    let changed = count(|d| matches!(d, Diff::Changed { .. }));
+    format!(
+        "Compared with {described}: {added} added, {removed} removed, {changed} changed, {} the same",
+        total - added - changed
+    )
+}
+
+fn key_of<'a>(diff: &Diff<'a>) -> &'a str {
+    match diff {
+        Diff::Added(item) | Diff::Removed(item) | Diff::Changed { new: item, .. } => &item.key,
+    }
+}
+
+pub(crate) fn fmt_item(item: &Item) -> String {
+    match item.bytes {
+        Some(bytes) if item.value.is_empty() => fmt_bytes(bytes, "B"),
+        Some(bytes) => format!("{} {}", item.value, fmt_bytes(bytes, "B")),
+        None => item.value.clone(),
+    }
+}
+
+fn diff_line(diff: &Diff, width: usize) -> Line<'static> {
+    let key = format!("{:width$} ", key_of(diff));
+    match diff {
+        Diff::Added(item) => {
+            let green = Style::default().fg(Color::Green);
+            Line::from(vec![
+                Span::styled("+ ", green),
+                Span::styled(key, green),
+                Span::raw(fmt_item(item)),
+            ])
+        }
+        Diff::Removed(item) => {
+            let red = Style::default().fg(Color::Red);
+            Line::from(vec![
+                Span::styled("- ", red),
+                Span::styled(key, red),
+                Span::styled(fmt_item(item), Style::default().add_modifier(Modifier::DIM)),
+            ])
+        }
+        Diff::Changed { old, new } => {
+            let yellow = Style::default().fg(Color::Yellow);
+            let mut line = vec![
+                Span::styled("~ ", yellow),
+                Span::styled(key, yellow),
+                Span::raw(format!("{} \u{2192} {}", fmt_item(old), fmt_item(new))),
+            ];
+            match diff.growth() {
+                Some(growth) if growth > 0 => line.push(Span::styled(
+                    format!(" (+{})", fmt_bytes(growth as u64, "B")),
+                    yellow.add_modifier(Modifier::BOLD),
+                )),
+                Some(growth) if growth < 0 => {
+                    line.push(Span::raw(format!(" (-{})", fmt_bytes(growth.unsigned_abs(), "B"))))
+                }
+                _ => {}
+            }
+            Line::from(line)
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! `--compare`: two processes side by side, with the differences between them highlighted.  Each
+//! process gets its own [`App`], so the data is collected just like in the usual UI.
+
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use anyhow::Context;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use procdump::{
+    baseline::{self, Item},
+    source::DataSource,
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Tabs},
+    Frame, Terminal,
+};
+
+use super::{
+    baseline::fmt_item,
+    events::{Event, Events},
+    widgets::{AppWidget, CGroupWidget, EnvWidget, LimitWidget, MapsWidget, MemWidget},
+    InputResult, ScrollController,
+};
+use crate::{cli, App, TabState};
+
+/// The tabs that can be compared
+const TABS: &[(&str, char)] = &[
+    (EnvWidget::TITLE, 'E'),
+    (MapsWidget::TITLE, 'M'),
+    (MemWidget::TITLE, 'R'),
+    (LimitWidget::TITLE, 'L'),
+    (CGroupWidget::TITLE, 'C'),
+];
+
+/// The widest that the key column gets
+const MAX_KEY_WIDTH: usize = 30;
+
+/// How the two sides of a row compare
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Same,
+    Different,
+    OnlyLeft,
+    OnlyRight,
+}
+
+impl Side {
+    /// How the row is marked in plain text
+    fn marker(self) -> char {
+        match self {
+            Side::Same => ' ',
+            Side::Different => '~',
+            Side::OnlyLeft => '<',
+            Side::OnlyRight => '>',
+        }
+    }
+}
+
+/// One row of a tab: the same item (by key) in both processes
+struct Row {
+    side: Side,
+    left: Option<Item>,
+    right: Option<Item>,
+}
+
+struct Compare<'a> {
+    apps: [App<'a>; 2],
+    tab: TabState<'a>,
+    /// Set to hide the rows that are the same in both processes
+    only_diffs: bool,
+    scroll: ScrollController,
+}
+
+impl Compare<'_> {
+    /// The rows of a tab, or an error if either process couldn't be read
+    fn rows(&self, tab: &str) -> Result<Vec<Row>, String> {
+        let items = |app: &App| {
+            let items = if tab == MapsWidget::TITLE {
+                app.maps_widget.libraries()
+            } else {
+                app.items(tab)
+            };
+            items.ok_or_else(|| format!("Unable to read the {tab} tab of process {}", app.proc.pid))
+        };
+        let (left, right) = (items(&self.apps[0])?, items(&self.apps[1])?);
+        let mut pairs = baseline::pair(&left, &right);
+        // what only the left process has comes last, which is out of place in a sorted list
+        let sorted = |items: &[Item]| items.windows(2).all(|w| w[0].key <= w[1].key);
+        if sorted(&left) && sorted(&right) {
+            pairs.sort_by_key(|(l, r)| l.or(*r).map(|item| item.key.clone()));
+        }
+        Ok(pairs
+            .into_iter()
+            .map(|(l, r)| Row {
+                side: match (l, r) {
+                    (Some(l), Some(r)) if l == r => Side::Same,
+                    (Some(..), Some(..)) => Side::Different,
+                    (Some(..), None) => Side::OnlyLeft,
+                    _ => Side::OnlyRight,
+                },
+                left: l.cloned(),
+                right: r.cloned(),
+            })
+            .filter(|row| !self.only_diffs || row.side != Side::Same)
+            .collect())
+    }
+
+    fn differences(&self, tab: &str) -> usize {
+        self.rows(tab)
+            .map_or(0, |rows| rows.iter().filter(|r| r.side != Side::Same).count())
+    }
+
+    fn tick(&mut self) {
+        for app in &mut self.apps {
+            app.tick();
+        }
+    }
+
+    fn handle_input(&mut self, input: KeyEvent, height: u16) -> InputResult {
+        match input.code {
+            KeyCode::Tab | KeyCode::Right => self.tab.select_next(),
+            KeyCode::BackTab | KeyCode::Left => self.tab.select_prev(),
+            KeyCode::Char('d') => self.only_diffs = !self.only_diffs,
+            KeyCode::Char(c) => return self.tab.select_by_char(c),
+            _ => return self.scroll.handle_input(input, height),
+        }
+        InputResult::NeedsRedraw
+    }
+
+    fn draw(&mut self, f: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1), // top line
+                Constraint::Length(3), // tab selector
+                Constraint::Length(1), // which process is on which side
+                Constraint::Min(0),    // the two sides
+                Constraint::Length(1), // help
+            ])
+            .split(f.size());
+
+        let title = format!(
+            "\u{2500} comparing processes {} and {} ",
+            self.apps[0].proc.pid, self.apps[1].proc.pid
+        );
+        f.render_widget(
+            Paragraph::new(format!("{title}{}", "\u{2500}".repeat(chunks[0].width as usize))),
+            chunks[0],
+        );
+
+        let titles: Vec<Line> = self
+            .tab
+            .titles()
+            .into_iter()
+            .zip(self.tab.labels())
+            .map(|(mut title, label)| {
+                let n = self.differences(label);
+                if n > 0 {
+                    title.spans.push(Span::raw(format!(" ({n})")));
+                }
+                title
+            })
+            .collect();
+        let tabs = Tabs::new(titles)
+            .block(Block::default().borders(Borders::TOP | Borders::BOTTOM))
+            .select(self.tab.current())
+            .style(Style::default().fg(Color::Cyan))
+            .highlight_style(Style::default().fg(Color::Yellow));
+        f.render_widget(tabs, chunks[1]);
+
+        let columns = |area: Rect| {
+            Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+                .split(area)
+        };
+        let headers = columns(chunks[2]);
+        for (app, area) in self.apps.iter().zip(headers.iter()) {
+            let header = format!("{} {}", app.proc.pid, app.command());
+            f.render_widget(
+                Paragraph::new(Span::styled(header, Style::default().add_modifier(Modifier::BOLD))),
+                *area,
+            );
+        }
+
+        let body = columns(chunks[3]);
+        match self.rows(self.tab.current_label()) {
+            Ok(rows) => {
+                let width = rows
+                    .iter()
+                    .flat_map(|r| r.left.iter().chain(&r.right))
+                    .map(|item| item.key.chars().count())
+                    .max()
+                    .unwrap_or(0)
+                    .min(MAX_KEY_WIDTH);
+                self.scroll.set_max_scroll(rows.len() as i32 - body[0].height as i32);
+                for (i, area) in body.iter().enumerate() {
+                    let lines: Vec<Line> = rows.iter().map(|row| side_line(row, i == 0, width)).collect();
+                    let block = if i == 0 {
+                        Block::default().borders(Borders::RIGHT)
+                    } else {
+                        Block::default().borders(Borders::NONE)
+                    };
+                    f.render_widget(
+                        Paragraph::new(lines)
+                            .block(block)
+                            .scroll((self.scroll.scroll_offset, 0)),
+                        *area,
+                    );
+                }
+            }
+            Err(e) => f.render_widget(
+                Paragraph::new(Span::styled(e, Style::default().fg(Color::Red))),
+                chunks[3],
+            ),
+        }
+
+        let key = Style::default().fg(Color::Green);
+        f.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled("Tab", key),
+                Span::raw(" switches tabs, "),
+                Span::styled("d", key),
+                Span::raw(if self.only_diffs {
+                    " shows everything, "
+                } else {
+                    " shows only the differences, "
+                }),
+                Span::styled("q", key),
+                Span::raw(" quits.  Differences are in yellow, and what only one process has is in green."),
+            ])),
+            chunks[4],
+        );
+    }
+
+    /// Writes every tab as plain text, each row marked with how its two sides compare
+    fn snapshot(&self) -> Result<String, std::fmt::Error> {
+        let mut out = String::new();
+        writeln!(out, "=== procdump comparison ===")?;
+        writeln!(out, "left: {} {}", self.apps[0].proc.pid, self.apps[0].command())?;
+        writeln!(out, "right: {} {}", self.apps[1].proc.pid, self.apps[1].command())?;
+        for label in self.tab.labels() {
+            writeln!(out, "\n=== {label} ({} differences) ===", self.differences(label))?;
+            match self.rows(label) {
+                Ok(rows) => {
+                    for row in rows {
+                        let text = |item: &Option<Item>| match item {
+                            Some(item) => format!("{} {}", item.key, fmt_item(item)),
+                            None => String::new(),
+                        };
+                        writeln!(
+                            out,
+                            "{} {:<60} | {}",
+                            row.side.marker(),
+                            text(&row.left),
+                            text(&row.right)
+                        )?;
+                    }
+                }
+                Err(e) => writeln!(out, "{e}")?,
+            }
+        }
+        Ok(out.replace('\u{00A0}', " "))
+    }
+}
+
+/// One side of a row
+fn side_line(row: &Row, left: bool, width: usize) -> Line<'static> {
+    let (item, alone) = if left {
+        (&row.left, Side::OnlyLeft)
+    } else {
+        (&row.right, Side::OnlyRight)
+    };
+    let Some(item) = item else {
+        return Line::default();
+    };
+    let style = match row.side {
+        Side::Same => Style::default(),
+        Side::Different => Style::default().fg(Color::Yellow),
+        side if side == alone => Style::default().fg(Color::Green),
+        _ => Style::default(),
+    };
+    Line::from(vec![
+        Span::styled(format!("{:width$} ", item.key), style.add_modifier(Modifier::BOLD)),
+        Span::styled(fmt_item(item), style),
+    ])
+}
+
+/// Runs `--compare`: prints the comparison with `--snapshot`, or else shows it until the user quits
+pub(crate) fn run(source: Arc<dyn DataSource>, (pid1, pid2): (i32, i32), opts: &cli::Options) -> anyhow::Result<()> {
+    let app = |pid: i32| -> anyhow::Result<App<'static>> {
+        let proc = source
+            .process(pid)
+            .with_context(|| format!("Unable to read process {pid}"))?;
+        App::new(source.clone(), proc).with_context(|| format!("Unable to read process {pid}"))
+    };
+    let mut compare = Compare {
+        apps: [app(pid1)?, app(pid2)?],
+        tab: TabState::new(TABS),
+        only_diffs: false,
+        scroll: ScrollController::new(),
+    };
+    if opts.snapshot {
+        print!("{}", compare.snapshot()?);
+        return Ok(());
+    }
+
+    let interval = opts.interval.unwrap_or(super::DEFAULT_INTERVAL);
+    for app in &mut compare.apps {
+        app.interval = interval;
+    }
+    super::set_refresh_interval(interval);
+    crate::set_panic_handler();
+    let events = Events::new(interval);
+
+    let mut stdout = std::io::stdout();
+    crossterm::execute!(stdout, EnterAlternateScreen)?;
+    crossterm::terminal::enable_raw_mode()?;
+    let result = (|| -> anyhow::Result<()> {
+        let mut terminal = Terminal::new(CrosstermBackend::new(&mut stdout))?;
+        terminal.hide_cursor()?;
+        terminal.clear()?;
+        let mut need_redraw = true;
+        let mut body_height = 0;
+        loop {
+            if need_redraw {
+                terminal.draw(|f| {
+                    body_height = f.size().height.saturating_sub(6);
+                    compare.draw(f);
+                })?;
+                need_redraw = false;
+            }
+            match events.rx.recv() {
+                Err(..) => break,
+                Ok(Event::Key(KeyEvent {
+                    code: KeyCode::Esc | KeyCode::Char('q'),
+                    ..
+                })) => break,
+                Ok(Event::Key(KeyEvent {
+                    code: KeyCode::Char('c'),
+                    modifiers,
+                    ..
+                })) if modifiers.contains(KeyModifiers::CONTROL) => break,
+                Ok(Event::Key(k)) => need_redraw = compare.handle_input(k, body_height) != InputResult::None,
+                Ok(Event::Tick) => {
+                    compare.tick();
+                    need_redraw = true;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    })();
+    crossterm::execute!(stdout, LeaveAlternateScreen)?;
+    crossterm::terminal::disable_raw_mode()?;
+    result
+}
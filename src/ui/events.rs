@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyEvent, MouseEvent};
+use procdump::{pidfd::PidFd, source::ProcessId};
+
+#[derive(Debug)]
+pub(crate) enum Event {
+    Key(KeyEvent),
+    Mouse(#[allow(dead_code)] MouseEvent),
+    Tick,
+    /// This process has exited
+    Exited(ProcessId),
+}
+
+pub(crate) struct Events {
+    pub rx: mpsc::Receiver<Event>,
+    tx: mpsc::Sender<Event>,
+    /// Time between ticks, in milliseconds
+    interval: Arc<AtomicU64>,
+    exit_watch: Arc<Mutex<ExitWatch>>,
+}
+
+/// Which processes have a thread waiting for them to exit
+#[derive(Default)]
+struct ExitWatch {
+    /// The process that's shown.  Watchers for any other process stop
+    wanted: Option<ProcessId>,
+    /// The processes that have a watcher running
+    running: HashSet<ProcessId>,
+}
+
+/// How often an exit watcher checks whether it's still wanted
+const EXIT_WATCH_CHECK: Duration = Duration::from_millis(500);
+
+impl Events {
+    /// Starts reading input, and sending a tick every `interval`
+    pub fn new(interval: Duration) -> Events {
+        // spawn a thread to handle keyboard input
+        let (tx, rx) = mpsc::channel();
+        let kbd_tx = tx.clone();
+        let tick_tx = tx.clone();
+        thread::Builder::new()
+            .name("kbd-reader".to_owned())
+            .spawn(move || {
+                use crossterm::event::{read, Event};
+
+                loop {
+                    let evt = read();
+                    let sent = match evt {
+                        Err(..) => return,
+                        Ok(Event::Key(e)) => kbd_tx.send(self::Event::Key(e)),
+                        Ok(Event::Mouse(m)) => kbd_tx.send(self::Event::Mouse(m)),
+                        // Ok(Event::Unsupported(bytes)) => match bytes.as_slice() {
+                        //     // manual parsing of cursor movement keys in application mode
+                        //     [0x1b, 79, 65] => kbd_tx.send(self::Event::Key(Key::Up)),
+                        //     [0x1b, 79, 66] => kbd_tx.send(self::Event::Key(Key::Down)),
+                        //     [0x1b, 79, 67] => kbd_tx.send(self::Event::Key(Key::Right)),
+                        //     [0x1b, 79, 68] => kbd_tx.send(self::Event::Key(Key::Left)),
+                        //     _ => continue,
+                        // },
+                        _ => continue,
+                    };
+                    if sent.is_err() {
+                        return;
+                    }
+                }
+            })
+            .unwrap();
+
+        let interval = Arc::new(AtomicU64::new(interval.as_millis() as u64));
+        let tick_interval = interval.clone();
+        thread::Builder::new()
+            .name("tick".to_owned())
+            .spawn(move || loop {
+                // sleep in small steps, so that a change to the interval takes effect right away
+                let start = Instant::now();
+                loop {
+                    let interval = Duration::from_millis(tick_interval.load(Ordering::Relaxed));
+                    let elapsed = start.elapsed();
+                    if elapsed >= interval {
+                        break;
+                    }
+                    thread::sleep((interval - elapsed).min(Duration::from_millis(50)));
+                }
+                if tick_tx.send(self::Event::Tick).is_err() {
+                    return;
+                }
+            })
+            .unwrap();
+
+        Events {
+            rx,
+            tx,
+            interval,
+            exit_watch: Arc::default(),
+        }
+    }
+
+    pub fn set_interval(&self, interval: Duration) {
+        self.interval.store(interval.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Sends [`Event::Exited`] as soon as the process behind `pidfd` exits.  Only the last process
+    /// passed in is watched: the watcher of a process that was shown before stops (and closes its
+    /// pidfd) once another one is shown, and a process that's still being watched isn't watched twice
+    pub fn watch_exit(&self, id: ProcessId, pidfd: PidFd) {
+        let mut watch = self.exit_watch.lock().unwrap();
+        watch.wanted = Some(id);
+        if !watch.running.insert(id) {
+            return;
+        }
+        drop(watch);
+
+        let tx = self.tx.clone();
+        let exit_watch = self.exit_watch.clone();
+        thread::Builder::new()
+            .name("exit-watcher".to_owned())
+            .spawn(move || loop {
+                let exited = pidfd.wait_exit(Some(EXIT_WATCH_CHECK));
+                let mut watch = exit_watch.lock().unwrap();
+                if exited.is_err() || watch.wanted != Some(id) {
+                    watch.running.remove(&id);
+                    return;
+                }
+                if let Ok(true) = exited {
+                    watch.running.remove(&id);
+                    let _ = tx.send(self::Event::Exited(id));
+                    return;
+                }
+            })
+            .unwrap();
+    }
+}
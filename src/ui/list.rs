@@ -0,0 +1,618 @@
+//! `--list`: a small `top` for a few processes, picked by PID or by `--name`, `--unit`, `--user`,
+//! and `--cgroup`.  Enter shows everything about one of them, in the same view as a single process,
+//! and Esc goes back to the list.
+//!
+//! `--group` is the same list, with the totals of all the processes and graphs of them.  There, a
+//! PID stands for the process and all of its descendants.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use procdump::{
+    collect::{Collector, IoRates, MemCollector, StatDelta},
+    select::Filter,
+    source::{DataSource, ProcessId},
+    util::{fmt_bytes, fmt_rate, ProcessTree},
+};
+use procfs::{
+    process::{Io, Process, Stat},
+    ProcResult,
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Sparkline, Table, TableState},
+    Frame, Terminal,
+};
+
+use super::{
+    events::{Event, Events},
+    InputResult,
+};
+use crate::{cli, App, SparklineData};
+
+/// What a process (or a whole group of them) is using
+#[derive(Debug, Default, Clone, Copy)]
+struct Usage {
+    /// Percent of one core
+    cpu: f32,
+    rss: u64,
+    /// Only read for `--group`
+    pss: Option<u64>,
+    threads: i64,
+    fds: Option<usize>,
+    /// Bytes per second
+    read: Option<f32>,
+    write: Option<f32>,
+}
+
+impl Usage {
+    fn add(&mut self, other: &Usage) {
+        fn sum<T: std::ops::Add<Output = T>>(a: Option<T>, b: Option<T>) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a + b),
+                (a, b) => a.or(b),
+            }
+        }
+        self.cpu += other.cpu;
+        self.rss += other.rss;
+        self.pss = sum(self.pss, other.pss);
+        self.threads += other.threads;
+        self.fds = sum(self.fds, other.fds);
+        self.read = sum(self.read, other.read);
+        self.write = sum(self.write, other.write);
+    }
+
+    fn cells(&self, pss: bool) -> Vec<Cell<'static>> {
+        let unknown = || "-".to_string();
+        let rate = |rate: Option<f32>| rate.map_or_else(unknown, |r| fmt_rate(r, "B/s"));
+        let mut cells = vec![
+            Cell::from(format!("{:.1}", self.cpu)),
+            Cell::from(fmt_bytes(self.rss, "B")),
+        ];
+        if pss {
+            cells.push(Cell::from(self.pss.map_or_else(unknown, |b| fmt_bytes(b, "B"))));
+        }
+        cells.extend([
+            Cell::from(self.threads.to_string()),
+            Cell::from(self.fds.map_or_else(unknown, |n| n.to_string())),
+            Cell::from(rate(self.read)),
+            Cell::from(rate(self.write)),
+        ]);
+        cells
+    }
+}
+
+/// The headers and widths of the columns.  The PSS column is only there for `--group`
+fn columns(pss: bool) -> (Vec<&'static str>, Vec<Constraint>) {
+    let mut columns = vec![
+        ("PID", 8),
+        ("State", 7),
+        ("CPU%", 6),
+        ("RSS", 11),
+        ("PSS", 11),
+        ("Threads", 8),
+        ("FDs", 6),
+        ("Read", 12),
+        ("Write", 12),
+        ("Command", 0),
+    ];
+    if !pss {
+        columns.retain(|(name, _)| *name != "PSS");
+    }
+    columns
+        .into_iter()
+        .map(|(name, width)| match width {
+            0 => (name, Constraint::Min(0)),
+            w => (name, Constraint::Length(w)),
+        })
+        .unzip()
+}
+
+/// What one row of the list knows about its process
+struct Entry {
+    proc: Process,
+    id: ProcessId,
+    command: String,
+    stat: StatDelta<Stat>,
+    io: Option<StatDelta<Io>>,
+    fds: Option<usize>,
+    /// Only read for `--group`, since reading it takes a while for a big process
+    pss: Option<u64>,
+    read_pss: bool,
+    exited: bool,
+}
+
+impl Entry {
+    fn new(source: &dyn DataSource, proc: Process, read_pss: bool) -> ProcResult<Entry> {
+        let now = source.now();
+        let stat = StatDelta::<Stat>::new(&proc, source.system_info().ticks_per_second(), now)?;
+        let mut entry = Entry {
+            id: ProcessId {
+                pid: proc.pid,
+                starttime: stat.latest().starttime,
+            },
+            command: String::new(),
+            io: StatDelta::<Io>::new(&proc, now).ok(),
+            stat,
+            fds: None,
+            pss: None,
+            read_pss,
+            exited: false,
+            proc,
+        };
+        entry.read();
+        Ok(entry)
+    }
+
+    /// Takes a new sample of the counters, unless the process has exited
+    fn update(&mut self, source: &dyn DataSource) {
+        if self.exited {
+            return;
+        }
+        if !self.proc.is_alive() || !self.id.is_current(source) {
+            self.exited = true;
+            return;
+        }
+        let now = source.now();
+        self.stat.update(&self.proc, now);
+        if let Some(io) = &mut self.io {
+            io.update(&self.proc, now);
+        }
+        self.read();
+    }
+
+    /// Reads what isn't a counter
+    fn read(&mut self) {
+        self.command = match self.proc.cmdline() {
+            Ok(cmdline) if !cmdline.is_empty() => cmdline.join(" "),
+            _ => self.stat.latest().comm.clone(),
+        };
+        self.fds = self.proc.fd_count().ok();
+        if self.read_pss {
+            self.pss = MemCollector
+                .collect(&self.proc)
+                .ok()
+                .and_then(|mem| mem.get("Pss").copied());
+        }
+    }
+
+    /// What the process is using, unless it has exited
+    fn usage(&self, page_size: u64) -> Option<Usage> {
+        if self.exited {
+            return None;
+        }
+        let stat = self.stat.latest();
+        let rates = self.io.as_ref().map(IoRates::new);
+        Some(Usage {
+            cpu: self.stat.cpu_percentage(),
+            rss: stat.rss * page_size,
+            pss: self.pss,
+            threads: stat.num_threads,
+            fds: self.fds,
+            read: rates.map(|r| r.read_bytes),
+            write: rates.map(|r| r.write_bytes),
+        })
+    }
+
+    fn row(&self, page_size: u64) -> Row<'static> {
+        let mut cells = vec![Cell::from(self.id.pid.to_string())];
+        match self.usage(page_size) {
+            Some(usage) => {
+                cells.push(Cell::from(self.stat.latest().state.to_string()));
+                cells.extend(usage.cells(self.read_pss));
+            }
+            None => {
+                cells.push(Cell::from("exited"));
+                let (headers, _) = columns(self.read_pss);
+                cells.extend((3..headers.len()).map(|_| Cell::from("-")));
+            }
+        }
+        cells.push(Cell::from(self.command.clone()));
+        let row = Row::new(cells);
+        if self.exited {
+            row.style(Style::default().add_modifier(Modifier::DIM))
+        } else {
+            row
+        }
+    }
+}
+
+/// Which processes are in the list
+enum Members {
+    /// The PIDs that were given, which stay in the list after they exit
+    Fixed,
+    /// Every process that matches, looked for again on every refresh
+    Matching(Filter),
+    /// These processes and all of their descendants, looked for again on every refresh
+    Subtrees(Vec<ProcessId>),
+}
+
+/// The history of the totals of a group, for `--group`
+struct Graphs {
+    cpu: SparklineData,
+    /// In KB, the PSS (or the RSS if the PSS can't be read)
+    mem: SparklineData,
+    /// In KB/s, reads and writes together
+    io: SparklineData,
+}
+
+impl Graphs {
+    fn push(&mut self, total: &Usage) {
+        self.cpu.push(total.cpu.round() as u64);
+        self.mem.push(total.pss.unwrap_or(total.rss) / 1000);
+        let io = total.read.unwrap_or(0.0) + total.write.unwrap_or(0.0);
+        self.io.push((io / 1000.0).round() as u64);
+    }
+}
+
+struct ProcessList<'o> {
+    source: Arc<dyn DataSource>,
+    opts: &'o cli::Options,
+    members: Members,
+    entries: Vec<Entry>,
+    /// Set for `--group`
+    graphs: Option<Graphs>,
+    state: TableState,
+    interval: Duration,
+    frozen: bool,
+    /// Shown on the top line, like when the processes couldn't be listed
+    status: Option<String>,
+    /// The process that is shown in full, instead of the list
+    detail: Option<App<'static>>,
+}
+
+impl ProcessList<'_> {
+    /// Looks for the processes that are in the list now.  `None` means that the list doesn't change
+    fn find(&self) -> anyhow::Result<Option<Vec<Process>>> {
+        match &self.members {
+            Members::Fixed => Ok(None),
+            Members::Matching(filter) => crate::find_processes(self.opts, &*self.source, filter).map(Some),
+            Members::Subtrees(roots) => {
+                let tree = ProcessTree::new(&*self.source, None)?;
+                let mut seen = HashSet::new();
+                let mut procs = Vec::new();
+                for root in roots {
+                    if tree.entries.get(&root.pid).map(|e| e.starttime) != Some(root.starttime) {
+                        continue;
+                    }
+                    for entry in tree.subtree(root.pid) {
+                        if seen.insert(entry.pid) {
+                            procs.extend(self.source.process(entry.pid).ok());
+                        }
+                    }
+                }
+                Ok(Some(procs))
+            }
+        }
+    }
+
+    /// Brings the list up to date with the processes that are in it now.  The ones that were
+    /// already there keep their history
+    fn refresh_members(&mut self) {
+        match self.find() {
+            Ok(Some(procs)) => {
+                let mut old = std::mem::take(&mut self.entries);
+                for proc in procs {
+                    let Ok(id) = ProcessId::of(&proc) else {
+                        continue;
+                    };
+                    match old.iter().position(|e| e.id == id && !e.exited) {
+                        Some(idx) => self.entries.push(old.swap_remove(idx)),
+                        None => self
+                            .entries
+                            .extend(Entry::new(&*self.source, proc, self.graphs.is_some()).ok()),
+                    }
+                }
+                self.status = None;
+            }
+            Ok(None) => {}
+            Err(e) => self.status = Some(format!("{e:#}")),
+        }
+        let last = self.entries.len().saturating_sub(1);
+        if self.state.selected().is_some_and(|idx| idx > last) {
+            self.state.select(Some(last));
+        }
+    }
+
+    /// The totals of the processes that haven't exited
+    fn total(&self) -> Usage {
+        let page_size = self.source.system_info().page_size();
+        let mut total = Usage::default();
+        for usage in self.entries.iter().filter_map(|e| e.usage(page_size)) {
+            total.add(&usage);
+        }
+        total
+    }
+
+    fn tick(&mut self) {
+        if let Some(app) = &mut self.detail {
+            if !app.frozen {
+                app.tick();
+            }
+        }
+        if self.frozen {
+            return;
+        }
+        for entry in &mut self.entries {
+            entry.update(&*self.source);
+        }
+        self.refresh_members();
+        let total = self.total();
+        if let Some(graphs) = &mut self.graphs {
+            graphs.push(&total);
+        }
+    }
+
+    /// Shows the selected process in full
+    fn open(&mut self) {
+        let Some(entry) = self.state.selected().and_then(|idx| self.entries.get(idx)) else {
+            return;
+        };
+        let app = entry
+            .id
+            .open(&*self.source)
+            .and_then(|proc| App::new(self.source.clone(), proc));
+        match app {
+            Ok(mut app) => {
+                app.interval = self.interval;
+                app.frozen = self.frozen;
+                self.detail = Some(app);
+                self.status = None;
+            }
+            Err(e) => self.status = Some(format!("Unable to read process {}: {e}", entry.id.pid)),
+        }
+    }
+
+    /// Goes back to the list from the full view of a process
+    fn close(&mut self) {
+        if let Some(app) = self.detail.take() {
+            self.interval = app.interval;
+            self.frozen = app.frozen;
+        }
+    }
+
+    fn handle_input(&mut self, input: KeyEvent, height: u16) -> InputResult {
+        if let Some(app) = &mut self.detail {
+            return match app.handle_input(input, height) {
+                InputResult::NeedsUpdate => {
+                    if !app.frozen {
+                        app.tick();
+                    }
+                    InputResult::NeedsRedraw
+                }
+                r => r,
+            };
+        }
+        let last = self.entries.len().saturating_sub(1);
+        let selected = self.state.selected().unwrap_or(0);
+        match input.code {
+            KeyCode::Enter => self.open(),
+            KeyCode::Up => self.state.select(Some(selected.saturating_sub(1))),
+            KeyCode::Down => self.state.select(Some((selected + 1).min(last))),
+            KeyCode::Home => self.state.select(Some(0)),
+            KeyCode::End => self.state.select(Some(last)),
+            _ => return InputResult::None,
+        }
+        InputResult::NeedsRedraw
+    }
+
+    /// Draws the list, or the process that's shown in full.  Returns the height that scrolling needs
+    fn draw(&mut self, f: &mut Frame) -> u16 {
+        if let Some(app) = &mut self.detail {
+            return app.draw(f, Some("Esc goes back to the list."));
+        }
+        let group = self.graphs.is_some();
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),                         // top line
+                Constraint::Min(0),                            // the processes
+                Constraint::Length(if group { 1 } else { 0 }), // totals
+                Constraint::Length(if group { 5 } else { 0 }), // graphs
+                Constraint::Length(1),                         // help
+            ])
+            .split(f.size());
+
+        let mut top = format!("\u{2500} {} processes ", self.entries.len());
+        if self.frozen {
+            top.push_str("(paused) ");
+        } else {
+            top.push_str(&format!("(every {:.1}s) ", self.interval.as_secs_f32()));
+        }
+        let mut line = vec![Span::raw(top)];
+        if let Some(status) = &self.status {
+            line.push(Span::styled(status.clone(), Style::default().fg(Color::Red)));
+        }
+        f.render_widget(Paragraph::new(Line::from(line)), chunks[0]);
+
+        let (headers, widths) = columns(group);
+        let page_size = self.source.system_info().page_size();
+        let rows: Vec<Row> = self.entries.iter().map(|e| e.row(page_size)).collect();
+        let table = Table::new(rows, widths.clone())
+            .header(Row::new(headers).style(Style::default().add_modifier(Modifier::BOLD)))
+            .highlight_style(Style::default().fg(Color::Magenta));
+        if self.state.selected().is_none() && !self.entries.is_empty() {
+            self.state.select(Some(0));
+        }
+        f.render_stateful_widget(table, chunks[1], &mut self.state);
+
+        if let Some(graphs) = &self.graphs {
+            let total = self.total();
+            let running = self.entries.iter().filter(|e| !e.exited).count();
+            let mut cells = vec![Cell::from("total"), Cell::from("")];
+            cells.extend(total.cells(true));
+            cells.push(Cell::from(format!("{running} processes")));
+            let totals = Table::new([Row::new(cells)], widths)
+                .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+            f.render_widget(totals, chunks[2]);
+
+            let areas = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Ratio(1, 3),
+                    Constraint::Ratio(1, 3),
+                    Constraint::Ratio(1, 3),
+                ])
+                .split(chunks[3]);
+            let mem = match total.pss {
+                Some(pss) => format!("Memory (PSS): {}", fmt_bytes(pss, "B")),
+                None => format!("Memory (RSS): {}", fmt_bytes(total.rss, "B")),
+            };
+            let io = total.read.unwrap_or(0.0) + total.write.unwrap_or(0.0);
+            draw_graph(f, areas[0], &format!("Cpu Usage: {:.1}%", total.cpu), &graphs.cpu);
+            draw_graph(f, areas[1], &mem, &graphs.mem);
+            draw_graph(f, areas[2], &format!("IO: {}", fmt_rate(io, "B/s")), &graphs.io);
+        }
+
+        let key = Style::default().fg(Color::Green);
+        f.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled("Up/Down", key),
+                Span::raw(" picks a process, "),
+                Span::styled("Enter", key),
+                Span::raw(" shows everything about it, "),
+                Span::styled("q", key),
+                Span::raw(" quits."),
+            ])),
+            chunks[4],
+        );
+        chunks[1].height
+    }
+}
+
+/// Draws the most recent values of a graph that fit
+fn draw_graph(f: &mut Frame, area: Rect, title: &str, data: &SparklineData) {
+    let data = data.as_slice();
+    let start = data.len().saturating_sub(area.width as usize);
+    let widget = Sparkline::default()
+        .block(
+            Block::default()
+                .title(title.to_string())
+                .borders(Borders::TOP | Borders::BOTTOM),
+        )
+        .data(&data[start..]);
+    f.render_widget(widget, area);
+}
+
+/// Runs `--list` and `--group`, until the user quits
+pub(crate) fn run(source: Arc<dyn DataSource>, opts: &cli::Options) -> anyhow::Result<()> {
+    let open = |pid: i32| {
+        source
+            .process(pid)
+            .and_then(|proc| Ok((ProcessId::of(&proc)?, proc)))
+            .with_context(|| format!("Unable to read process {pid}"))
+    };
+    let mut entries = Vec::new();
+    let members = if opts.pids.is_empty() {
+        Members::Matching(crate::process_filter(opts)?.0)
+    } else if opts.group {
+        let mut roots = Vec::new();
+        for &pid in &opts.pids {
+            roots.push(open(pid)?.0);
+        }
+        Members::Subtrees(roots)
+    } else {
+        for &pid in &opts.pids {
+            let (_, proc) = open(pid)?;
+            entries.push(Entry::new(&*source, proc, false).with_context(|| format!("Unable to read process {pid}"))?);
+        }
+        Members::Fixed
+    };
+    let interval = opts.interval.unwrap_or(super::DEFAULT_INTERVAL);
+    let mut list = ProcessList {
+        source,
+        opts,
+        members,
+        entries,
+        graphs: opts.group.then(|| Graphs {
+            cpu: SparklineData::new(),
+            mem: SparklineData::new(),
+            io: SparklineData::new(),
+        }),
+        state: TableState::default(),
+        interval,
+        frozen: false,
+        status: None,
+        detail: None,
+    };
+    list.refresh_members();
+
+    super::set_refresh_interval(interval);
+    crate::set_panic_handler();
+    let events = Events::new(interval);
+
+    let mut stdout = std::io::stdout();
+    crossterm::execute!(stdout, EnterAlternateScreen)?;
+    crossterm::terminal::enable_raw_mode()?;
+    let result = (|| -> anyhow::Result<()> {
+        let mut terminal = Terminal::new(CrosstermBackend::new(&mut stdout))?;
+        terminal.hide_cursor()?;
+        terminal.clear()?;
+        let mut need_redraw = true;
+        let mut height = 0;
+        loop {
+            if need_redraw {
+                terminal.draw(|f| height = list.draw(f))?;
+                need_redraw = false;
+            }
+            let editing = list.detail.as_ref().is_some_and(|app| app.editing());
+            match events.rx.recv() {
+                Err(..) => break,
+                Ok(Event::Key(KeyEvent {
+                    code: KeyCode::Char('c'),
+                    modifiers,
+                    ..
+                })) if modifiers.contains(KeyModifiers::CONTROL) => break,
+                // a filter is being typed in, so keys like q are just text
+                Ok(Event::Key(k)) if editing => need_redraw = list.handle_input(k, height) != InputResult::None,
+                Ok(Event::Key(KeyEvent { code: KeyCode::Esc, .. })) if list.detail.is_some() => {
+                    list.close();
+                    need_redraw = true;
+                }
+                Ok(Event::Key(KeyEvent {
+                    code: KeyCode::Esc | KeyCode::Char('q'),
+                    ..
+                })) => break,
+                Ok(Event::Key(KeyEvent {
+                    code: KeyCode::Char(c @ ('+' | '=' | '-')),
+                    ..
+                })) => {
+                    list.interval = super::step_interval(list.interval, c != '-');
+                    if let Some(app) = &mut list.detail {
+                        app.interval = list.interval;
+                    }
+                    events.set_interval(list.interval);
+                    super::set_refresh_interval(list.interval);
+                    need_redraw = true;
+                }
+                Ok(Event::Key(KeyEvent {
+                    code: KeyCode::Char('p'),
+                    ..
+                })) => {
+                    list.frozen = !list.frozen;
+                    if let Some(app) = &mut list.detail {
+                        app.frozen = list.frozen;
+                    }
+                    need_redraw = true;
+                }
+                Ok(Event::Key(k)) => need_redraw = list.handle_input(k, height) != InputResult::None,
+                Ok(Event::Tick) => {
+                    list.tick();
+                    need_redraw = true;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    })();
+    crossterm::execute!(stdout, LeaveAlternateScreen)?;
+    crossterm::terminal::disable_raw_mode()?;
+    result
+}
@@ -1,16 +1,50 @@
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::layout::Rect;
 use ratatui::terminal::Frame;
+use ratatui::text::{Line, Span};
 use ratatui::widgets::*;
 
+pub mod baseline;
+pub mod compare;
+pub mod events;
+pub mod list;
+pub mod picker;
+pub mod replay;
 pub mod widgets;
 
-const ONE_SECONDS: Duration = Duration::from_secs(1);
+pub(crate) const ONE_SECONDS: Duration = Duration::from_secs(1);
 const TWO_SECONDS: Duration = Duration::from_secs(2);
 const TEN_SECONDS: Duration = Duration::from_secs(10);
 
+/// The time between refreshes, unless `--interval` says otherwise
+pub(crate) const DEFAULT_INTERVAL: Duration = Duration::from_millis(1500);
+/// The shortest and longest refresh intervals that can be picked
+pub(crate) const MIN_INTERVAL: Duration = Duration::from_millis(100);
+pub(crate) const MAX_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The refresh interval that widgets throttle themselves against, in milliseconds
+static REFRESH_INTERVAL_MS: AtomicU64 = AtomicU64::new(DEFAULT_INTERVAL.as_millis() as u64);
+
+pub(crate) fn set_refresh_interval(interval: Duration) {
+    REFRESH_INTERVAL_MS.store(interval.as_millis() as u64, Ordering::Relaxed);
+}
+
+/// Halves the refresh interval (or doubles it if `faster` isn't set), within the limits
+pub(crate) fn step_interval(interval: Duration, faster: bool) -> Duration {
+    let interval = if faster { interval / 2 } else { interval * 2 };
+    interval.clamp(MIN_INTERVAL, MAX_INTERVAL)
+}
+
+/// Scales one of the throttling constants above (which are meant for [`DEFAULT_INTERVAL`]) to the
+/// current refresh interval, so that a widget that updates every other tick keeps doing so
+fn throttle(d: Duration) -> Duration {
+    let interval = REFRESH_INTERVAL_MS.load(Ordering::Relaxed) as f64;
+    d.mul_f64(interval / DEFAULT_INTERVAL.as_millis() as f64)
+}
+
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum InputResult {
     /// The widget needs to be redrawn
@@ -111,3 +145,51 @@ impl ScrollController {
         }
     }
 }
+
+pub(crate) fn get_numlines_from_spans<'t, I>(spans: I, width: usize) -> usize
+where
+    I: Iterator<Item = &'t Line<'t>>,
+{
+    let mut num_lines = 1;
+    for span in spans {
+        num_lines += 1 + (span.width() / width);
+    }
+
+    num_lines
+}
+
+/// Given some text, and a width, try to figure out how many lines it needs
+#[allow(dead_code)]
+pub(crate) fn get_numlines<'t, I>(i: I, width: usize) -> usize
+where
+    I: Iterator<Item = &'t Span<'t>>,
+{
+    let mut cur_line_length = 0;
+    let mut num_lines = 1;
+    for item in i {
+        // we assume that if there is a newline, it will only be at the *end*
+        if item.content.ends_with('\n') {
+            cur_line_length += item.content.len() - 1;
+            num_lines += 1 + (cur_line_length / width);
+            cur_line_length = 0;
+        } else {
+            cur_line_length += item.content.len();
+        }
+    }
+    num_lines += cur_line_length / width;
+
+    num_lines
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::text::Span;
+
+    #[test]
+    fn test_boxsize() {
+        let text = [Span::raw("hi\n"), Span::raw("hey")];
+
+        let l = super::get_numlines(text.iter(), 5);
+        assert_eq!(l, 2);
+    }
+}
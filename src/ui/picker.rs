@@ -0,0 +1,76 @@
+use std::collections::HashSet;
+
+use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use procdump::{source::DataSource, util::ProcessTree};
+use ratatui::{
+    backend::CrosstermBackend,
+    style::{Color, Style},
+    widgets::{Block, Borders, List, ListItem, ListState},
+    Terminal,
+};
+
+/// Asks the user to pick one of several processes, which are shown in process tree order and
+/// indented by their depth in the tree.  Returns `None` if the user quit without picking one
+pub(crate) fn pick(source: &dyn DataSource, pids: &[i32]) -> anyhow::Result<Option<i32>> {
+    let tree = ProcessTree::new(source, None)?;
+    let wanted: HashSet<i32> = pids.iter().copied().collect();
+    let entries: Vec<_> = tree
+        .flatten()
+        .into_iter()
+        .filter(|(_, entry)| wanted.contains(&entry.pid))
+        .collect();
+    let min_depth = entries.iter().map(|(depth, _)| *depth).min().unwrap_or(0);
+    let items: Vec<(i32, String)> = entries
+        .iter()
+        .map(|(depth, entry)| {
+            let indent = "  ".repeat((depth - min_depth) as usize);
+            (entry.pid, format!("{indent}{} {}", entry.pid, entry.cmdline))
+        })
+        .collect();
+    if items.is_empty() {
+        return Ok(None);
+    }
+
+    let mut stdout = std::io::stdout();
+    crossterm::execute!(stdout, EnterAlternateScreen)?;
+    crossterm::terminal::enable_raw_mode()?;
+    let picked = run(&mut stdout, &items);
+    crossterm::execute!(stdout, LeaveAlternateScreen)?;
+    crossterm::terminal::disable_raw_mode()?;
+    picked
+}
+
+fn run(stdout: &mut std::io::Stdout, items: &[(i32, String)]) -> anyhow::Result<Option<i32>> {
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+    terminal.hide_cursor()?;
+    terminal.clear()?;
+
+    let title = format!(
+        "{} processes match. Pick one with Up/Down and Enter, or q to quit",
+        items.len()
+    );
+    let mut state = ListState::default().with_selected(Some(0));
+    loop {
+        terminal.draw(|f| {
+            let list = List::new(items.iter().map(|(_, line)| ListItem::new(line.as_str())))
+                .block(Block::default().title(title.as_str()).borders(Borders::ALL))
+                .highlight_style(Style::default().fg(Color::Magenta));
+            f.render_stateful_widget(list, f.size(), &mut state);
+        })?;
+
+        let selected = state.selected().unwrap_or(0);
+        if let Event::Key(KeyEvent { code, modifiers, .. }) = read()? {
+            match code {
+                KeyCode::Esc | KeyCode::Char('q') => return Ok(None),
+                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return Ok(None),
+                KeyCode::Enter => return Ok(Some(items[selected].0)),
+                KeyCode::Up => state.select(Some(selected.saturating_sub(1))),
+                KeyCode::Down => state.select(Some((selected + 1).min(items.len() - 1))),
+                KeyCode::Home => state.select(Some(0)),
+                KeyCode::End => state.select(Some(items.len() - 1)),
+                _ => {}
+            }
+        }
+    }
+}
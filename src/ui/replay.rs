@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use procdump::{record::Replay, util::fmt_duration};
+
+/// How far `[` and `]` jump in a replay
+const SEEK_STEP: Duration = Duration::from_secs(60);
+
+/// What should happen after a key was pressed during a replay
+pub(crate) enum ReplayAction {
+    /// The key wasn't meant for the replay
+    None,
+    Redraw,
+    /// Move to this frame
+    Seek(usize),
+}
+
+/// Playback state for `--replay`
+pub(crate) struct ReplayControl {
+    pub replay: Replay,
+    pub paused: bool,
+}
+
+impl ReplayControl {
+    pub fn new(replay: Replay) -> ReplayControl {
+        ReplayControl { replay, paused: false }
+    }
+
+    /// Returns the frame to show on the next tick, if the replay is playing
+    pub fn next_frame(&mut self) -> Option<usize> {
+        let next = self.replay.position() + 1;
+        if self.paused {
+            None
+        } else if next < self.replay.len() {
+            Some(next)
+        } else {
+            // stop at the end, so that the last frame can be looked at
+            self.paused = true;
+            None
+        }
+    }
+
+    pub fn handle_input(&mut self, input: KeyEvent) -> ReplayAction {
+        let pos = self.replay.position();
+        match input.code {
+            KeyCode::Char(' ') => {
+                self.paused = !self.paused;
+                ReplayAction::Redraw
+            }
+            KeyCode::Char('.') => {
+                self.paused = true;
+                if pos + 1 < self.replay.len() {
+                    ReplayAction::Seek(pos + 1)
+                } else {
+                    ReplayAction::Redraw
+                }
+            }
+            KeyCode::Char(',') => {
+                self.paused = true;
+                if pos > 0 {
+                    ReplayAction::Seek(pos - 1)
+                } else {
+                    ReplayAction::Redraw
+                }
+            }
+            KeyCode::Char(']') => ReplayAction::Seek(self.replay.position_at(self.replay.elapsed() + SEEK_STEP)),
+            KeyCode::Char('[') => {
+                ReplayAction::Seek(self.replay.position_at(self.replay.elapsed().saturating_sub(SEEK_STEP)))
+            }
+            _ => ReplayAction::None,
+        }
+    }
+
+    /// A short description of where the replay is, for the top line
+    pub fn status(&self) -> String {
+        let wall = chrono::DateTime::parse_from_rfc3339(&self.replay.header.started_at)
+            .map(|start| {
+                let at = start + chrono::Duration::from_std(self.replay.elapsed()).unwrap_or_default();
+                format!("{} ", at.format("%Y-%m-%d %T"))
+            })
+            .unwrap_or_default();
+        format!(
+            "[replay {wall}{}/{} frame {}/{}{}] ",
+            fmt_duration(self.replay.elapsed()),
+            fmt_duration(self.replay.duration()),
+            self.replay.position() + 1,
+            self.replay.len(),
+            if self.paused { " paused" } else { "" }
+        )
+    }
+
+    /// The keys that control the replay, for the help text
+    pub fn help(&self) -> &'static str {
+        "space:pause/play  .,:step  []:seek 1min"
+    }
+}
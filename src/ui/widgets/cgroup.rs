@@ -1,12 +1,13 @@
-use std::{
-    collections::{BTreeSet, HashMap, HashSet},
-    fs::read_to_string,
-    path::PathBuf,
-    time::Instant,
-};
+use std::{collections::BTreeSet, time::Instant};
 
 use crossterm::event::{KeyCode, KeyEvent};
-use procfs::{process::Process, ProcResult, ProcessCGroup};
+use procdump::{
+    baseline::{self, Item},
+    collect::{CGroupCollector, CGroupInfo, Collector},
+    json::{self, Section},
+    source::DataSource,
+};
+use procfs::process::Process;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
@@ -15,58 +16,91 @@ use ratatui::{
     Frame,
 };
 
-use crate::ui::{InputResult, TEN_SECONDS};
+use crate::ui::{throttle, InputResult, TEN_SECONDS};
 
 use super::AppWidget;
 
 pub struct CGroupWidget {
-    proc_groups: ProcResult<Vec<ProcessCGroup>>,
+    collector: CGroupCollector,
+    proc_groups: <CGroupCollector as Collector>::Output,
     last_updated: Instant,
-
-    // map from controller name to mount path
-    v1_controllers: HashMap<BTreeSet<String>, PathBuf>,
     select_idx: u16,
 }
 
 impl CGroupWidget {
-    pub fn new(proc: &Process) -> CGroupWidget {
-        let mut map = HashMap::new();
-
-        // get the list of v1 controllers on this system
-        let groups: HashSet<String> = procfs::cgroups()
-            .ok()
-            .map(|cgcs| cgcs.0)
-            .unwrap_or_default()
-            .into_iter()
-            .filter_map(|cg| if cg.enabled { Some(cg.name) } else { None })
-            .collect();
-
-        if let Ok(mountinfo) = proc.mountinfo() {
-            for mut mi in mountinfo {
-                if mi.fs_type == "cgroup" {
-                    let super_options: HashSet<String> = HashSet::from_iter(mi.super_options.drain().map(|(k, _)| k));
-                    let controllers: BTreeSet<String> = super_options.intersection(&groups).cloned().collect();
-                    map.insert(controllers, mi.mount_point);
-                }
-            }
-        }
-
-        let groups = proc.cgroups().map(|mut l| {
-            l.0.sort_by_key(|g| g.hierarchy);
-            l.0
-        });
-
+    pub fn new(proc: &Process, source: &dyn DataSource) -> CGroupWidget {
+        let mut collector = CGroupCollector::new(source, proc);
         CGroupWidget {
             last_updated: Instant::now(),
-            proc_groups: groups,
-            v1_controllers: map,
+            proc_groups: collector.collect(proc),
+            collector,
             select_idx: 0,
         }
     }
+    /// The control groups, along with the files that were read for each of them
+    pub fn groups(&self) -> Option<&[CGroupInfo]> {
+        self.proc_groups.as_deref().ok()
+    }
+    /// Formats some details about the given cgroup
+    fn details(&self, cg: &CGroupInfo) -> Vec<Line<'static>> {
+        let mut details = Vec::new();
+        let files = &cg.files;
+        let Some(mountpoint) = &cg.mount_point else {
+            details.push(Line::from(Span::raw("This controller isn't supported by procdump")));
+            return details;
+        };
+        let file = |name: &str| files.get(name).map(|s| s.trim());
+
+        details.push(Line::from(Span::raw(format!(
+            "{:?}",
+            BTreeSet::from_iter(cg.controllers.iter())
+        ))));
+        if let (Some(current), Some(max)) = (file("pids.current"), file("pids.max")) {
+            details.push(Line::from(Span::raw(format!("{current} of {max}"))));
+        }
+        if let Some(state) = file("freezer.state") {
+            details.push(Line::from(Span::raw(format!("state: {state}"))));
+        }
+        if let Some(usage) = file("memory.usage_in_bytes") {
+            details.push(Line::from(Span::raw(format!("Group Usage: {usage} bytes"))));
+        }
+        if let Some(limit) = file("memory.limit_in_bytes") {
+            details.push(Line::from(Span::raw(format!("Group Limit: {limit} bytes"))));
+        }
+        if let Some(usage) = file("memory.kmem.usage_in_bytes") {
+            details.push(Line::from(Span::raw(format!("Kernel Usage: {usage} bytes"))));
+        }
+        if let Some(limit) = file("memory.kmem.limit_in_bytes") {
+            details.push(Line::from(Span::raw(format!("Kernel Limit: {limit} bytes"))));
+        }
+        if let Some(stat) = files.get("memory.stat") {
+            details.push(Line::from(vec![Span::raw("stats:\n"), Span::raw(stat.clone())]));
+        }
+        if let Some(classid) = file("net_cls.classid") {
+            details.push(Line::from(Span::raw(format!("Class ID: {classid}"))));
+        }
+        if let Some(idx) = files.get("net_prio.prioidx") {
+            details.push(Line::from(Span::raw(format!("Prioidx: {idx}"))));
+        }
+        if let Some(map) = files.get("net_prio.ifpriomap") {
+            details.push(Line::from(vec![Span::raw("ifpriomap:"), Span::raw(map.clone())]));
+        }
+        if let Some(acct) = file("cpuacct.usage") {
+            details.push(Line::from(Span::raw(format!("Total nanoseconds: {acct}"))));
+        }
+        if let Some(usage_all) = files.get("cpuacct.usage_all") {
+            details.push(Line::from(Span::raw(usage_all.clone())));
+        }
+        details.push(Line::from(Span::raw(format!("--> {mountpoint:?}"))));
+        details.push(Line::from(Span::raw(format!("--> {:?}", cg.pathname))));
+
+        details
+    }
 }
 
 impl AppWidget for CGroupWidget {
     const TITLE: &'static str = "CGroups";
+    type Json = Vec<json::CGroup>;
     fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
         let line = Line::from(vec![
             Span::raw("The "),
@@ -94,84 +128,17 @@ impl AppWidget for CGroupWidget {
             for (idx, cg) in cgroups.iter().enumerate() {
                 let mut line: Vec<Span> = Vec::new();
                 let current = idx == self.select_idx as usize;
-                let groups = BTreeSet::from_iter(cg.controllers.clone());
                 let controller_name = if cg.controllers.is_empty() {
                     "???".to_owned()
                 } else {
                     cg.controllers.join(",")
                 };
-                if let Some(mountpoint) = self.v1_controllers.get(&groups) {
+                if cg.mount_point.is_some() {
                     line.push(Span::styled(
                         format!("{controller_name}: "),
                         if current { green } else { selected },
                     ));
                     line.push(Span::raw(format!("{}\n", cg.pathname)));
-
-                    let root = if cg.pathname.starts_with('/') {
-                        mountpoint.join(&cg.pathname[1..])
-                    } else {
-                        mountpoint.join(&cg.pathname)
-                    };
-
-                    if current {
-                        details.push(Line::from(Span::raw(format!("{groups:?}"))));
-                        if groups.contains("pids") {
-                            let current = read_to_string(root.join("pids.current"));
-                            let max = read_to_string(root.join("pids.max"));
-                            if let (Ok(current), Ok(max)) = (current, max) {
-                                details.push(Line::from(Span::raw(format!("{} of {}", current.trim(), max.trim()))));
-                            }
-                        }
-                        if groups.contains("freezer") {
-                            let state = read_to_string(root.join("freezer.state"));
-                            if let Ok(state) = state {
-                                details.push(Line::from(Span::raw(format!("state: {}", state.trim()))));
-                            }
-                        }
-                        if groups.contains("memory") {
-                            if let Ok(usage) = read_to_string(root.join("memory.usage_in_bytes")) {
-                                details.push(Line::from(Span::raw(format!("Group Usage: {} bytes", usage.trim()))));
-                            }
-                            if let Ok(limit) = read_to_string(root.join("memory.limit_in_bytes")) {
-                                details.push(Line::from(Span::raw(format!("Group Limit: {} bytes", limit.trim()))));
-                            }
-                            if let Ok(usage) = read_to_string(root.join("memory.kmem.usage_in_bytes")) {
-                                details.push(Line::from(Span::raw(format!("Kernel Usage: {} bytes", usage.trim()))));
-                            }
-                            if let Ok(limit) = read_to_string(root.join("memory.kmem.limit_in_bytes")) {
-                                details.push(Line::from(Span::raw(format!("Kernel Limit: {} bytes", limit.trim()))));
-                            }
-                            if let Ok(limit) = read_to_string(root.join("memory.stat")) {
-                                details.push(Line::from(vec![Span::raw("stats:\n"), Span::raw(limit)]));
-                            }
-                        }
-                        if groups.contains("net_cls") {
-                            if let Ok(classid) = read_to_string(root.join("net_cls.classid")) {
-                                details.push(Line::from(Span::raw(format!("Class ID: {}", classid.trim()))));
-                            }
-                        }
-                        if groups.contains("net_prio") {
-                            if let Ok(idx) = read_to_string(root.join("net_prio.prioidx")) {
-                                details.push(Line::from(Span::raw(format!("Prioidx: {idx}"))));
-                            }
-                            if let Ok(map) = read_to_string(root.join("net_prio.ifpriomap")) {
-                                details.push(Line::from(vec![Span::raw("ifpriomap:"), Span::raw(map)]));
-                            }
-                        }
-                        if groups.contains("blkio") {}
-                        if groups.contains("cpuacct") {
-                            if let Ok(acct) = read_to_string(root.join("cpuacct.usage")) {
-                                details.push(Line::from(Span::raw(format!("Total nanoseconds: {}", acct.trim()))));
-                            }
-                            if let Ok(usage_all) = read_to_string(root.join("cpuacct.usage_all")) {
-                                details.push(Line::from(Span::raw(usage_all)));
-                            }
-                        }
-                        {
-                            details.push(Line::from(Span::raw(format!("--> {mountpoint:?}"))));
-                            details.push(Line::from(Span::raw(format!("--> {:?}", cg.pathname))));
-                        }
-                    }
                 } else {
                     line.push(Span::styled(
                         format!("{controller_name}: "),
@@ -182,9 +149,9 @@ impl AppWidget for CGroupWidget {
                         },
                     ));
                     line.push(Span::raw(cg.pathname.to_string()));
-                    if idx == self.select_idx as usize {
-                        details.push(Line::from(Span::raw("This controller isn't supported by procdump")));
-                    }
+                }
+                if current {
+                    details = self.details(cg);
                 }
                 text.push(Line::from(line));
             }
@@ -205,13 +172,10 @@ impl AppWidget for CGroupWidget {
             .wrap(Wrap { trim: false });
         f.render_widget(widget, chunks[1]);
     }
-    fn update(&mut self, proc: &Process) {
-        if self.last_updated.elapsed() > TEN_SECONDS {
-            self.proc_groups = proc.cgroups().map(|mut l| {
-                l.0.sort_by_key(|g| g.hierarchy);
-                l.0
-            });
-            self.last_updated = Instant::now();
+    fn update(&mut self, proc: &Process, now: Instant) {
+        if now.saturating_duration_since(self.last_updated) > throttle(TEN_SECONDS) {
+            self.proc_groups = self.collector.collect(proc);
+            self.last_updated = now;
         }
     }
     fn handle_input(&mut self, input: KeyEvent, _height: u16) -> InputResult {
@@ -236,4 +200,40 @@ impl AppWidget for CGroupWidget {
             _ => InputResult::None,
         }
     }
+    fn snapshot(&self, out: &mut String) -> std::fmt::Result {
+        use std::fmt::Write;
+
+        match &self.proc_groups {
+            Ok(cgroups) => {
+                for cg in cgroups {
+                    let controller_name = if cg.controllers.is_empty() {
+                        "???".to_owned()
+                    } else {
+                        cg.controllers.join(",")
+                    };
+                    writeln!(out, "{controller_name}: {}", cg.pathname)?;
+                    for line in self.details(cg) {
+                        let details: String = line.spans.iter().map(|span| span.content.as_ref()).collect();
+                        for l in details.lines() {
+                            writeln!(out, "    {l}")?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+            Err(e) => writeln!(out, "Error getting cgroups: {e}"),
+        }
+    }
+    fn json(&self) -> Section<Self::Json> {
+        match &self.proc_groups {
+            Ok(cgroups) => Section::data(cgroups.iter().map(json::CGroup::from).collect()),
+            Err(e) => Section::error(e),
+        }
+    }
+    fn items(&self) -> Option<Vec<Item>> {
+        self.proc_groups
+            .as_ref()
+            .ok()
+            .map(|groups| baseline::cgroup_items(groups))
+    }
 }
@@ -1,7 +1,12 @@
-use std::{collections::HashMap, ffi::OsString, time::Instant};
+use std::{collections::BTreeMap, time::Instant};
 
 use crossterm::event::KeyEvent;
-use procfs::{process::Process, ProcError};
+use procdump::{
+    baseline::{self, Item},
+    collect::{Collector, EnvCollector},
+    json::{self, Section},
+};
+use procfs::process::Process;
 use ratatui::{
     layout::Rect,
     style::{Color, Style},
@@ -10,21 +15,23 @@ use ratatui::{
     Frame,
 };
 
-use crate::ui::{InputResult, ScrollController, TWO_SECONDS};
+use crate::ui::{get_numlines_from_spans, throttle, InputResult, ScrollController, TWO_SECONDS};
 
-use super::AppWidget;
+use super::{write_lines, AppWidget};
 
 pub struct EnvWidget {
-    env: Result<HashMap<OsString, OsString>, ProcError>,
+    collector: EnvCollector,
+    env: <EnvCollector as Collector>::Output,
     last_updated: Instant,
     scroll: ScrollController,
 }
 
 impl EnvWidget {
     pub fn new(proc: &Process) -> EnvWidget {
-        let env = proc.environ();
+        let mut collector = EnvCollector;
         EnvWidget {
-            env,
+            env: collector.collect(proc),
+            collector,
             last_updated: Instant::now(),
             scroll: ScrollController::new(),
         }
@@ -32,30 +39,8 @@ impl EnvWidget {
     pub fn draw_scrollbar(&self, f: &mut Frame, area: Rect) {
         self.scroll.draw_scrollbar(f, area)
     }
-}
-
-impl AppWidget for EnvWidget {
-    const TITLE: &'static str = "Env";
-    fn handle_input(&mut self, input: KeyEvent, height: u16) -> InputResult {
-        self.scroll.handle_input(input, height)
-    }
-
-    fn update(&mut self, proc: &Process) {
-        if self.last_updated.elapsed() > TWO_SECONDS {
-            self.env = proc.environ();
-            self.last_updated = Instant::now();
-        }
-    }
-    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
+    fn lines(&self) -> Vec<Line<'static>> {
         let mut text: Vec<Line> = Vec::new();
-
-        let spans = Line::from(vec![
-            Span::raw("The "),
-            Span::styled("Env", Style::default().fg(Color::Yellow)),
-            Span::raw(" tab shows the environment variables for the process"),
-        ]);
-        help_text.extend(Text::from(spans));
-
         match &self.env {
             Err(e) => {
                 text.push(From::from(Span::styled(
@@ -75,7 +60,33 @@ impl AppWidget for EnvWidget {
                 }
             }
         }
-        let max_scroll = crate::get_numlines_from_spans(text.iter(), area.width as usize) as i32 - area.height as i32;
+        text
+    }
+}
+
+impl AppWidget for EnvWidget {
+    const TITLE: &'static str = "Env";
+    type Json = BTreeMap<String, String>;
+    fn handle_input(&mut self, input: KeyEvent, height: u16) -> InputResult {
+        self.scroll.handle_input(input, height)
+    }
+
+    fn update(&mut self, proc: &Process, now: Instant) {
+        if now.saturating_duration_since(self.last_updated) > throttle(TWO_SECONDS) {
+            self.env = self.collector.collect(proc);
+            self.last_updated = now;
+        }
+    }
+    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
+        let spans = Line::from(vec![
+            Span::raw("The "),
+            Span::styled("Env", Style::default().fg(Color::Yellow)),
+            Span::raw(" tab shows the environment variables for the process"),
+        ]);
+        help_text.extend(Text::from(spans));
+
+        let text = self.lines();
+        let max_scroll = get_numlines_from_spans(text.iter(), area.width as usize) as i32 - area.height as i32;
         self.scroll.set_max_scroll(max_scroll);
 
         let widget = Paragraph::new(text)
@@ -84,4 +95,16 @@ impl AppWidget for EnvWidget {
             .scroll((self.scroll.scroll_offset, 0));
         f.render_widget(widget, area);
     }
+    fn snapshot(&self, out: &mut String) -> std::fmt::Result {
+        write_lines(out, &self.lines())
+    }
+    fn json(&self) -> Section<Self::Json> {
+        match &self.env {
+            Ok(map) => Section::data(json::env(map)),
+            Err(e) => Section::error(e),
+        }
+    }
+    fn items(&self) -> Option<Vec<Item>> {
+        self.env.as_ref().ok().map(baseline::env_items)
+    }
 }
@@ -0,0 +1,198 @@
+use std::{collections::VecDeque, fmt::Write as _, path::Path, time::Instant};
+
+use chrono::{DateTime, Local};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use procdump::{
+    collect::{Change, ChangeKind},
+    json::{self, Section},
+};
+use procfs::process::Process;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+use crate::ui::{get_numlines_from_spans, InputResult, ScrollController};
+
+use super::{write_lines, AppWidget};
+
+/// The most events that are kept.  Older ones are dropped
+const MAX_EVENTS: usize = 10_000;
+
+struct Entry {
+    when: DateTime<Local>,
+    change: Change,
+}
+
+impl Entry {
+    fn matches(&self, filter: &str) -> bool {
+        filter.is_empty()
+            || format!("{} {}", self.change.kind, self.change.message)
+                .to_lowercase()
+                .contains(&filter.to_lowercase())
+    }
+}
+
+/// A log of everything that changed in the other tabs, like threads that started and files that
+/// were closed.  The changes are found by the other widgets, and handed over by the app
+pub struct EventsWidget {
+    entries: VecDeque<Entry>,
+    /// Only events that contain this text are shown (ignoring case)
+    filter: String,
+    /// Set while the filter is being typed in
+    editing: bool,
+    scroll: ScrollController,
+}
+
+impl EventsWidget {
+    pub fn new() -> EventsWidget {
+        EventsWidget {
+            entries: VecDeque::new(),
+            filter: String::new(),
+            editing: false,
+            scroll: ScrollController::new(),
+        }
+    }
+    pub fn draw_scrollbar(&self, f: &mut Frame, area: Rect) {
+        self.scroll.draw_scrollbar(f, area)
+    }
+    pub fn push(&mut self, when: DateTime<Local>, change: Change) {
+        if self.entries.len() >= MAX_EVENTS {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Entry { when, change });
+    }
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+    /// True while the filter is being typed in, so every key belongs to this widget
+    pub fn editing(&self) -> bool {
+        self.editing
+    }
+    /// The events that match the filter, oldest first
+    fn shown(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(|e| e.matches(&self.filter))
+    }
+    /// Writes the events that match the filter into a file, oldest first.  Returns how many were
+    /// written
+    pub fn export(&self, path: &Path) -> std::io::Result<usize> {
+        let mut out = String::new();
+        let mut count = 0;
+        for entry in self.shown() {
+            let _ = writeln!(
+                out,
+                "{} {:<7} {}",
+                entry.when.format("%F %T"),
+                entry.change.kind,
+                entry.change.message
+            );
+            count += 1;
+        }
+        std::fs::write(path, out)?;
+        Ok(count)
+    }
+    /// The events that match the filter, newest first
+    fn lines(&self) -> Vec<Line<'static>> {
+        let mut text: Vec<Line> = Vec::new();
+        if self.editing || !self.filter.is_empty() {
+            let shown = self.shown().count();
+            text.push(Line::from(vec![
+                Span::styled("filter: ", Style::default().fg(Color::Green)),
+                Span::raw(self.filter.clone()),
+                Span::styled(if self.editing { "_" } else { "" }, Style::default().fg(Color::Yellow)),
+                Span::raw(format!(" ({shown} of {} events)", self.entries.len())),
+            ]));
+        }
+        for entry in self.shown().collect::<Vec<_>>().into_iter().rev() {
+            let color = match entry.change.kind {
+                ChangeKind::Thread => Color::Blue,
+                ChangeKind::State => Color::Red,
+                ChangeKind::File => Color::Magenta,
+                ChangeKind::Net => Color::Green,
+                ChangeKind::Child => Color::Cyan,
+                ChangeKind::Process => Color::Yellow,
+            };
+            text.push(Line::from(vec![
+                Span::raw(format!("{} ", entry.when.format("%T"))),
+                Span::styled(format!("{:<7} ", entry.change.kind), Style::default().fg(color)),
+                Span::raw(entry.change.message.clone()),
+            ]));
+        }
+        if self.entries.is_empty() {
+            text.push(Line::from(Span::raw("Nothing has changed yet")));
+        }
+        text
+    }
+}
+
+impl AppWidget for EventsWidget {
+    const TITLE: &'static str = "Events";
+    type Json = Vec<json::Event>;
+    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
+        let spans = Line::from(vec![
+            Span::raw("The "),
+            Span::styled("Events", Style::default().fg(Color::Yellow)),
+            Span::raw(
+                " tab lists threads, files, connections and children that came and went, and threads that got \
+                 stuck or became zombies, newest first.  Press ",
+            ),
+            Span::styled("/", Style::default().fg(Color::Green)),
+            Span::raw(" to filter them, and "),
+            Span::styled("s", Style::default().fg(Color::Green)),
+            Span::raw(" to save the ones shown to a file."),
+        ]);
+        help_text.extend(Text::from(spans));
+
+        let text = self.lines();
+        let max_scroll = get_numlines_from_spans(text.iter(), area.width as usize) as i32 - area.height as i32;
+        self.scroll.set_max_scroll(max_scroll);
+
+        let widget = Paragraph::new(text)
+            .block(Block::default().borders(Borders::NONE))
+            .scroll((self.scroll.scroll_offset, 0));
+        f.render_widget(widget, area);
+    }
+    fn update(&mut self, _proc: &Process, _now: Instant) {
+        // the other widgets find the changes, and the app passes them on with `push`
+    }
+    fn handle_input(&mut self, input: KeyEvent, height: u16) -> InputResult {
+        if self.editing {
+            match input.code {
+                KeyCode::Enter => self.editing = false,
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.editing = false;
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) if !input.modifiers.contains(KeyModifiers::CONTROL) => self.filter.push(c),
+                _ => return InputResult::None,
+            }
+            return InputResult::NeedsRedraw;
+        }
+        if input.code == KeyCode::Char('/') {
+            self.editing = true;
+            return InputResult::NeedsRedraw;
+        }
+        self.scroll.handle_input(input, height)
+    }
+    fn snapshot(&self, out: &mut String) -> std::fmt::Result {
+        write_lines(out, &self.lines())
+    }
+    fn json(&self) -> Section<Self::Json> {
+        Section::data(
+            self.entries
+                .iter()
+                .map(|e| json::Event {
+                    time: e.when.to_rfc3339(),
+                    kind: e.change.kind.to_string(),
+                    message: e.change.message.clone(),
+                })
+                .collect(),
+        )
+    }
+}
@@ -1,11 +1,13 @@
-use std::{collections::HashMap, ffi::CString, os::unix::prelude::OsStrExt, time::Instant};
+use std::{sync::Arc, time::Instant};
 
 use crossterm::event::KeyEvent;
-use procfs::{
-    net::{TcpNetEntry, UdpNetEntry, UnixNetEntry},
-    process::{FDTarget, Process},
-    ProcResult,
+use procdump::{
+    baseline::{self, Item},
+    collect::{file_changes, Change, Collector, FilesCollector, OpenFile, Socket},
+    json::{self, Section},
+    source::DataSource,
 };
+use procfs::process::{FDPermissions, FDTarget, Process};
 use ratatui::{
     layout::Rect,
     style::{Color, Modifier, Style},
@@ -14,56 +16,42 @@ use ratatui::{
     Frame,
 };
 
-use crate::{
-    ui::{InputResult, ScrollController, TEN_SECONDS, TWO_SECONDS},
-    util,
-};
+use crate::ui::{get_numlines_from_spans, throttle, InputResult, ScrollController, TWO_SECONDS};
 
-use super::AppWidget;
+use super::{write_lines, AppWidget};
 
 pub struct FilesWidget {
-    fds: ProcResult<Vec<procfs::process::FDInfo>>,
-    locks: ProcResult<Vec<procfs::Lock>>,
-    pipe_inodes: HashMap<u64, (util::ProcessTreeEntry, util::ProcessTreeEntry)>,
-    tcp_map: HashMap<u64, TcpNetEntry>,
-    udp_map: HashMap<u64, UdpNetEntry>,
-    unix_map: HashMap<u64, UnixNetEntry>,
+    collector: FilesCollector,
+    fds: <FilesCollector as Collector>::Output,
     last_updated: Instant,
-    pipes_updated: Instant,
+    changes: Vec<Change>,
     scroll: ScrollController,
 }
 
 impl FilesWidget {
-    pub fn new(proc: &Process) -> FilesWidget {
+    pub fn new(proc: &Process, source: Arc<dyn DataSource>) -> FilesWidget {
+        let mut collector = FilesCollector::new(source);
         FilesWidget {
-            fds: proc.fd().map(|iter| iter.filter_map(|f| f.ok()).collect()),
-            locks: util::get_locks_for_pid(proc.pid),
-            tcp_map: crate::util::get_tcp_table(proc),
-            udp_map: crate::util::get_udp_table(proc),
-            unix_map: crate::util::get_unix_table(proc),
+            fds: collector.collect(proc),
+            collector,
             last_updated: Instant::now(),
-            pipe_inodes: util::get_pipe_pairs(),
-            pipes_updated: Instant::now(),
+            changes: Vec::new(),
             scroll: ScrollController::new(),
         }
     }
+    /// The open files, or nothing if they couldn't be read
+    pub fn files(&self) -> &[OpenFile] {
+        self.fds.as_deref().unwrap_or_default()
+    }
+    /// The changes found by the last refreshes, for the Events tab
+    pub fn take_changes(&mut self) -> Vec<Change> {
+        std::mem::take(&mut self.changes)
+    }
     pub fn draw_scrollbar(&self, f: &mut Frame, area: Rect) {
         self.scroll.draw_scrollbar(f, area)
     }
-}
-
-impl AppWidget for FilesWidget {
-    const TITLE: &'static str = "Files";
-    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
+    fn lines(&self) -> Vec<Line<'static>> {
         let mut text: Vec<Line> = Vec::new();
-
-        let spans = Line::from(vec![
-            Span::raw("The "),
-            Span::styled("Files", Style::default().fg(Color::Yellow)),
-            Span::raw(" tab shows the currently open files."),
-        ]);
-        help_text.extend(Text::from(spans));
-
         match self.fds {
             Ok(ref fds) => {
                 let fd_style = Style::default().fg(Color::Green);
@@ -77,38 +65,33 @@ impl AppWidget for FilesWidget {
                                 Style::default().fg(Color::Magenta),
                             ));
 
-                            // get the inode and device for this path to see if it is locked
-                            let cstr = CString::new(path.as_os_str().as_bytes()).unwrap();
-                            let mut stat = unsafe { std::mem::zeroed() };
-                            if unsafe { libc::stat(cstr.as_ptr(), &mut stat) } == 0 {
-                                if let Ok(locks) = &self.locks {
-                                    if let Some(lock) = locks.iter().find(|lock| {
-                                        let lock_dev = libc::makedev(lock.devmaj, lock.devmin);
-                                        lock.inode == stat.st_ino && stat.st_dev == lock_dev
-                                    }) {
-                                        line.push(Span::styled(
-                                            format!(" ({:?} {:?} {:?})", lock.lock_type, lock.mode, lock.kind),
-                                            Style::default().add_modifier(Modifier::DIM),
-                                        ));
-                                    }
-                                }
+                            // files on the root filesystem are the usual case, so only other mounts are noted
+                            if let Some(mount_point) = fd.mount_point.as_ref().filter(|m| m.as_os_str() != "/") {
+                                line.push(Span::styled(
+                                    format!(" (on {})", mount_point.display()),
+                                    Style::default().fg(Color::Cyan),
+                                ));
+                            }
+                            if let Some(lock) = &fd.lock {
+                                line.push(Span::styled(
+                                    format!(" ({:?} {:?} {:?})", lock.lock_type, lock.mode, lock.kind),
+                                    Style::default().add_modifier(Modifier::DIM),
+                                ));
                             }
                         }
                         FDTarget::Pipe(inode) => {
                             line.push(Span::styled(format!("pipe: {inode}"), Style::default().fg(Color::Blue)));
 
-                            if let Some((rd_side, wr_side)) = self.pipe_inodes.get(inode) {
-                                if fd.mode().contains(procfs::process::FDPermissions::READ) {
-                                    line.push(Span::styled(
-                                        format!(" (--> {} {})", wr_side.pid, wr_side.cmdline),
-                                        Style::default().add_modifier(Modifier::DIM),
-                                    ));
-                                } else if fd.mode().contains(procfs::process::FDPermissions::WRITE) {
-                                    line.push(Span::styled(
-                                        format!(" (<-- {} {})", rd_side.pid, rd_side.cmdline),
-                                        Style::default().add_modifier(Modifier::DIM),
-                                    ));
-                                }
+                            if let Some(peer) = &fd.pipe_peer {
+                                let arrow = if fd.mode.contains(FDPermissions::READ) {
+                                    "-->"
+                                } else {
+                                    "<--"
+                                };
+                                line.push(Span::styled(
+                                    format!(" ({arrow} {} {})", peer.pid, peer.cmdline),
+                                    Style::default().add_modifier(Modifier::DIM),
+                                ));
                             }
                         }
                         FDTarget::Socket(inode) => {
@@ -117,35 +100,34 @@ impl AppWidget for FilesWidget {
                                 Style::default().fg(Color::Yellow),
                             ));
                             // do we have an entry for this socket inode in any of our tables?
-                            if let Some(entry) = self.tcp_map.get(inode) {
-                                line.push(Span::raw(format!(
+                            match &fd.socket {
+                                Some(Socket::Tcp(entry)) => line.push(Span::raw(format!(
                                     "[tcp] {} -> {} ({:?})",
                                     entry.local_address, entry.remote_address, entry.state
-                                )));
-                            } else if let Some(entry) = self.udp_map.get(inode) {
-                                line.push(Span::raw(format!(
+                                ))),
+                                Some(Socket::Udp(entry)) => line.push(Span::raw(format!(
                                     "[udp] {} -> {} ({:?})",
                                     entry.local_address, entry.remote_address, entry.state
-                                )));
-                            } else if let Some(entry) = self.unix_map.get(inode) {
-                                line.push(Span::styled("[unix]", Style::default().fg(Color::Yellow)));
-                                line.push(Span::raw(match entry.socket_type as i32 {
-                                    libc::SOCK_STREAM => " STREAM    ",
-                                    libc::SOCK_DGRAM => " DGRAM     ",
-                                    libc::SOCK_SEQPACKET => " SEQPACKET ",
-                                    _ => "           ",
-                                }));
-                                if let Some(path) = &entry.path {
-                                    line.push(Span::raw(format!(" {}", path.display())));
-                                } else {
-                                    line.push(Span::styled(" (no socket path)", Style::default().fg(Color::Gray)));
+                                ))),
+                                Some(Socket::Unix(entry)) => {
+                                    line.push(Span::styled("[unix]", Style::default().fg(Color::Yellow)));
+                                    line.push(Span::raw(match entry.socket_type as i32 {
+                                        libc::SOCK_STREAM => " STREAM    ",
+                                        libc::SOCK_DGRAM => " DGRAM     ",
+                                        libc::SOCK_SEQPACKET => " SEQPACKET ",
+                                        _ => "           ",
+                                    }));
+                                    if let Some(path) = &entry.path {
+                                        line.push(Span::raw(format!(" {}", path.display())));
+                                    } else {
+                                        line.push(Span::styled(" (no socket path)", Style::default().fg(Color::Gray)));
+                                    }
+                                    line.push(Span::raw(format!(" ({:?})\n", entry.state)));
                                 }
-                                line.push(Span::raw(format!(" ({:?})\n", entry.state)));
-                            } else {
-                                line.push(Span::styled(
+                                None => line.push(Span::styled(
                                     format!("socket: {inode}"),
                                     Style::default().fg(Color::Yellow),
-                                ))
+                                )),
                             }
                         }
                         x => line.push(Span::raw(format!("{x:?}"))),
@@ -160,8 +142,24 @@ impl AppWidget for FilesWidget {
                 )));
             }
         }
+        text
+    }
+}
+
+impl AppWidget for FilesWidget {
+    const TITLE: &'static str = "Files";
+    type Json = Vec<json::File>;
+    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
+        let spans = Line::from(vec![
+            Span::raw("The "),
+            Span::styled("Files", Style::default().fg(Color::Yellow)),
+            Span::raw(" tab shows the currently open files."),
+        ]);
+        help_text.extend(Text::from(spans));
 
-        let max_scroll = crate::get_numlines_from_spans(text.iter(), area.width as usize) as i32 - area.height as i32;
+        let text = self.lines();
+
+        let max_scroll = get_numlines_from_spans(text.iter(), area.width as usize) as i32 - area.height as i32;
         self.scroll.set_max_scroll(max_scroll);
 
         let widget = Paragraph::new(text)
@@ -169,21 +167,64 @@ impl AppWidget for FilesWidget {
             .scroll((self.scroll.scroll_offset, 0));
         f.render_widget(widget, area);
     }
-    fn update(&mut self, proc: &Process) {
-        if self.last_updated.elapsed() > TWO_SECONDS {
-            self.fds = proc.fd().map(|iter| iter.filter_map(|f| f.ok()).collect());
-            self.locks = util::get_locks_for_pid(proc.pid);
-            self.last_updated = Instant::now();
-            self.tcp_map = crate::util::get_tcp_table(proc);
-            self.udp_map = crate::util::get_udp_table(proc);
-            self.unix_map = crate::util::get_unix_table(proc);
-        }
-        if self.pipes_updated.elapsed() > TEN_SECONDS {
-            self.pipe_inodes = util::get_pipe_pairs();
-            self.pipes_updated = Instant::now();
+    fn update(&mut self, proc: &Process, now: Instant) {
+        if now.saturating_duration_since(self.last_updated) > throttle(TWO_SECONDS) {
+            let old = std::mem::replace(&mut self.fds, self.collector.collect(proc));
+            if let (Ok(old), Ok(new)) = (&old, &self.fds) {
+                self.changes.extend(file_changes(old, new));
+            }
+            self.last_updated = now;
         }
     }
     fn handle_input(&mut self, input: KeyEvent, height: u16) -> InputResult {
         self.scroll.handle_input(input, height)
     }
+    fn snapshot(&self, out: &mut String) -> std::fmt::Result {
+        write_lines(out, &self.lines())
+    }
+    fn json(&self) -> Section<Self::Json> {
+        match &self.fds {
+            Ok(fds) => Section::data(fds.iter().map(json::File::from).collect()),
+            Err(e) => Section::error(e),
+        }
+    }
+    fn items(&self) -> Option<Vec<Item>> {
+        self.fds.as_ref().ok().map(|fds| baseline::file_items(fds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use procdump::source::DataSource;
+    use ratatui::text::Text;
+
+    use super::{AppWidget, FilesWidget};
+    use crate::ui::widgets::{buffer_lines, fixture, render};
+
+    #[test]
+    fn test_draw_fixture() {
+        let source = fixture();
+        let mut sleep = FilesWidget::new(&source.process(101).unwrap(), source.clone());
+        let buffer = render(40, 4, |f| sleep.draw(f, f.size(), &mut Text::default()));
+        assert_eq!(
+            buffer_lines(&buffer),
+            [
+                "0   /dev/null (on /dev)                 ",
+                "1   pipe: 5000 (<-- 102 cat)            ",
+                "2   /dev/pts/0 (on /dev/pts)            ",
+                "                                        ",
+            ]
+        );
+
+        // the other end of the same pipe
+        let mut cat = FilesWidget::new(&source.process(102).unwrap(), source.clone());
+        let buffer = render(40, 2, |f| cat.draw(f, f.size(), &mut Text::default()));
+        assert_eq!(
+            buffer_lines(&buffer),
+            [
+                "0   pipe: 5000 (--> 101 sleep)          ",
+                "                                        ",
+            ]
+        );
+    }
 }
@@ -1,6 +1,11 @@
 use std::time::Instant;
 
 use crossterm::event::KeyEvent;
+use procdump::{
+    collect::{Collector, IoCollector},
+    json::{self, Section},
+    util::{fmt_bytes, fmt_rate},
+};
 use procfs::process::Process;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
@@ -11,17 +16,19 @@ use ratatui::{
 };
 
 use crate::{
-    ui::{InputResult, ONE_SECONDS},
-    util::{fmt_bytes, fmt_rate},
-    SparklineData, StatDelta,
+    ui::{throttle, InputResult, ONE_SECONDS},
+    SparklineData,
 };
 
-use super::AppWidget;
+use super::{write_lines, AppWidget};
+
+/// Colors of the all-IO, IO-ops, and disk-IO sparklines
+const SPARK_COLORS: [Color; 3] = [Color::LightCyan, Color::LightMagenta, Color::LightGreen];
 
 pub struct IOWidget {
     last_updated: Instant,
-    //io: procfs::ProcResult<procfs::process::Io>,
-    io_d: anyhow::Result<StatDelta<procfs::process::Io>>,
+    collector: IoCollector,
+    io: <IoCollector as Collector>::Output,
     io_spark: SparklineData,
     ops_spark: SparklineData,
     disk_spark: SparklineData,
@@ -29,47 +36,45 @@ pub struct IOWidget {
 
 impl IOWidget {
     pub fn new(proc: &Process) -> IOWidget {
-        //let io = proc.io();
+        let mut collector = IoCollector::default();
         IOWidget {
             last_updated: Instant::now(),
-            io_d: StatDelta::<procfs::process::Io>::new(proc),
+            io: collector.collect(proc),
+            collector,
             io_spark: SparklineData::new(),
             ops_spark: SparklineData::new(),
             disk_spark: SparklineData::new(),
         }
     }
-}
-
-impl AppWidget for IOWidget {
-    const TITLE: &'static str = "IO";
-    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
-        let spans = Line::from(vec![
-            Span::raw("The "),
-            Span::styled("IO", Style::default().fg(Color::Yellow)),
-            Span::raw(" tab shows various I/O stats. The "),
-            Span::styled("blue", Style::default().fg(Color::LightCyan)),
-            Span::raw(" graph shows all IO (bytes per sec), the "),
-            Span::styled("magenta", Style::default().fg(Color::LightMagenta)),
-            Span::raw(" graph shows IO ops per sec, and the "),
-            Span::styled("green", Style::default().fg(Color::LightGreen)),
-            Span::raw(" graph shows disk IO bytes per sec."),
-        ]);
-        help_text.extend(Text::from(spans));
-
-        let chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .margin(0)
-            .constraints([Constraint::Length(52), Constraint::Min(1)].as_ref())
-            .split(area);
-
-        let spark_colors = [Color::LightCyan, Color::LightMagenta, Color::LightGreen];
+    /// The history of each sparkline, with a name for it
+    pub fn sparklines(&self) -> [(&'static str, &[u64]); 3] {
+        [
+            ("io_bytes_per_second", self.io_spark.as_slice()),
+            ("io_ops_per_second", self.ops_spark.as_slice()),
+            ("disk_bytes_per_second", self.disk_spark.as_slice()),
+        ]
+    }
+    /// Takes over the history of the widget for the process that this one replaced, with a marker
+    /// where one ends and the other starts
+    pub fn carry_history(&mut self, old: IOWidget) {
+        self.io_spark = old.io_spark;
+        self.ops_spark = old.ops_spark;
+        self.disk_spark = old.disk_spark;
+        for spark in [&mut self.io_spark, &mut self.ops_spark, &mut self.disk_spark] {
+            spark.mark();
+        }
+    }
+    /// The IO counters from the last update, if they could be read
+    pub fn totals(&self) -> Option<&procfs::process::Io> {
+        self.io.as_ref().ok().map(|stats| &stats.io)
+    }
+    fn lines(&self) -> Vec<Line<'static>> {
+        let spark_colors = SPARK_COLORS;
         let mut text: Vec<Line> = Vec::new();
         let s = Style::default().fg(Color::Green);
-        if let Ok(ref io_d) = self.io_d {
-            let io = io_d.latest();
-            let prev_io = io_d.previous();
-            let duration = io_d.duration();
-            let dur_sec = duration.as_millis() as f32 / 1000.0;
+        if let Ok(ref stats) = self.io {
+            let io = &stats.io;
+            let rates = &stats.rates;
 
             // all IO
             text.push(Line::from(vec![
@@ -80,14 +85,11 @@ impl AppWidget for IOWidget {
                 Span::styled("\u{2503}", Style::default().fg(spark_colors[0])),
             ]));
 
-            let io_read_rate = (io.rchar - prev_io.rchar) as f32 / dur_sec;
-            let io_write_rate = (io.wchar - prev_io.wchar) as f32 / dur_sec;
-
             text.push(Line::from(vec![
                 Span::styled("read rate:   ", s),
-                Span::raw(format!("{: <12}", fmt_rate(io_read_rate, "Bps"))),
+                Span::raw(format!("{: <12}", fmt_rate(rates.read_bytes, "Bps"))),
                 Span::styled("write rate:  ", s),
-                Span::raw(format!("{: <12}", fmt_rate(io_write_rate, "Bps"))),
+                Span::raw(format!("{: <12}", fmt_rate(rates.write_bytes, "Bps"))),
                 Span::styled("\u{2503}", Style::default().fg(spark_colors[0])),
             ]));
 
@@ -100,14 +102,11 @@ impl AppWidget for IOWidget {
                 Span::styled("\u{2503}", Style::default().fg(spark_colors[1])),
             ]));
 
-            let io_rop_rate = (io.syscr - prev_io.syscr) as f32 / dur_sec;
-            let io_wop_rate = (io.syscw - prev_io.syscw) as f32 / dur_sec;
-
             text.push(Line::from(vec![
                 Span::styled("op rate:     ", s),
-                Span::raw(format!("{: <12}", fmt_rate(io_rop_rate, "ps"))),
+                Span::raw(format!("{: <12}", fmt_rate(rates.read_ops, "ps"))),
                 Span::styled("op rate:     ", s),
-                Span::raw(format!("{: <12}", fmt_rate(io_wop_rate, "ps"))),
+                Span::raw(format!("{: <12}", fmt_rate(rates.write_ops, "ps"))),
                 Span::styled("\u{2503}", Style::default().fg(spark_colors[1])),
             ]));
 
@@ -120,14 +119,11 @@ impl AppWidget for IOWidget {
                 Span::styled("\u{2503}", Style::default().fg(spark_colors[2])),
             ]));
 
-            let disk_read_rate = (io.read_bytes - prev_io.read_bytes) as f32 / dur_sec;
-            let disk_write_rate = (io.write_bytes - prev_io.write_bytes) as f32 / dur_sec;
-
             text.push(Line::from(vec![
                 Span::styled("disk rate:   ", s),
-                Span::raw(format!("{: <12}", fmt_rate(disk_read_rate, "Bps"))),
+                Span::raw(format!("{: <12}", fmt_rate(rates.disk_read_bytes, "Bps"))),
                 Span::styled("disk rate:   ", s),
-                Span::raw(format!("{: <12}", fmt_rate(disk_write_rate, "Bps"))),
+                Span::raw(format!("{: <12}", fmt_rate(rates.disk_write_bytes, "Bps"))),
                 Span::styled("\u{2503}", Style::default().fg(spark_colors[2])),
             ]));
 
@@ -142,6 +138,35 @@ impl AppWidget for IOWidget {
             //let rps = (io.read_bytes - prev_io.read_bytes) as f32 / dur_sec;
             //text.push(Text::raw(format!("{} ({})", fmt_bytes(io.read_bytes), fmt_rate(rps))));
         }
+        text
+    }
+}
+
+impl AppWidget for IOWidget {
+    const TITLE: &'static str = "IO";
+    type Json = json::Io;
+    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
+        let spans = Line::from(vec![
+            Span::raw("The "),
+            Span::styled("IO", Style::default().fg(Color::Yellow)),
+            Span::raw(" tab shows various I/O stats. The "),
+            Span::styled("blue", Style::default().fg(Color::LightCyan)),
+            Span::raw(" graph shows all IO (bytes per sec), the "),
+            Span::styled("magenta", Style::default().fg(Color::LightMagenta)),
+            Span::raw(" graph shows IO ops per sec, and the "),
+            Span::styled("green", Style::default().fg(Color::LightGreen)),
+            Span::raw(" graph shows disk IO bytes per sec."),
+        ]);
+        help_text.extend(Text::from(spans));
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .margin(0)
+            .constraints([Constraint::Length(52), Constraint::Min(1)].as_ref())
+            .split(area);
+
+        let spark_colors = SPARK_COLORS;
+        let text = self.lines();
 
         let widget = Paragraph::new(text)
             .block(Block::default().borders(Borders::NONE))
@@ -164,15 +189,12 @@ impl AppWidget for IOWidget {
             )
             .split(chunks[1]);
 
-        for (idx, (data, max)) in [
-            self.io_spark.as_slice(),
-            self.ops_spark.as_slice(),
-            self.disk_spark.as_slice(),
-        ]
-        .iter()
-        .zip([10000, 100, 10000].iter())
-        .enumerate()
+        for (idx, (spark, max)) in [&self.io_spark, &self.ops_spark, &self.disk_spark]
+            .iter()
+            .zip([10000, 100, 10000].iter())
+            .enumerate()
         {
+            let data = spark.as_slice();
             let s = std::cmp::max(0, data.len() as i32 - chunks[1].width as i32) as usize;
             let max = std::cmp::max(*max, *data[s..].iter().max().unwrap_or(&1));
             let widget = Sparkline::default()
@@ -180,34 +202,41 @@ impl AppWidget for IOWidget {
                 .max(max)
                 .style(Style::default().fg(spark_colors[idx]));
             f.render_widget(widget, spark_chunks[idx]);
+            spark.draw_markers(f, spark_chunks[idx], s);
         }
     }
-    fn update(&mut self, proc: &Process) {
-        if self.last_updated.elapsed() > ONE_SECONDS {
-            if let Ok(ref mut io_d) = self.io_d {
-                io_d.update(proc);
-
-                let io = io_d.latest();
-                let prev_io = io_d.previous();
-                let duration = io_d.duration();
-                let dur_sec = duration.as_millis() as f32 / 1000.0;
-
-                let io_read_rate = (io.rchar - prev_io.rchar) as f32 / dur_sec;
-                let io_write_rate = (io.wchar - prev_io.wchar) as f32 / dur_sec;
-                self.io_spark.push((io_read_rate + io_write_rate) as u64);
-
-                let io_rop_rate = (io.syscr - prev_io.syscr) as f32 / dur_sec;
-                let io_wop_rate = (io.syscw - prev_io.syscw) as f32 / dur_sec;
-                self.ops_spark.push((io_rop_rate + io_wop_rate) as u64);
-
-                let disk_read_rate = (io.read_bytes - prev_io.read_bytes) as f32 / dur_sec;
-                let disk_write_rate = (io.write_bytes - prev_io.write_bytes) as f32 / dur_sec;
-                self.disk_spark.push((disk_read_rate + disk_write_rate) as u64);
+    fn update(&mut self, proc: &Process, now: Instant) {
+        if now.saturating_duration_since(self.last_updated) > throttle(ONE_SECONDS) {
+            self.io = self.collector.collect_at(proc, now);
+            if let Ok(ref stats) = self.io {
+                let rates = &stats.rates;
+                self.io_spark.push((rates.read_bytes + rates.write_bytes) as u64);
+                self.ops_spark.push((rates.read_ops + rates.write_ops) as u64);
+                self.disk_spark
+                    .push((rates.disk_read_bytes + rates.disk_write_bytes) as u64);
             }
-            self.last_updated = Instant::now();
+            self.last_updated = now;
         }
     }
     fn handle_input(&mut self, _input: KeyEvent, _height: u16) -> InputResult {
         InputResult::NeedsRedraw
     }
+    fn snapshot(&self, out: &mut String) -> std::fmt::Result {
+        if let Err(ref e) = self.io {
+            use std::fmt::Write;
+            return writeln!(out, "Error getting io stats: {e}");
+        }
+        // drop the sparkline color markers at the end of each line
+        let mut lines = self.lines();
+        for line in &mut lines {
+            line.spans.retain(|span| span.content != "\u{2503}");
+        }
+        write_lines(out, &lines)
+    }
+    fn json(&self) -> Section<Self::Json> {
+        match &self.io {
+            Ok(stats) => Section::data(json::Io::from(stats)),
+            Err(e) => Section::error(e),
+        }
+    }
 }
@@ -1,7 +1,13 @@
 use std::{borrow::Cow, time::Instant};
 
 use crossterm::event::KeyEvent;
-use procfs::{process::Process, ProcResult};
+use procdump::{
+    baseline::{self, Item},
+    collect::{named_limits, Collector, LimitsCollector},
+    json::{self, Section},
+    util::limit_to_string,
+};
+use procfs::process::Process;
 use ratatui::{
     layout::{Constraint, Rect},
     style::{Color, Modifier, Style},
@@ -10,31 +16,49 @@ use ratatui::{
     Frame,
 };
 
-use crate::{
-    ui::{InputResult, ScrollController, TWO_SECONDS},
-    util::limit_to_string,
-};
+use crate::ui::{throttle, InputResult, ScrollController, TWO_SECONDS};
 
 use super::AppWidget;
 
 pub struct LimitWidget {
-    limits: ProcResult<procfs::process::Limits>,
+    collector: LimitsCollector,
+    limits: <LimitsCollector as Collector>::Output,
     last_updated: Instant,
     scroll: ScrollController,
 }
 
 impl LimitWidget {
     pub fn new(proc: &Process) -> LimitWidget {
+        let mut collector = LimitsCollector;
         LimitWidget {
-            limits: proc.limits(),
+            limits: collector.collect(proc),
+            collector,
             last_updated: Instant::now(),
             scroll: ScrollController::new(),
         }
     }
+    /// Each row has 4 columns: the limit name, the soft limit, the hard limit, and the units
+    fn rows(&self) -> Vec<Vec<Cow<'static, str>>> {
+        let Ok(ref limits) = self.limits else {
+            return Vec::new();
+        };
+        named_limits(limits)
+            .into_iter()
+            .map(|(name, limit, units)| {
+                vec![
+                    Cow::Borrowed(name),
+                    limit_to_string(&limit.soft_limit),
+                    limit_to_string(&limit.hard_limit),
+                    units.map_or(Cow::Borrowed(""), |u| Cow::Owned(format!("({u})"))),
+                ]
+            })
+            .collect()
+    }
 }
 
 impl AppWidget for LimitWidget {
     const TITLE: &'static str = "Limits";
+    type Json = Vec<json::Limit>;
     fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
         let spans = Line::from(vec![
             Span::raw("The "),
@@ -50,170 +74,11 @@ impl AppWidget for LimitWidget {
             Cell::from("Hard Limit").style(header_cell_style),
             Cell::from(""),
         ];
-        let mut rows = Vec::new();
-
-        if let Ok(ref limits) = self.limits {
-            rows.push(Row::new(
-                vec![
-                    Cow::Borrowed("Cpu Time"),
-                    limit_to_string(&limits.max_cpu_time.soft_limit),
-                    limit_to_string(&limits.max_cpu_time.hard_limit),
-                    Cow::Borrowed("(seconds)"),
-                ]
-                .into_iter()
-                .map(ratatui::text::Text::raw),
-            ));
-            rows.push(Row::new(
-                vec![
-                    Cow::Borrowed("File Size"),
-                    limit_to_string(&limits.max_file_size.soft_limit),
-                    limit_to_string(&limits.max_file_size.hard_limit),
-                    Cow::Borrowed("(bytes)"),
-                ]
-                .into_iter()
-                .map(ratatui::text::Text::raw),
-            ));
-            rows.push(Row::new(
-                vec![
-                    Cow::Borrowed("Data Size"),
-                    limit_to_string(&limits.max_data_size.soft_limit),
-                    limit_to_string(&limits.max_data_size.hard_limit),
-                    Cow::Borrowed("(bytes)"),
-                ]
-                .into_iter()
-                .map(ratatui::text::Text::raw),
-            ));
-            rows.push(Row::new(
-                vec![
-                    Cow::Borrowed("Stack Size"),
-                    limit_to_string(&limits.max_stack_size.soft_limit),
-                    limit_to_string(&limits.max_stack_size.hard_limit),
-                    Cow::Borrowed("(bytes)"),
-                ]
-                .into_iter()
-                .map(ratatui::text::Text::raw),
-            ));
-            rows.push(Row::new(
-                vec![
-                    Cow::Borrowed("Core File Size"),
-                    limit_to_string(&limits.max_core_file_size.soft_limit),
-                    limit_to_string(&limits.max_core_file_size.hard_limit),
-                    Cow::Borrowed("(bytes)"),
-                ]
-                .into_iter()
-                .map(ratatui::text::Text::raw),
-            ));
-            rows.push(Row::new(
-                vec![
-                    Cow::Borrowed("Resident Set"),
-                    limit_to_string(&limits.max_resident_set.soft_limit),
-                    limit_to_string(&limits.max_resident_set.hard_limit),
-                    Cow::Borrowed("(bytes)"),
-                ]
-                .into_iter()
-                .map(ratatui::text::Text::raw),
-            ));
-            rows.push(Row::new(
-                vec![
-                    Cow::Borrowed("Processes"),
-                    limit_to_string(&limits.max_processes.soft_limit),
-                    limit_to_string(&limits.max_processes.hard_limit),
-                    Cow::Borrowed(""),
-                ]
-                .into_iter()
-                .map(ratatui::text::Text::raw),
-            ));
-            rows.push(Row::new(
-                vec![
-                    Cow::Borrowed("Open Files"),
-                    limit_to_string(&limits.max_open_files.soft_limit),
-                    limit_to_string(&limits.max_open_files.hard_limit),
-                    Cow::Borrowed(""),
-                ]
-                .into_iter()
-                .map(ratatui::text::Text::raw),
-            ));
-            rows.push(Row::new(
-                vec![
-                    Cow::Borrowed("Locked Memory"),
-                    limit_to_string(&limits.max_locked_memory.soft_limit),
-                    limit_to_string(&limits.max_locked_memory.hard_limit),
-                    Cow::Borrowed("(bytes)"),
-                ]
-                .into_iter()
-                .map(ratatui::text::Text::raw),
-            ));
-            rows.push(Row::new(
-                vec![
-                    Cow::Borrowed("Address Space"),
-                    limit_to_string(&limits.max_address_space.soft_limit),
-                    limit_to_string(&limits.max_address_space.hard_limit),
-                    Cow::Borrowed(""),
-                ]
-                .into_iter()
-                .map(ratatui::text::Text::raw),
-            ));
-            rows.push(Row::new(
-                vec![
-                    Cow::Borrowed("File Locks"),
-                    limit_to_string(&limits.max_file_locks.soft_limit),
-                    limit_to_string(&limits.max_file_locks.hard_limit),
-                    Cow::Borrowed(""),
-                ]
-                .into_iter()
-                .map(ratatui::text::Text::raw),
-            ));
-            rows.push(Row::new(
-                vec![
-                    Cow::Borrowed("Pending Signals"),
-                    limit_to_string(&limits.max_pending_signals.soft_limit),
-                    limit_to_string(&limits.max_pending_signals.hard_limit),
-                    Cow::Borrowed(""),
-                ]
-                .into_iter()
-                .map(ratatui::text::Text::raw),
-            ));
-            rows.push(Row::new(
-                vec![
-                    Cow::Borrowed("Msgqueue Size"),
-                    limit_to_string(&limits.max_msgqueue_size.soft_limit),
-                    limit_to_string(&limits.max_msgqueue_size.hard_limit),
-                    Cow::Borrowed("(bytes)"),
-                ]
-                .into_iter()
-                .map(ratatui::text::Text::raw),
-            ));
-            rows.push(Row::new(
-                vec![
-                    Cow::Borrowed("Nice Priority"),
-                    limit_to_string(&limits.max_nice_priority.soft_limit),
-                    limit_to_string(&limits.max_nice_priority.hard_limit),
-                    Cow::Borrowed(""),
-                ]
-                .into_iter()
-                .map(ratatui::text::Text::raw),
-            ));
-            rows.push(Row::new(
-                vec![
-                    Cow::Borrowed("Realtime Priority"),
-                    limit_to_string(&limits.max_realtime_priority.soft_limit),
-                    limit_to_string(&limits.max_realtime_priority.hard_limit),
-                    Cow::Borrowed(""),
-                ]
-                .into_iter()
-                .map(ratatui::text::Text::raw),
-            ));
-            rows.push(Row::new(
-                vec![
-                    Cow::Borrowed("Realtime Timeout"),
-                    limit_to_string(&limits.max_realtime_timeout.soft_limit),
-                    limit_to_string(&limits.max_realtime_timeout.hard_limit),
-                    Cow::Borrowed("(μseconds)"),
-                ]
-                .into_iter()
-                .map(ratatui::text::Text::raw),
-            ));
-        }
+        let mut rows: Vec<Row> = self
+            .rows()
+            .into_iter()
+            .map(|row| Row::new(row.into_iter().map(ratatui::text::Text::raw)))
+            .collect();
 
         self.scroll.set_max_scroll(rows.len() as i32 + 2);
 
@@ -230,7 +95,7 @@ impl AppWidget for LimitWidget {
         };
 
         let widget = Table::new(
-            rows.into_iter(),
+            rows,
             [
                 Constraint::Length(18),
                 Constraint::Length(12),
@@ -238,16 +103,37 @@ impl AppWidget for LimitWidget {
                 Constraint::Length(11),
             ],
         )
-        .header(Row::new(headers.into_iter()).style(header_cell_style).bottom_margin(1));
+        .header(Row::new(headers).style(header_cell_style).bottom_margin(1));
         f.render_widget(widget, area);
     }
-    fn update(&mut self, proc: &Process) {
-        if self.last_updated.elapsed() > TWO_SECONDS {
-            self.limits = proc.limits();
-            self.last_updated = Instant::now();
+    fn update(&mut self, proc: &Process, now: Instant) {
+        if now.saturating_duration_since(self.last_updated) > throttle(TWO_SECONDS) {
+            self.limits = self.collector.collect(proc);
+            self.last_updated = now;
         }
     }
     fn handle_input(&mut self, input: KeyEvent, height: u16) -> InputResult {
         self.scroll.handle_input(input, height)
     }
+    fn snapshot(&self, out: &mut String) -> std::fmt::Result {
+        use std::fmt::Write;
+
+        if let Err(ref e) = self.limits {
+            return writeln!(out, "Error getting limits: {e}");
+        }
+        writeln!(out, "{:<18} {:<12} {:<12}", "Type", "Soft Limit", "Hard Limit")?;
+        for row in self.rows() {
+            writeln!(out, "{:<18} {:<12} {:<12} {}", row[0], row[1], row[2], row[3])?;
+        }
+        Ok(())
+    }
+    fn json(&self) -> Section<Self::Json> {
+        match &self.limits {
+            Ok(limits) => Section::data(json::limits(limits)),
+            Err(e) => Section::error(e),
+        }
+    }
+    fn items(&self) -> Option<Vec<Item>> {
+        self.limits.as_ref().ok().map(baseline::limit_items)
+    }
 }
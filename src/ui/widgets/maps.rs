@@ -1,10 +1,13 @@
 use std::time::Instant;
 
 use crossterm::event::{KeyCode, KeyEvent};
-use procfs::{
-    process::{MMapPath, MemoryMaps, Process},
-    ProcResult,
+use procdump::{
+    baseline::{self, Item},
+    collect::{Collector, MapsCollector},
+    json::{self, Section},
+    util::fmt_bytes,
 };
+use procfs::process::{MMapPath, Process};
 use ratatui::{
     layout::Rect,
     style::{Color, Style},
@@ -13,68 +16,48 @@ use ratatui::{
     Frame,
 };
 
-use crate::{
-    ui::{InputResult, ScrollController, TWO_SECONDS},
-    util::fmt_bytes,
-};
-
-use super::AppWidget;
+use crate::ui::{get_numlines_from_spans, throttle, InputResult, ScrollController, TWO_SECONDS};
 
-enum Maps {
-    Maps(ProcResult<MemoryMaps>),
-    SMaps(ProcResult<MemoryMaps>),
-}
+use super::{write_lines, AppWidget};
 
 pub struct MapsWidget {
-    maps: Maps,
-    want_smaps: bool,
+    collector: MapsCollector,
+    maps: <MapsCollector as Collector>::Output,
     last_updated: Instant,
     scroll: ScrollController,
     force_update: bool,
 }
 
 impl MapsWidget {
+    /// The mapped files, keyed by library name, for comparing with another process
+    pub fn libraries(&self) -> Option<Vec<Item>> {
+        self.maps.as_ref().ok().map(baseline::library_items)
+    }
     pub fn new(proc: &Process) -> MapsWidget {
+        let mut collector = MapsCollector::default();
         MapsWidget {
-            maps: Maps::Maps(proc.maps()),
-            want_smaps: false,
+            maps: collector.collect(proc),
+            collector,
             last_updated: Instant::now(),
             scroll: ScrollController::new(),
             force_update: false,
         }
     }
+    /// Switches between showing the basic maps and the detailed smaps (takes effect on the next update)
+    pub fn set_want_smaps(&mut self, want_smaps: bool) {
+        if self.collector.detailed != want_smaps {
+            self.collector.detailed = want_smaps;
+            self.force_update = true;
+        }
+    }
     pub fn draw_scrollbar(&self, f: &mut Frame, area: Rect) {
         self.scroll.draw_scrollbar(f, area)
     }
-}
-
-impl AppWidget for MapsWidget {
-    const TITLE: &'static str = "Maps";
-    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
+    fn lines(&self) -> Vec<Line<'static>> {
         let mut text: Vec<Line> = Vec::new();
-
-        let spans = Line::from(vec![
-            Span::raw("The "),
-            Span::styled("Maps", Style::default().fg(Color::Yellow)),
-            Span::raw(" tab shows the currently mapped memory regions. Press "),
-            Span::styled("d", Style::default().fg(Color::Green)),
-            Span::raw(" to toggle extra details about each map."),
-        ]);
-        help_text.extend(Text::from(spans));
-        if self.want_smaps {
-            let spans = Line::from(vec![
-                Span::raw(" The "),
-                Span::styled("Size", Style::default().fg(Color::Magenta)),
-                Span::raw(" column shows the total size of the mapped page, and the "),
-                Span::styled("Rss", Style::default().fg(Color::Magenta)),
-                Span::raw(" column shows how much of that size is mapped into physical memory."),
-            ]);
-            help_text.extend(Text::from(spans));
-        }
-
         match &self.maps {
-            Maps::Maps(Ok(maps)) => {
-                for map in &maps.0 {
+            Ok(maps) if !maps.detailed => {
+                for map in &maps.regions {
                     let mut line = vec![
                         Span::raw(format!("0x{:012x}-0x{:012x} ", map.address.0, map.address.1)),
                         Span::raw(format!("{} ", map.perms.as_str())),
@@ -98,7 +81,7 @@ impl AppWidget for MapsWidget {
                     text.push(Line::from(line));
                 }
             }
-            Maps::SMaps(Ok(maps)) => {
+            Ok(maps) => {
                 let header_style = Style::default().fg(Color::Magenta);
                 text.push(Line::from(vec![
                     Span::styled(format!("{:29} ", "Address"), header_style),
@@ -107,7 +90,7 @@ impl AppWidget for MapsWidget {
                     Span::styled("Size       ", header_style),
                     Span::styled("Rss        ", header_style),
                 ]));
-                for map in &maps.0 {
+                for map in &maps.regions {
                     let mut line = vec![
                         Span::raw(format!("0x{:012x}-0x{:012x} ", map.address.0, map.address.1)),
                         Span::raw(format!("{} ", map.perms.as_str())),
@@ -147,14 +130,42 @@ impl AppWidget for MapsWidget {
                     text.push(Line::from(line));
                 }
             }
-            Maps::Maps(Err(ref e)) | Maps::SMaps(Err(ref e)) => {
+            Err(e) => {
                 text.push(Line::from(Span::styled(
                     format!("Error getting maps: {e}"),
                     Style::default().fg(Color::Red).bg(Color::Reset),
                 )));
             }
         }
-        let max_scroll = crate::get_numlines_from_spans(text.iter(), area.width as usize) as i32 - area.height as i32;
+        text
+    }
+}
+
+impl AppWidget for MapsWidget {
+    const TITLE: &'static str = "Maps";
+    type Json = Vec<json::Map>;
+    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
+        let spans = Line::from(vec![
+            Span::raw("The "),
+            Span::styled("Maps", Style::default().fg(Color::Yellow)),
+            Span::raw(" tab shows the currently mapped memory regions. Press "),
+            Span::styled("d", Style::default().fg(Color::Green)),
+            Span::raw(" to toggle extra details about each map."),
+        ]);
+        help_text.extend(Text::from(spans));
+        if self.collector.detailed {
+            let spans = Line::from(vec![
+                Span::raw(" The "),
+                Span::styled("Size", Style::default().fg(Color::Magenta)),
+                Span::raw(" column shows the total size of the mapped page, and the "),
+                Span::styled("Rss", Style::default().fg(Color::Magenta)),
+                Span::raw(" column shows how much of that size is mapped into physical memory."),
+            ]);
+            help_text.extend(Text::from(spans));
+        }
+
+        let text = self.lines();
+        let max_scroll = get_numlines_from_spans(text.iter(), area.width as usize) as i32 - area.height as i32;
         self.scroll.set_max_scroll(max_scroll);
 
         let widget = Paragraph::new(text)
@@ -162,23 +173,31 @@ impl AppWidget for MapsWidget {
             .scroll((self.scroll.scroll_offset, 0));
         f.render_widget(widget, area);
     }
-    fn update(&mut self, proc: &Process) {
-        if self.last_updated.elapsed() > TWO_SECONDS || self.force_update {
-            if self.want_smaps {
-                self.maps = Maps::SMaps(proc.smaps());
-            } else {
-                self.maps = Maps::Maps(proc.maps());
-            }
-            self.last_updated = Instant::now();
+    fn update(&mut self, proc: &Process, now: Instant) {
+        if now.saturating_duration_since(self.last_updated) > throttle(TWO_SECONDS) || self.force_update {
+            self.maps = self.collector.collect(proc);
+            self.last_updated = now;
             self.force_update = false;
         }
     }
     fn handle_input(&mut self, input: KeyEvent, height: u16) -> InputResult {
         if let KeyCode::Char('d') = input.code {
-            self.want_smaps = !self.want_smaps;
+            self.collector.detailed = !self.collector.detailed;
             self.force_update = true;
             return InputResult::NeedsUpdate;
         }
         self.scroll.handle_input(input, height)
     }
+    fn snapshot(&self, out: &mut String) -> std::fmt::Result {
+        write_lines(out, &self.lines())
+    }
+    fn json(&self) -> Section<Self::Json> {
+        match &self.maps {
+            Ok(maps) => Section::data(json::maps(maps)),
+            Err(e) => Section::error(e),
+        }
+    }
+    fn items(&self) -> Option<Vec<Item>> {
+        self.maps.as_ref().ok().map(baseline::map_items)
+    }
 }
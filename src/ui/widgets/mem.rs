@@ -1,10 +1,13 @@
-use std::time::Instant;
+use std::{collections::BTreeMap, time::Instant};
 
 use crossterm::event::KeyEvent;
-use procfs::{
-    process::{Process, SmapsRollup},
-    ProcResult,
+use procdump::{
+    baseline::{self, Item},
+    collect::{Collector, MemCollector},
+    json::Section,
+    util::fmt_bytes,
 };
+use procfs::process::Process;
 use ratatui::{
     layout::Rect,
     style::{Color, Style},
@@ -12,37 +15,30 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph},
 };
 
-use crate::{
-    ui::{InputResult, TWO_SECONDS},
-    util::fmt_bytes,
-};
+use crate::ui::{throttle, InputResult, TWO_SECONDS};
 
-use super::AppWidget;
+use super::{write_lines, AppWidget};
 
 pub struct MemWidget {
-    rollup: ProcResult<SmapsRollup>,
+    collector: MemCollector,
+    rollup: <MemCollector as Collector>::Output,
     last_updated: Instant,
 }
 
 impl MemWidget {
     pub fn new(proc: &Process) -> Self {
+        let mut collector = MemCollector;
         Self {
-            rollup: proc.smaps_rollup(),
+            rollup: collector.collect(proc),
+            collector,
             last_updated: Instant::now(),
         }
     }
-}
-
-impl AppWidget for MemWidget {
-    const TITLE: &'static str = "Mem";
-
-    fn draw(&mut self, f: &mut ratatui::Frame, area: Rect, _help_text: &mut Text) {
+    fn lines(&self) -> Vec<Line<'static>> {
         let mut text: Vec<Line> = Vec::new();
-
         match &self.rollup {
-            Ok(rollup) => {
+            Ok(data) => {
                 let key_style = Style::default().fg(Color::Green);
-                let data = &rollup.memory_map_rollup.0[0].extension.map;
                 if let Some(x) = data.get("Rss") {
                     text.push(Line::from(vec![
                         Span::styled(format!("{:15}", "Rss:"), key_style),
@@ -105,19 +101,40 @@ impl AppWidget for MemWidget {
                 )));
             }
         }
+        text
+    }
+}
+
+impl AppWidget for MemWidget {
+    const TITLE: &'static str = "Memory";
+    type Json = BTreeMap<String, u64>;
 
+    fn draw(&mut self, f: &mut ratatui::Frame, area: Rect, _help_text: &mut Text) {
+        let text = self.lines();
         let widget = Paragraph::new(text).block(Block::default().borders(Borders::NONE));
         f.render_widget(widget, area);
     }
 
-    fn update(&mut self, proc: &Process) {
-        if self.last_updated.elapsed() > TWO_SECONDS {
-            self.rollup = proc.smaps_rollup();
-            self.last_updated = Instant::now();
+    fn update(&mut self, proc: &Process, now: Instant) {
+        if now.saturating_duration_since(self.last_updated) > throttle(TWO_SECONDS) {
+            self.rollup = self.collector.collect(proc);
+            self.last_updated = now;
         }
     }
 
     fn handle_input(&mut self, _input: KeyEvent, _heightt: u16) -> InputResult {
         InputResult::None
     }
+    fn snapshot(&self, out: &mut String) -> std::fmt::Result {
+        write_lines(out, &self.lines())
+    }
+    fn json(&self) -> Section<Self::Json> {
+        match &self.rollup {
+            Ok(data) => Section::data(data.iter().map(|(k, v)| (k.clone(), *v)).collect()),
+            Err(e) => Section::error(e),
+        }
+    }
+    fn items(&self) -> Option<Vec<Item>> {
+        self.rollup.as_ref().ok().map(baseline::mem_items)
+    }
 }
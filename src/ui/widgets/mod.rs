@@ -1,35 +1,121 @@
+use std::time::Instant;
+
 use crossterm::event::KeyEvent;
+use procdump::baseline::Item;
 use procfs::process::Process;
-use ratatui::{layout::Rect, text::Text, Frame};
+use ratatui::{
+    layout::Rect,
+    text::{Line, Text},
+    Frame,
+};
 
 use super::InputResult;
 
 pub mod cgroup;
 pub mod env;
+pub mod events;
 pub mod files;
 pub mod io;
 pub mod limit;
 pub mod maps;
 pub mod mem;
+pub mod mounts;
 pub mod net;
+pub mod ns;
+pub mod security;
+pub mod signals;
 pub mod task;
 pub mod tree;
 
 pub use cgroup::*;
 pub use env::*;
+pub use events::*;
 pub use files::*;
 pub use io::*;
 pub use limit::*;
 pub use maps::*;
 pub use mem::*;
+pub use mounts::*;
 pub use net::*;
+pub use ns::*;
+pub use security::*;
+pub use signals::*;
 pub use task::*;
 pub use tree::*;
 
 pub trait AppWidget {
     const TITLE: &'static str;
+    /// The data that this widget contributes to the `--json` output
+    type Json: serde::Serialize;
 
     fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text);
-    fn update(&mut self, proc: &Process);
+    /// Refreshes the widget, if enough time has passed since the last refresh.  `now` comes from
+    /// [`DataSource::now`](procdump::source::DataSource::now), so that replays refresh at their
+    /// recorded pace
+    fn update(&mut self, proc: &Process, now: Instant);
     fn handle_input(&mut self, input: KeyEvent, height: u16) -> InputResult;
+    /// Writes the current contents of this widget as plain text (used by `--snapshot`)
+    fn snapshot(&self, out: &mut String) -> std::fmt::Result;
+    /// Returns the current contents of this widget (used by `--json`)
+    fn json(&self) -> crate::json::Section<Self::Json>;
+    /// What's shown, in a form that can be compared with a baseline or with another process.
+    /// `None` for widgets that can't be compared, or that couldn't read what they show
+    fn items(&self) -> Option<Vec<Item>> {
+        None
+    }
+}
+
+/// The parts of [`AppWidget`] that can be used through a `dyn` reference, so that the app can go
+/// through all of its widgets without naming each one
+pub trait AnyWidget {
+    fn title(&self) -> &'static str;
+    fn items(&self) -> Option<Vec<Item>>;
+}
+
+impl<W: AppWidget> AnyWidget for W {
+    fn title(&self) -> &'static str {
+        W::TITLE
+    }
+    fn items(&self) -> Option<Vec<Item>> {
+        AppWidget::items(self)
+    }
+}
+
+/// Writes some styled lines as plain text, dropping all styling
+pub(crate) fn write_lines(out: &mut String, lines: &[Line]) -> std::fmt::Result {
+    use std::fmt::Write;
+
+    for line in lines {
+        for span in &line.spans {
+            out.push_str(span.content.trim_end_matches('\n'));
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// The directory of test fixtures that ships with the source
+#[cfg(test)]
+pub(crate) fn fixture() -> std::sync::Arc<procdump::source::FixtureSource> {
+    std::sync::Arc::new(procdump::source::FixtureSource::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/proc"
+    )))
+}
+
+/// Draws onto a [`TestBackend`](ratatui::backend::TestBackend) of the given size, and returns what
+/// ended up in it
+#[cfg(test)]
+pub(crate) fn render(width: u16, height: u16, draw: impl FnOnce(&mut Frame)) -> ratatui::buffer::Buffer {
+    let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(width, height)).unwrap();
+    terminal.draw(draw).unwrap();
+    terminal.backend().buffer().clone()
+}
+
+/// The text of every line of `buffer`, without any styling
+#[cfg(test)]
+pub(crate) fn buffer_lines(buffer: &ratatui::buffer::Buffer) -> Vec<String> {
+    (0..buffer.area.height)
+        .map(|y| (0..buffer.area.width).map(|x| buffer.get(x, y).symbol()).collect())
+        .collect()
 }
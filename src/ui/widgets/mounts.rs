@@ -0,0 +1,218 @@
+use std::{sync::Arc, time::Instant};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use procdump::{
+    collect::{mount_options, mount_propagation, Collector, MountEntry, MountsCollector, OpenFile},
+    json::{self, Section},
+    source::DataSource,
+};
+use procfs::process::Process;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+use crate::ui::{get_numlines_from_spans, throttle, InputResult, ScrollController, TEN_SECONDS};
+
+use super::{write_lines, AppWidget};
+
+/// Everything about a mount that the filter is matched against
+fn describe(mount: &MountEntry) -> String {
+    let info = &mount.info;
+    format!(
+        "{} {} {} {} {}",
+        info.mount_point.display(),
+        info.mount_source.as_deref().unwrap_or(""),
+        info.fs_type,
+        mount_options(info),
+        mount_propagation(info)
+    )
+}
+
+pub struct MountsWidget {
+    collector: MountsCollector,
+    mounts: <MountsCollector as Collector>::Output,
+    last_updated: Instant,
+    /// Only mounts that contain this text are shown (ignoring case)
+    filter: String,
+    /// Set while the filter is being typed in
+    editing: bool,
+    scroll: ScrollController,
+}
+
+impl MountsWidget {
+    /// `files` are the open files of the process, from the Files tab
+    pub fn new(proc: &Process, source: Arc<dyn DataSource>, files: &[OpenFile]) -> MountsWidget {
+        let mut collector = MountsCollector::new(source);
+        collector.set_open_files(files);
+        MountsWidget {
+            mounts: collector.collect(proc),
+            collector,
+            last_updated: Instant::now(),
+            filter: String::new(),
+            editing: false,
+            scroll: ScrollController::new(),
+        }
+    }
+    pub fn draw_scrollbar(&self, f: &mut Frame, area: Rect) {
+        self.scroll.draw_scrollbar(f, area)
+    }
+    /// Recounts the open files on each mount, after the Files tab has refreshed.  The mount table
+    /// itself is only reread every ten seconds, but the counts are kept up to date
+    pub fn set_open_files(&mut self, files: &[OpenFile]) {
+        self.collector.set_open_files(files);
+        if let Ok(mounts) = &mut self.mounts {
+            for mount in mounts {
+                mount.open_files = self.collector.open_files(mount.info.mnt_id);
+            }
+        }
+    }
+    /// True while the filter is being typed in, so every key belongs to this widget
+    pub fn editing(&self) -> bool {
+        self.editing
+    }
+    fn lines(&self) -> Vec<Line<'static>> {
+        let mut text: Vec<Line> = Vec::new();
+        let mounts = match &self.mounts {
+            Ok(mounts) => mounts,
+            Err(e) => {
+                text.push(Line::from(Span::styled(
+                    format!("Error getting mounts: {e}"),
+                    Style::default().fg(Color::Red),
+                )));
+                return text;
+            }
+        };
+        let filter = self.filter.to_lowercase();
+        let shown: Vec<&MountEntry> = mounts
+            .iter()
+            .filter(|m| filter.is_empty() || describe(m).to_lowercase().contains(&filter))
+            .collect();
+
+        if self.editing || !self.filter.is_empty() {
+            text.push(Line::from(vec![
+                Span::styled("filter: ", Style::default().fg(Color::Green)),
+                Span::raw(self.filter.clone()),
+                Span::styled(if self.editing { "_" } else { "" }, Style::default().fg(Color::Yellow)),
+                Span::raw(format!(" ({} of {} mounts)", shown.len(), mounts.len())),
+            ]));
+        }
+        let differ = mounts.iter().filter(|m| m.differs_from_init == Some(true)).count();
+        if mounts.iter().all(|m| m.differs_from_init.is_none()) {
+            text.push(Line::from(Span::raw(
+                "PID 1's mounts can't be read, so they can't be compared",
+            )));
+        } else if differ > 0 {
+            text.push(Line::from(Span::styled(
+                format!("{differ} of {} mounts aren't in PID 1's view", mounts.len()),
+                Style::default().fg(Color::Yellow),
+            )));
+        }
+
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let dim = Style::default().add_modifier(Modifier::DIM);
+        for mount in shown {
+            let info = &mount.info;
+            let style = if mount.differs_from_init == Some(true) {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            let mut line = vec![
+                Span::styled(format!("{:<5} ", info.mnt_id), Style::default().fg(Color::Green)),
+                Span::styled(info.mount_point.display().to_string(), bold.patch(style)),
+                Span::styled(
+                    format!(" {} {}", info.fs_type, info.mount_source.as_deref().unwrap_or("none")),
+                    style,
+                ),
+            ];
+            if mount.open_files > 0 {
+                line.push(Span::styled(
+                    format!(
+                        " ({} open file{})",
+                        mount.open_files,
+                        if mount.open_files == 1 { "" } else { "s" }
+                    ),
+                    Style::default().fg(Color::Cyan),
+                ));
+            }
+            text.push(Line::from(line));
+            let root = if info.root == "/" {
+                String::new()
+            } else {
+                format!("  root {}", info.root)
+            };
+            text.push(Line::from(Span::styled(
+                format!("      {}  {}{root}", mount_options(info), mount_propagation(info)),
+                dim,
+            )));
+        }
+        text
+    }
+}
+
+impl AppWidget for MountsWidget {
+    const TITLE: &'static str = "Mounts";
+    type Json = Vec<json::Mount>;
+    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
+        let spans = Line::from(vec![
+            Span::raw("The "),
+            Span::styled("Mounts", Style::default().fg(Color::Yellow)),
+            Span::raw(
+                " tab shows the mount table of the process.  Mounts that PID 1 doesn't have are in yellow.  Press ",
+            ),
+            Span::styled("/", Style::default().fg(Color::Green)),
+            Span::raw(" to filter them."),
+        ]);
+        help_text.extend(Text::from(spans));
+
+        let text = self.lines();
+        let max_scroll = get_numlines_from_spans(text.iter(), area.width as usize) as i32 - area.height as i32;
+        self.scroll.set_max_scroll(max_scroll);
+
+        let widget = Paragraph::new(text)
+            .block(Block::default().borders(Borders::NONE))
+            .scroll((self.scroll.scroll_offset, 0));
+        f.render_widget(widget, area);
+    }
+    fn update(&mut self, proc: &Process, now: Instant) {
+        if now.saturating_duration_since(self.last_updated) > throttle(TEN_SECONDS) {
+            self.mounts = self.collector.collect(proc);
+            self.last_updated = now;
+        }
+    }
+    fn handle_input(&mut self, input: KeyEvent, height: u16) -> InputResult {
+        if self.editing {
+            match input.code {
+                KeyCode::Enter => self.editing = false,
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.editing = false;
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) if !input.modifiers.contains(KeyModifiers::CONTROL) => self.filter.push(c),
+                _ => return InputResult::None,
+            }
+            return InputResult::NeedsRedraw;
+        }
+        if input.code == KeyCode::Char('/') {
+            self.editing = true;
+            return InputResult::NeedsRedraw;
+        }
+        self.scroll.handle_input(input, height)
+    }
+    fn snapshot(&self, out: &mut String) -> std::fmt::Result {
+        write_lines(out, &self.lines())
+    }
+    fn json(&self) -> Section<Self::Json> {
+        match &self.mounts {
+            Ok(mounts) => Section::data(mounts.iter().map(json::Mount::from).collect()),
+            Err(e) => Section::error(e),
+        }
+    }
+}
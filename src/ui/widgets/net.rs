@@ -1,11 +1,12 @@
-use std::{collections::HashMap, time::Instant};
+use std::time::Instant;
 
 use crossterm::event::KeyEvent;
-use procfs::{
-    net::{TcpNetEntry, UdpNetEntry, UnixNetEntry},
-    process::{FDInfo, Process},
-    ProcError,
+use procdump::{
+    baseline::{self, Item},
+    collect::{socket_changes, Change, Collector, NetCollector, Socket},
+    json::{self, Section},
 };
+use procfs::process::Process;
 use ratatui::{
     layout::Rect,
     style::{Color, Modifier, Style},
@@ -14,52 +15,43 @@ use ratatui::{
     Frame,
 };
 
-use crate::ui::{InputResult, ScrollController, TWO_SECONDS};
+use crate::ui::{get_numlines_from_spans, throttle, InputResult, ScrollController, TWO_SECONDS};
 
-use super::AppWidget;
+use super::{write_lines, AppWidget};
 
 pub struct NetWidget {
-    tcp_map: HashMap<u64, TcpNetEntry>,
-    udp_map: HashMap<u64, UdpNetEntry>,
-    unix_map: HashMap<u64, UnixNetEntry>,
-    fd: Result<Vec<FDInfo>, ProcError>,
+    collector: NetCollector,
+    sockets: <NetCollector as Collector>::Output,
     last_updated: Instant,
+    changes: Vec<Change>,
     scroll: ScrollController,
 }
 
 impl NetWidget {
     pub fn new(proc: &Process) -> NetWidget {
+        let mut collector = NetCollector;
         NetWidget {
-            tcp_map: crate::util::get_tcp_table(proc),
-            udp_map: crate::util::get_udp_table(proc),
-            unix_map: crate::util::get_unix_table(proc),
-            fd: proc.fd().map(|iter| iter.filter_map(|f| f.ok()).collect()),
+            sockets: collector.collect(proc),
+            collector,
             last_updated: Instant::now(),
+            changes: Vec::new(),
             scroll: ScrollController::new(),
         }
     }
+    /// The changes found by the last refreshes, for the Events tab
+    pub fn take_changes(&mut self) -> Vec<Change> {
+        std::mem::take(&mut self.changes)
+    }
     pub fn draw_scrollbar(&self, f: &mut Frame, area: Rect) {
         self.scroll.draw_scrollbar(f, area)
     }
-}
-
-impl AppWidget for NetWidget {
-    const TITLE: &'static str = "Net";
-    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
+    fn lines(&self) -> Vec<Line<'static>> {
         let mut text: Vec<Line> = Vec::new();
-
-        let spans = Line::from(vec![
-            Span::raw("The "),
-            Span::styled("Net", Style::default().fg(Color::Yellow)),
-            Span::raw(" tab shows all of the open network connections."),
-        ]);
-        help_text.extend(Text::from(spans));
-
-        match &self.fd {
-            Ok(fd) => {
-                for fd in fd {
-                    if let procfs::process::FDTarget::Socket(inode) = fd.target {
-                        if let Some(entry) = self.tcp_map.get(&inode) {
+        match &self.sockets {
+            Ok(sockets) => {
+                for socket in sockets {
+                    match socket {
+                        Socket::Tcp(entry) => {
                             text.push(Line::from(vec![
                                 Span::styled("[tcp] ", Style::default().fg(Color::Green)),
                                 Span::raw(format!(
@@ -68,7 +60,7 @@ impl AppWidget for NetWidget {
                                 )),
                             ]));
                         }
-                        if let Some(entry) = self.udp_map.get(&inode) {
+                        Socket::Udp(entry) => {
                             text.push(Line::from(vec![
                                 Span::styled("[udp] ", Style::default().fg(Color::Blue)),
                                 Span::raw(format!(
@@ -77,7 +69,7 @@ impl AppWidget for NetWidget {
                                 )),
                             ]));
                         }
-                        if let Some(entry) = self.unix_map.get(&inode) {
+                        Socket::Unix(entry) => {
                             text.push(Line::from(vec![
                                 Span::styled("[unix]", Style::default().fg(Color::Yellow)),
                                 Span::raw(match entry.socket_type as i32 {
@@ -111,24 +103,55 @@ impl AppWidget for NetWidget {
                 Style::default().fg(Color::Gray).add_modifier(Modifier::DIM),
             )));
         }
+        text
+    }
+}
 
-        let max_scroll = crate::get_numlines_from_spans(text.iter(), area.width as usize) as i32 - area.height as i32;
+impl AppWidget for NetWidget {
+    const TITLE: &'static str = "Net";
+    type Json = Vec<json::Socket>;
+    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
+        let spans = Line::from(vec![
+            Span::raw("The "),
+            Span::styled("Net", Style::default().fg(Color::Yellow)),
+            Span::raw(" tab shows all of the open network connections."),
+        ]);
+        help_text.extend(Text::from(spans));
+
+        let text = self.lines();
+
+        let max_scroll = get_numlines_from_spans(text.iter(), area.width as usize) as i32 - area.height as i32;
         self.scroll.set_max_scroll(max_scroll);
         let widget = Paragraph::new(text)
             .block(Block::default().borders(Borders::NONE))
             .scroll((self.scroll.scroll_offset, 0));
         f.render_widget(widget, area);
     }
-    fn update(&mut self, proc: &Process) {
-        if self.last_updated.elapsed() > TWO_SECONDS {
-            self.fd = proc.fd().map(|iter| iter.filter_map(|f| f.ok()).collect());
-            self.tcp_map = crate::util::get_tcp_table(proc);
-            self.udp_map = crate::util::get_udp_table(proc);
-            self.unix_map = crate::util::get_unix_table(proc);
-            self.last_updated = Instant::now();
+    fn update(&mut self, proc: &Process, now: Instant) {
+        if now.saturating_duration_since(self.last_updated) > throttle(TWO_SECONDS) {
+            let old = std::mem::replace(&mut self.sockets, self.collector.collect(proc));
+            if let (Ok(old), Ok(new)) = (&old, &self.sockets) {
+                self.changes.extend(socket_changes(old, new));
+            }
+            self.last_updated = now;
         }
     }
     fn handle_input(&mut self, input: KeyEvent, height: u16) -> InputResult {
         self.scroll.handle_input(input, height)
     }
+    fn snapshot(&self, out: &mut String) -> std::fmt::Result {
+        write_lines(out, &self.lines())
+    }
+    fn json(&self) -> Section<Self::Json> {
+        match &self.sockets {
+            Ok(sockets) => Section::data(sockets.iter().map(json::Socket::from).collect()),
+            Err(e) => Section::error(e),
+        }
+    }
+    fn items(&self) -> Option<Vec<Item>> {
+        self.sockets
+            .as_ref()
+            .ok()
+            .map(|sockets| baseline::socket_items(sockets))
+    }
 }
@@ -0,0 +1,143 @@
+use std::{sync::Arc, time::Instant};
+
+use crossterm::event::KeyEvent;
+use procdump::{
+    collect::{Collector, NamespaceCollector, ProcessNamespaces},
+    json::{self, Section},
+    source::DataSource,
+};
+use procfs::process::Process;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Paragraph, Wrap},
+    Frame,
+};
+
+use crate::ui::{get_numlines_from_spans, throttle, InputResult, ScrollController, TEN_SECONDS};
+
+use super::{write_lines, AppWidget};
+
+pub struct NamespacesWidget {
+    collector: NamespaceCollector,
+    namespaces: <NamespaceCollector as Collector>::Output,
+    last_updated: Instant,
+    scroll: ScrollController,
+}
+
+impl NamespacesWidget {
+    pub fn new(proc: &Process, source: Arc<dyn DataSource>) -> NamespacesWidget {
+        let mut collector = NamespaceCollector::new(source);
+        NamespacesWidget {
+            namespaces: collector.collect(proc),
+            collector,
+            last_updated: Instant::now(),
+            scroll: ScrollController::new(),
+        }
+    }
+    pub fn draw_scrollbar(&self, f: &mut Frame, area: Rect) {
+        self.scroll.draw_scrollbar(f, area)
+    }
+    fn lines(ns: &ProcessNamespaces) -> Vec<Line<'static>> {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let mut lines = Vec::new();
+        let ids = |ids: &Option<Vec<i32>>| match ids {
+            Some(ids) => ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(" "),
+            None => "unknown".to_owned(),
+        };
+        lines.push(Line::from(vec![
+            Span::styled("NSpid:  ", bold),
+            Span::raw(ids(&ns.nspid)),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("NStgid: ", bold),
+            Span::raw(ids(&ns.nstgid)),
+        ]));
+        lines.push(Line::default());
+
+        for info in &ns.namespaces {
+            let (compared, style) = match info.differs_from_init {
+                Some(true) => ("differs from PID 1", Style::default().fg(Color::Yellow)),
+                Some(false) => ("same as PID 1", Style::default()),
+                None => ("PID 1 is unreadable", Style::default().add_modifier(Modifier::DIM)),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{:<7}", info.kind), bold.patch(style)),
+                Span::raw(format!("{:<12}", info.inode)),
+                Span::styled(compared, style),
+            ]));
+            let shared = if info.shared_with.is_empty() {
+                "no other processes".to_owned()
+            } else {
+                let members: Vec<String> = info
+                    .shared_with
+                    .iter()
+                    .map(|m| format!("{} ({})", m.pid, m.comm))
+                    .collect();
+                format!(
+                    "shared with {} other process{}: {}",
+                    members.len(),
+                    if members.len() == 1 { "" } else { "es" },
+                    members.join(", ")
+                )
+            };
+            lines.push(Line::from(Span::styled(
+                format!("       {shared}"),
+                Style::default().fg(Color::Gray),
+            )));
+        }
+        lines
+    }
+}
+
+impl AppWidget for NamespacesWidget {
+    const TITLE: &'static str = "Namespaces";
+    type Json = json::Namespaces;
+    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
+        let line = Line::from(vec![
+            Span::raw("The "),
+            Span::styled("Namespaces", Style::default().fg(Color::Yellow)),
+            Span::raw(" tab shows the namespaces of the process, and which other processes are in them."),
+        ]);
+        help_text.extend(Text::from(line));
+
+        let lines = match &self.namespaces {
+            Ok(ns) => Self::lines(ns),
+            Err(e) => vec![Line::from(Span::styled(
+                format!("Error getting namespaces: {e}"),
+                Style::default().fg(Color::Red),
+            ))],
+        };
+        let num_lines = get_numlines_from_spans(lines.iter(), area.width as usize) as i32;
+        self.scroll.set_max_scroll(num_lines - area.height as i32);
+
+        let widget = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll.scroll_offset, 0));
+        f.render_widget(widget, area);
+    }
+    fn update(&mut self, proc: &Process, now: Instant) {
+        if now.saturating_duration_since(self.last_updated) > throttle(TEN_SECONDS) {
+            self.namespaces = self.collector.collect(proc);
+            self.last_updated = now;
+        }
+    }
+    fn handle_input(&mut self, input: KeyEvent, height: u16) -> InputResult {
+        self.scroll.handle_input(input, height)
+    }
+    fn snapshot(&self, out: &mut String) -> std::fmt::Result {
+        use std::fmt::Write;
+
+        match &self.namespaces {
+            Ok(ns) => write_lines(out, &Self::lines(ns)),
+            Err(e) => writeln!(out, "Error getting namespaces: {e}"),
+        }
+    }
+    fn json(&self) -> Section<Self::Json> {
+        match &self.namespaces {
+            Ok(ns) => Section::data(ns.into()),
+            Err(e) => Section::error(e),
+        }
+    }
+}
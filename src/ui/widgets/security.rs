@@ -0,0 +1,190 @@
+use std::time::Instant;
+
+use crossterm::event::KeyEvent;
+use procdump::{
+    collect::{
+        capability_names, named_capability_sets, seccomp_mode, Collector, NamedId, SecurityCollector, SecurityInfo,
+        CAPABILITIES,
+    },
+    json::{self, Section},
+};
+use procfs::process::Process;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Paragraph, Wrap},
+    Frame,
+};
+
+use crate::ui::{get_numlines_from_spans, throttle, InputResult, ScrollController, TEN_SECONDS};
+
+use super::{write_lines, AppWidget};
+
+pub struct SecurityWidget {
+    collector: SecurityCollector,
+    info: <SecurityCollector as Collector>::Output,
+    last_updated: Instant,
+    scroll: ScrollController,
+}
+
+/// Formats an ID like `1000 (alice)`
+fn named(id: &NamedId) -> String {
+    format!("{} ({})", id.id, id.name)
+}
+
+/// The capabilities in a mask, shortened to "all" (or "all except ...") when most of them are set
+fn describe_capabilities(mask: u64) -> String {
+    let all = (1u64 << CAPABILITIES.len()) - 1;
+    if mask == 0 {
+        "none".to_owned()
+    } else if mask == all {
+        "all".to_owned()
+    } else if (mask & all).count_ones() as usize > CAPABILITIES.len() / 2 {
+        let missing = capability_names(!mask & all).join(", ");
+        let extra = capability_names(mask & !all);
+        if extra.is_empty() {
+            format!("all except {missing}")
+        } else {
+            format!("all except {missing}, plus {}", extra.join(", "))
+        }
+    } else {
+        capability_names(mask).join(", ")
+    }
+}
+
+impl SecurityWidget {
+    pub fn new(proc: &Process) -> SecurityWidget {
+        let mut collector = SecurityCollector;
+        SecurityWidget {
+            info: collector.collect(proc),
+            collector,
+            last_updated: Instant::now(),
+            scroll: ScrollController::new(),
+        }
+    }
+    pub fn draw_scrollbar(&self, f: &mut Frame, area: Rect) {
+        self.scroll.draw_scrollbar(f, area)
+    }
+    fn lines(info: &SecurityInfo) -> Vec<Line<'static>> {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let yellow = Style::default().fg(Color::Yellow);
+        let field = |name: &str, value: String, style: Style| {
+            Line::from(vec![
+                Span::styled(format!("{name:<12}"), bold),
+                Span::styled(value, style),
+            ])
+        };
+        let ids = |ids: &[NamedId; 4]| {
+            format!(
+                "real {}  effective {}  saved {}  fs {}",
+                named(&ids[0]),
+                named(&ids[1]),
+                named(&ids[2]),
+                named(&ids[3])
+            )
+        };
+        // when the IDs don't all match, the process has changed (or can change) who it runs as
+        let mixed = |ids: &[NamedId; 4]| {
+            if ids.iter().all(|id| id.id == ids[0].id) {
+                Style::default()
+            } else {
+                yellow
+            }
+        };
+
+        let mut lines = vec![
+            field("UIDs", ids(&info.uids), mixed(&info.uids)),
+            field("GIDs", ids(&info.gids), mixed(&info.gids)),
+        ];
+        let groups = if info.groups.is_empty() {
+            "none".to_owned()
+        } else {
+            info.groups.iter().map(named).collect::<Vec<_>>().join(", ")
+        };
+        lines.push(field("Groups", groups, Style::default()));
+        lines.push(Line::default());
+
+        for (name, mask) in named_capability_sets(info) {
+            let (value, style) = match mask {
+                Some(mask) => (
+                    format!("{mask:016x}  {}", describe_capabilities(mask)),
+                    if mask != 0 && name == "CapEff" {
+                        yellow
+                    } else {
+                        Style::default()
+                    },
+                ),
+                None => ("not supported by the kernel".to_owned(), Style::default()),
+            };
+            lines.push(field(name, value, style));
+        }
+        lines.push(Line::default());
+
+        let seccomp = match info.seccomp {
+            Some(mode) => seccomp_mode(mode).to_owned(),
+            None => "not supported by the kernel".to_owned(),
+        };
+        lines.push(field("Seccomp", seccomp, Style::default()));
+        let no_new_privs = match info.no_new_privs {
+            Some(true) => "yes",
+            Some(false) => "no",
+            None => "not supported by the kernel",
+        };
+        lines.push(field("NoNewPrivs", no_new_privs.to_owned(), Style::default()));
+        let label = info.lsm_label.clone().unwrap_or_else(|| "none".to_owned());
+        lines.push(field("LSM label", label, Style::default()));
+        lines
+    }
+}
+
+impl AppWidget for SecurityWidget {
+    const TITLE: &'static str = "Security";
+    type Json = json::Security;
+    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
+        let line = Line::from(vec![
+            Span::raw("The "),
+            Span::styled("Security", Style::default().fg(Color::Yellow)),
+            Span::raw(" tab shows who the process runs as, its capabilities, and its seccomp and LSM settings."),
+        ]);
+        help_text.extend(Text::from(line));
+
+        let lines = match &self.info {
+            Ok(info) => Self::lines(info),
+            Err(e) => vec![Line::from(Span::styled(
+                format!("Error getting security info: {e}"),
+                Style::default().fg(Color::Red),
+            ))],
+        };
+        let num_lines = get_numlines_from_spans(lines.iter(), area.width as usize) as i32;
+        self.scroll.set_max_scroll(num_lines - area.height as i32);
+
+        let widget = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll.scroll_offset, 0));
+        f.render_widget(widget, area);
+    }
+    fn update(&mut self, proc: &Process, now: Instant) {
+        if now.saturating_duration_since(self.last_updated) > throttle(TEN_SECONDS) {
+            self.info = self.collector.collect(proc);
+            self.last_updated = now;
+        }
+    }
+    fn handle_input(&mut self, input: KeyEvent, height: u16) -> InputResult {
+        self.scroll.handle_input(input, height)
+    }
+    fn snapshot(&self, out: &mut String) -> std::fmt::Result {
+        use std::fmt::Write;
+
+        match &self.info {
+            Ok(info) => write_lines(out, &Self::lines(info)),
+            Err(e) => writeln!(out, "Error getting security info: {e}"),
+        }
+    }
+    fn json(&self) -> Section<Self::Json> {
+        match &self.info {
+            Ok(info) => Section::data(info.into()),
+            Err(e) => Section::error(e),
+        }
+    }
+}
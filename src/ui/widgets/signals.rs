@@ -0,0 +1,328 @@
+use std::time::Instant;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use procdump::{
+    collect::{signal_names, Collector, ProcessSignals, SignalsCollector},
+    json::{self, Section},
+    pidfd,
+    source::{DataSource, ProcessId},
+    util::{parse_signal, signal_name},
+};
+use procfs::process::Process;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Paragraph, Wrap},
+    Frame,
+};
+
+use crate::ui::{throttle, InputResult, TWO_SECONDS};
+
+use super::{write_lines, AppWidget};
+
+/// The signals that a process is usually asked to exit with, whose fate is spelled out at the top
+const EXIT_SIGNALS: &[i32] = &[libc::SIGTERM, libc::SIGINT, libc::SIGHUP, libc::SIGQUIT];
+
+/// The steps of sending a signal
+enum Prompt {
+    /// The signal is being typed in
+    Choosing(String),
+    /// Waiting for a `y` to send this signal.  The thread is picked when the signal is, so that the
+    /// signal goes to the one named in the prompt even if the threads change in the meantime
+    Confirming {
+        signal: i32,
+        /// `None` for the whole process
+        tid: Option<i32>,
+        target: String,
+    },
+}
+
+pub struct SignalsWidget {
+    collector: SignalsCollector,
+    signals: <SignalsCollector as Collector>::Output,
+    last_updated: Instant,
+    /// The process that signals are sent to
+    id: Option<ProcessId>,
+    /// Whether `id` is a process on this system right now, rather than one in a recording or a copy
+    /// of `/proc`.  Otherwise no signals are sent, since the PID could belong to anything here
+    live: bool,
+    /// What a signal would be sent to: 0 is the whole process, and N is the Nth thread
+    selected: usize,
+    prompt: Option<Prompt>,
+    /// The outcome of the last signal that was sent, and whether it failed
+    message: Option<(String, bool)>,
+}
+
+/// Formats a signal mask as a list of names
+fn names(mask: u64) -> String {
+    if mask == 0 {
+        "none".to_owned()
+    } else {
+        signal_names(mask).join(", ")
+    }
+}
+
+impl SignalsWidget {
+    pub fn new(proc: &Process, source: &dyn DataSource) -> SignalsWidget {
+        let mut collector = SignalsCollector;
+        SignalsWidget {
+            signals: collector.collect(proc),
+            collector,
+            last_updated: Instant::now(),
+            id: ProcessId::of(proc).ok(),
+            live: source.is_live(),
+            selected: 0,
+            prompt: None,
+            message: None,
+        }
+    }
+    /// True while a signal is being picked, so that keys go to the prompt
+    pub fn editing(&self) -> bool {
+        self.prompt.is_some()
+    }
+    /// The selected thread (`None` for the whole process), and a description of it
+    fn target(&self) -> (Option<i32>, String) {
+        let thread = self.selected.checked_sub(1).and_then(|idx| {
+            let signals = self.signals.as_ref().ok()?;
+            signals.threads.get(idx)
+        });
+        match (thread, self.id) {
+            (Some(thread), _) => (Some(thread.tid), format!("thread {} ({})", thread.tid, thread.comm)),
+            (None, Some(id)) => (None, format!("process {}", id.pid)),
+            (None, None) => (None, "the process".to_owned()),
+        }
+    }
+    fn send(&mut self, signal: i32, tid: Option<i32>, target: &str) {
+        self.message = Some(match self.id.map(|id| pidfd::send_signal(id, tid, signal)) {
+            Some(Ok(())) => (format!("Sent {} to {target}", signal_name(signal)), false),
+            Some(Err(e)) => (format!("Unable to send {} to {target}: {e}", signal_name(signal)), true),
+            None => (
+                "The process can't be identified, so no signal was sent".to_owned(),
+                true,
+            ),
+        });
+    }
+    /// The lines to show, and which of them is selected.  `interactive` adds the selection marker,
+    /// the prompt, and the outcome of the last signal
+    fn lines(&self, signals: &ProcessSignals, interactive: bool) -> (Vec<Line<'static>>, usize) {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let green = Style::default().fg(Color::Green);
+        let yellow = Style::default().fg(Color::Yellow);
+        let mut lines = Vec::new();
+
+        if interactive {
+            let (_, target) = self.target();
+            match &self.prompt {
+                Some(Prompt::Choosing(text)) => lines.push(Line::from(vec![
+                    Span::styled(format!("Signal to send to {target} (like TERM, KILL, or 9): "), green),
+                    Span::raw(text.clone()),
+                    Span::styled("_", yellow),
+                ])),
+                Some(Prompt::Confirming { signal, target, .. }) => lines.push(Line::from(vec![
+                    Span::styled(format!("Send {} to {target}? ", signal_name(*signal)), yellow),
+                    Span::styled("y", green),
+                    Span::raw(" sends it, anything else cancels"),
+                ])),
+                None => match &self.message {
+                    Some((message, failed)) => lines.push(Line::from(Span::styled(
+                        message.clone(),
+                        if *failed {
+                            Style::default().fg(Color::Red)
+                        } else {
+                            yellow
+                        },
+                    ))),
+                    None if !self.live => lines.push(Line::from(Span::raw(
+                        "This isn't a running process, so no signals can be sent to it",
+                    ))),
+                    None => lines.push(Line::from(vec![
+                        Span::raw("Select the process or a thread with the arrow keys, and press "),
+                        Span::styled("k", green),
+                        Span::raw(" to send it a signal"),
+                    ])),
+                },
+            }
+            lines.push(Line::default());
+        }
+
+        for signal in EXIT_SIGNALS {
+            let disposition = signals.disposition(*signal);
+            let style = if disposition == "default action" {
+                Style::default()
+            } else {
+                yellow
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{:<18}", signal_name(*signal)), bold),
+                Span::styled(disposition, style),
+            ]));
+        }
+        lines.push(Line::default());
+
+        let mut selected_line = lines.len();
+        let marker = |idx: usize| {
+            if interactive && idx == self.selected {
+                "> "
+            } else {
+                "  "
+            }
+        };
+        lines.push(Line::from(Span::styled(
+            format!("{}Whole process", marker(0)),
+            if interactive && self.selected == 0 {
+                green.add_modifier(Modifier::BOLD)
+            } else {
+                bold
+            },
+        )));
+        for (name, mask) in [
+            ("Pending (ShdPnd)", signals.shared_pending),
+            ("Ignored (SigIgn)", signals.ignored),
+            ("Caught (SigCgt)", signals.caught),
+        ] {
+            lines.push(Line::from(vec![
+                Span::raw(format!("    {name:<18}")),
+                Span::raw(names(mask)),
+            ]));
+        }
+
+        for (idx, thread) in signals.threads.iter().enumerate() {
+            if interactive && idx + 1 == self.selected {
+                selected_line = lines.len();
+            }
+            lines.push(Line::from(Span::styled(
+                format!("{}Thread {} ({})", marker(idx + 1), thread.tid, thread.comm),
+                if interactive && idx + 1 == self.selected {
+                    green.add_modifier(Modifier::BOLD)
+                } else {
+                    bold
+                },
+            )));
+            lines.push(Line::from(vec![
+                Span::raw(format!("    {:<18}", "Pending (SigPnd)")),
+                Span::styled(
+                    names(thread.pending),
+                    if thread.pending != 0 { yellow } else { Style::default() },
+                ),
+            ]));
+            lines.push(Line::from(vec![
+                Span::raw(format!("    {:<18}", "Blocked (SigBlk)")),
+                Span::raw(names(thread.blocked)),
+            ]));
+        }
+        (lines, selected_line)
+    }
+}
+
+impl AppWidget for SignalsWidget {
+    const TITLE: &'static str = "Signals";
+    type Json = json::Signals;
+    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
+        let line = Line::from(vec![
+            Span::raw("The "),
+            Span::styled("Signals", Style::default().fg(Color::Yellow)),
+            Span::raw(" tab shows which signals are pending, blocked, ignored, and caught.  Press "),
+            Span::styled("k", Style::default().fg(Color::Green)),
+            Span::raw(" to send a signal to the selected process or thread."),
+        ]);
+        help_text.extend(Text::from(line));
+
+        let (lines, selected_line) = match &self.signals {
+            Ok(signals) => self.lines(signals, true),
+            Err(e) => (
+                vec![Line::from(Span::styled(
+                    format!("Error getting signals: {e}"),
+                    Style::default().fg(Color::Red),
+                ))],
+                0,
+            ),
+        };
+        // keep the selection in the middle, once there's enough to scroll through
+        let max_scroll = (lines.len() as i32 - area.height as i32).max(0);
+        let scroll = (selected_line as i32 - area.height as i32 / 2).clamp(0, max_scroll);
+        let widget = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .scroll((scroll as u16, 0));
+        f.render_widget(widget, area);
+    }
+    fn update(&mut self, proc: &Process, now: Instant) {
+        if now.saturating_duration_since(self.last_updated) > throttle(TWO_SECONDS) {
+            self.signals = self.collector.collect(proc);
+            let threads = self.signals.as_ref().map_or(0, |s| s.threads.len());
+            self.selected = self.selected.min(threads);
+            self.last_updated = now;
+        }
+    }
+    fn handle_input(&mut self, input: KeyEvent, _height: u16) -> InputResult {
+        match self.prompt.take() {
+            Some(Prompt::Choosing(mut text)) => {
+                match input.code {
+                    KeyCode::Enter => match parse_signal(&text) {
+                        Some(signal) => {
+                            let (tid, target) = self.target();
+                            self.prompt = Some(Prompt::Confirming { signal, tid, target });
+                        }
+                        None => self.message = Some((format!("{text:?} isn't a signal"), true)),
+                    },
+                    KeyCode::Esc => {}
+                    KeyCode::Backspace => {
+                        text.pop();
+                        self.prompt = Some(Prompt::Choosing(text));
+                    }
+                    KeyCode::Char(c) if !input.modifiers.contains(KeyModifiers::CONTROL) => {
+                        text.push(c);
+                        self.prompt = Some(Prompt::Choosing(text));
+                    }
+                    _ => {
+                        self.prompt = Some(Prompt::Choosing(text));
+                        return InputResult::None;
+                    }
+                }
+                return InputResult::NeedsRedraw;
+            }
+            Some(Prompt::Confirming { signal, tid, target }) => {
+                if input.code == KeyCode::Char('y') {
+                    self.send(signal, tid, &target);
+                    return InputResult::NeedsUpdate;
+                }
+                self.message = Some(("No signal was sent".to_owned(), false));
+                return InputResult::NeedsRedraw;
+            }
+            None => {}
+        }
+        let threads = self.signals.as_ref().map_or(0, |s| s.threads.len());
+        match input.code {
+            KeyCode::Char('k') if !self.live => {
+                self.message = Some((
+                    "Signals can only be sent to a running process, not to a recording or a copy of /proc".to_owned(),
+                    true,
+                ));
+            }
+            KeyCode::Char('k') => {
+                self.prompt = Some(Prompt::Choosing(String::new()));
+                self.message = None;
+            }
+            KeyCode::Up if self.selected > 0 => self.selected -= 1,
+            KeyCode::Down if self.selected < threads => self.selected += 1,
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = threads,
+            _ => return InputResult::None,
+        }
+        InputResult::NeedsRedraw
+    }
+    fn snapshot(&self, out: &mut String) -> std::fmt::Result {
+        use std::fmt::Write;
+
+        match &self.signals {
+            Ok(signals) => write_lines(out, &self.lines(signals, false).0),
+            Err(e) => writeln!(out, "Error getting signals: {e}"),
+        }
+    }
+    fn json(&self) -> Section<Self::Json> {
+        match &self.signals {
+            Ok(signals) => Section::data(signals.into()),
+            Err(e) => Section::error(e),
+        }
+    }
+}
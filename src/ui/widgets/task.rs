@@ -1,95 +1,169 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use crossterm::event::KeyEvent;
+use crossterm::event::{KeyCode, KeyEvent};
 use indexmap::IndexMap;
-use procfs::{process::Process, ProcResult};
+use procdump::{
+    baseline::{self, Item},
+    collect::{group_stacks, task_changes, Change, Collector, TaskCollector, TaskInfo},
+    json::{self, Section},
+};
+use procfs::process::Process;
 use ratatui::{
     layout::Rect,
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
     text::{Line, Span, Text},
     widgets::{Block, Borders, Paragraph},
     Frame,
 };
 
-use crate::ui::{InputResult, ScrollController, TWO_SECONDS};
+use crate::ui::{get_numlines_from_spans, throttle, InputResult, ScrollController, TWO_SECONDS};
 
-use super::AppWidget;
+use super::{write_lines, AppWidget};
 
-struct TaskData {
-    task: procfs::process::Task,
-    _io: procfs::process::Io,
-    stat: procfs::process::Stat,
-}
-impl TaskData {
-    fn new(task: procfs::process::Task) -> Option<Self> {
-        match (task.io(), task.stat()) {
-            (Ok(io), Ok(stat)) => Some(TaskData { task, _io: io, stat }),
-            _ => None,
-        }
-    }
-}
 pub struct TaskWidget {
     last_updated: Instant,
-    tasks: ProcResult<IndexMap<i32, TaskData>>,
-    last_tasks: Option<IndexMap<i32, TaskData>>,
+    collector: TaskCollector,
+    tasks: <TaskCollector as Collector>::Output,
+    last_tasks: Option<IndexMap<i32, TaskInfo>>,
+    /// The time between `last_tasks` and `tasks`
+    elapsed: Duration,
+    /// Clock ticks per second, to turn CPU times into percentages
+    tps: u64,
+    changes: Vec<Change>,
+    force_update: bool,
     scroll: ScrollController,
 }
-impl TaskWidget {
-    pub fn new(proc: &Process) -> TaskWidget {
-        let tasks = proc
-            .tasks()
-            .map(|i| {
-                i.filter_map(|t| t.ok()).filter_map(|t| {
-                    let tid = t.tid;
-                    TaskData::new(t).map(|td| (tid, td))
-                })
-            })
-            .map(IndexMap::from_iter);
 
+/// How many TIDs are listed for a group of threads before the rest are just counted
+const MAX_TIDS: usize = 8;
+impl TaskWidget {
+    pub fn new(proc: &Process, tps: u64) -> TaskWidget {
+        let mut collector = TaskCollector::default();
         TaskWidget {
             last_updated: Instant::now(),
-            tasks,
+            tasks: collector.collect(proc),
+            collector,
             last_tasks: None,
+            elapsed: Duration::ZERO,
+            tps,
+            changes: Vec::new(),
+            force_update: false,
             scroll: ScrollController::new(),
         }
     }
+    /// The changes found by the last refreshes, for the Events tab
+    pub fn take_changes(&mut self) -> Vec<Change> {
+        std::mem::take(&mut self.changes)
+    }
+    /// Switches between listing the threads, and grouping them by where they are in the kernel
+    /// (takes effect on the next update)
+    pub fn set_want_stacks(&mut self, want_stacks: bool) {
+        if self.collector.stacks != want_stacks {
+            self.collector.stacks = want_stacks;
+            self.force_update = true;
+        }
+    }
     pub fn draw_scrollbar(&self, f: &mut Frame, area: Rect) {
         self.scroll.draw_scrollbar(f, area)
     }
-}
-impl AppWidget for TaskWidget {
-    const TITLE: &'static str = "Task";
-    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
-        let spans = Line::from(vec![
-            Span::raw("The "),
-            Span::styled("Task", Style::default().fg(Color::Yellow)),
-            Span::raw(" tab shows each thread in the process, its name, how much CPU it's using, and its state."),
-        ]);
-        help_text.extend(Text::from(spans));
-
+    fn lines(&self) -> Vec<Line<'static>> {
         let mut text: Vec<Line> = Vec::new();
-
         if let Ok(tasks) = &self.tasks {
             for task in tasks.values() {
                 let name = &task.stat.comm;
 
-                let cpu_str = if let Some(prev) = self.last_tasks.as_ref().and_then(|map| map.get(&task.task.tid)) {
-                    let diff = task.stat.utime - prev.stat.utime;
-                    format!("{:.1}%", diff as f64 / 2.0)
-                } else {
-                    "?.?%".to_string()
+                let prev = self.last_tasks.as_ref().and_then(|map| map.get(&task.tid));
+                // samples closer together than 100ms are too noisy to be useful
+                let cpu_str = match prev {
+                    Some(prev) if self.elapsed >= Duration::from_millis(100) => {
+                        let ticks =
+                            (task.stat.utime + task.stat.stime).saturating_sub(prev.stat.utime + prev.stat.stime);
+                        let usage = ticks as f64 / self.tps as f64 / self.elapsed.as_secs_f64();
+                        format!("{:.1}%", usage * 100.0)
+                    }
+                    _ => "?.?%".to_string(),
                 };
 
                 text.push(Line::from(Span::raw(format!(
                     "{:<16} {:<7} {:<7} {}",
-                    name, task.task.tid, cpu_str, task.stat.state
+                    name, task.tid, cpu_str, task.stat.state
                 ))));
             }
         } else {
             text.push(Line::from(Span::raw("Error reading tasks".to_string())));
         }
+        text
+    }
+    /// The threads grouped by their kernel stacks, like "40 threads in futex_wait_queue"
+    fn stack_lines(&self) -> Vec<Line<'static>> {
+        let mut text: Vec<Line> = Vec::new();
+        let tasks = match &self.tasks {
+            Ok(tasks) => tasks,
+            Err(..) => {
+                text.push(Line::from(Span::raw("Error reading tasks".to_string())));
+                return text;
+            }
+        };
+        if self.force_update {
+            text.push(Line::from(Span::styled(
+                "Kernel stacks will be read on the next refresh",
+                Style::default().add_modifier(Modifier::DIM),
+            )));
+            return text;
+        }
+        if tasks.values().all(|task| task.stack.is_none()) {
+            text.push(Line::from(Span::styled(
+                "Kernel stacks can't be read (that needs root), so threads are grouped by wchan",
+                Style::default().add_modifier(Modifier::DIM),
+            )));
+            text.push(Line::default());
+        }
+        for group in group_stacks(tasks.values()) {
+            let count = group.tids.len();
+            let mut tids: Vec<String> = group.tids.iter().take(MAX_TIDS).map(|tid| tid.to_string()).collect();
+            if count > MAX_TIDS {
+                tids.push(format!("and {} more", count - MAX_TIDS));
+            }
+            text.push(Line::from(vec![
+                Span::styled(
+                    format!("{count} thread{} in ", if count == 1 { "" } else { "s" }),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    group.place().to_owned(),
+                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!("  ({})", tids.join(", "))),
+            ]));
+            for frame in group.stack.unwrap_or_default() {
+                text.push(Line::from(Span::raw(format!("    {frame}"))));
+            }
+        }
+        text
+    }
+}
+impl AppWidget for TaskWidget {
+    const TITLE: &'static str = "Task";
+    type Json = Vec<json::Task>;
+    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
+        let spans = Line::from(vec![
+            Span::raw("The "),
+            Span::styled("Task", Style::default().fg(Color::Yellow)),
+            Span::raw(
+                " tab shows each thread in the process, its name, how much CPU it's using, and its state.  Press ",
+            ),
+            Span::styled("s", Style::default().fg(Color::Green)),
+            Span::raw(" to group the threads by where they're waiting in the kernel."),
+        ]);
+        help_text.extend(Text::from(spans));
 
-        let max_scroll = crate::get_numlines_from_spans(text.iter(), area.width as usize) as i32 - area.height as i32;
+        let text = if self.collector.stacks {
+            self.stack_lines()
+        } else {
+            self.lines()
+        };
+
+        let max_scroll = get_numlines_from_spans(text.iter(), area.width as usize) as i32 - area.height as i32;
         self.scroll.set_max_scroll(max_scroll);
 
         let widget = Paragraph::new(text)
@@ -97,25 +171,47 @@ impl AppWidget for TaskWidget {
             .scroll((self.scroll.scroll_offset, 0));
         f.render_widget(widget, area);
     }
-    fn update(&mut self, proc: &Process) {
-        if self.last_updated.elapsed() > TWO_SECONDS {
-            let mut new_tasks = proc
-                .tasks()
-                .map(|i| {
-                    i.filter_map(|t| t.ok()).filter_map(|t| {
-                        let tid = t.tid;
-                        TaskData::new(t).map(|td| (tid, td))
-                    })
-                })
-                .map(IndexMap::from_iter);
+    fn update(&mut self, proc: &Process, now: Instant) {
+        if now.saturating_duration_since(self.last_updated) > throttle(TWO_SECONDS) || self.force_update {
+            let mut new_tasks = self.collector.collect(proc);
             std::mem::swap(&mut new_tasks, &mut self.tasks);
             // "new_tasks" now contains the "old_tasks"
+            if let (Ok(old), Ok(new)) = (&new_tasks, &self.tasks) {
+                self.changes.extend(task_changes(old, new));
+            }
             self.last_tasks = new_tasks.ok();
+            self.elapsed = now.saturating_duration_since(self.last_updated);
 
-            self.last_updated = Instant::now();
+            self.last_updated = now;
+            self.force_update = false;
         }
     }
     fn handle_input(&mut self, input: KeyEvent, height: u16) -> InputResult {
+        if input.code == KeyCode::Char('s') {
+            self.set_want_stacks(!self.collector.stacks);
+            self.scroll.scroll_offset = 0;
+            return InputResult::NeedsUpdate;
+        }
         self.scroll.handle_input(input, height)
     }
+    fn snapshot(&self, out: &mut String) -> std::fmt::Result {
+        write_lines(out, &self.lines())?;
+        if self.collector.stacks {
+            out.push('\n');
+            write_lines(out, &self.stack_lines())?;
+        }
+        Ok(())
+    }
+    fn json(&self) -> Section<Self::Json> {
+        match &self.tasks {
+            Ok(tasks) => Section::data(tasks.values().map(json::Task::from).collect()),
+            Err(e) => Section::error(e),
+        }
+    }
+    fn items(&self) -> Option<Vec<Item>> {
+        self.tasks
+            .as_ref()
+            .ok()
+            .map(|tasks| baseline::task_items(tasks.values()))
+    }
 }
@@ -1,6 +1,12 @@
-use std::time::Instant;
+use std::{sync::Arc, time::Instant};
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use procdump::{
+    collect::{child_changes, Change},
+    json::{self, Section},
+    source::{DataSource, ProcessId},
+    util,
+};
 use procfs::process::Process;
 use ratatui::{
     layout::Rect,
@@ -10,14 +16,12 @@ use ratatui::{
     Frame,
 };
 
-use crate::{
-    ui::{InputResult, TWO_SECONDS},
-    util,
-};
+use crate::ui::{throttle, InputResult, TWO_SECONDS};
 
-use super::AppWidget;
+use super::{write_lines, AppWidget};
 
 pub struct TreeWidget {
+    source: Arc<dyn DataSource>,
     tree: util::ProcessTree,
     last_updated: Instant,
     force_update: bool,
@@ -25,37 +29,36 @@ pub struct TreeWidget {
     selected_pid: i32,
     show_all: bool,
     this_pid: i32,
+    changes: Vec<Change>,
 }
 
 impl TreeWidget {
-    pub fn new(proc: &Process) -> TreeWidget {
-        let tree = util::ProcessTree::new(None).unwrap();
+    pub fn new(proc: &Process, source: Arc<dyn DataSource>) -> TreeWidget {
+        let tree = util::ProcessTree::new(&*source, None).unwrap();
         TreeWidget {
+            source,
             tree,
             show_all: true,
             force_update: false,
             last_updated: Instant::now(),
             selected_pid: proc.pid,
             this_pid: proc.pid,
+            changes: Vec::new(),
         }
     }
-    pub fn get_selected_pid(&self) -> i32 {
-        self.selected_pid
+    /// The selected process.  This has the start time that the tree was built with, so it doesn't
+    /// match a newer process that has reused the PID since
+    pub fn get_selected(&self) -> Option<ProcessId> {
+        self.tree.entries.get(&self.selected_pid).map(|entry| ProcessId {
+            pid: entry.pid,
+            starttime: entry.starttime,
+        })
     }
-}
-
-impl AppWidget for TreeWidget {
-    const TITLE: &'static str = "Tree";
-    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
-        let spans = Line::from(vec![
-            Span::raw("The "),
-            Span::styled("Tree", Style::default().fg(Color::Yellow)),
-            Span::raw(" tab shows the currently selected process in a process tree. Press "),
-            Span::styled("ctrl-t", Style::default().fg(Color::Green)),
-            Span::raw(" to show only the parent processes and direct children."),
-        ]);
-        help_text.extend(Text::from(spans));
-
+    /// The changes found by the last refreshes, for the Events tab
+    pub fn take_changes(&mut self) -> Vec<Change> {
+        std::mem::take(&mut self.changes)
+    }
+    fn lines(&self) -> Vec<Line<'static>> {
         let selected_style = Style::default().fg(Color::Magenta);
         let self_style = Style::default().fg(Color::Yellow);
         let unselected_style = Style::default();
@@ -127,6 +130,26 @@ impl AppWidget for TreeWidget {
             ));
             text.push(Line::from(line));
         }
+        text
+    }
+}
+
+impl AppWidget for TreeWidget {
+    const TITLE: &'static str = "Tree";
+    type Json = Vec<json::TreeEntry>;
+    fn draw(&mut self, f: &mut Frame, area: Rect, help_text: &mut Text) {
+        let spans = Line::from(vec![
+            Span::raw("The "),
+            Span::styled("Tree", Style::default().fg(Color::Yellow)),
+            Span::raw(" tab shows the currently selected process in a process tree. Press "),
+            Span::styled("ctrl-t", Style::default().fg(Color::Green)),
+            Span::raw(" to show only the parent processes and direct children."),
+        ]);
+        help_text.extend(Text::from(spans));
+
+        let text = self.lines();
+
+        let flattened = self.tree.flatten();
         let select_idx = flattened
             .iter()
             .enumerate()
@@ -147,8 +170,8 @@ impl AppWidget for TreeWidget {
             .scroll((scroll as u16, 0));
         f.render_widget(widget, area);
     }
-    fn update(&mut self, proc: &Process) {
-        if self.last_updated.elapsed() > TWO_SECONDS || self.force_update {
+    fn update(&mut self, proc: &Process, now: Instant) {
+        if now.saturating_duration_since(self.last_updated) > throttle(TWO_SECONDS) || self.force_update {
             // before we update, get a llist of our parents PIDs, all the way up to pid1.
             // After the refresh, our selected process might be gone, so we'll want to instead
             // select its next available parent
@@ -164,8 +187,11 @@ impl AppWidget for TreeWidget {
                 }
             }
             parents.push(1);
-            self.tree = util::ProcessTree::new(if self.show_all { None } else { Some((&parents, proc)) }).unwrap();
-            self.last_updated = Instant::now();
+            let tree = util::ProcessTree::new(&*self.source, if self.show_all { None } else { Some((&parents, proc)) })
+                .unwrap();
+            self.changes.extend(child_changes(&self.tree, &tree, self.this_pid));
+            self.tree = tree;
+            self.last_updated = now;
             self.force_update = false;
 
             if !self.tree.entries.contains_key(&self.selected_pid) {
@@ -198,23 +224,15 @@ impl AppWidget for TreeWidget {
                 self.force_update = true;
                 return InputResult::NeedsUpdate;
             }
-            KeyEvent { code: KeyCode::Up, .. } => {
-                if select_idx > 0 {
-                    select_idx -= 1;
-                    true
-                } else {
-                    false
-                }
+            KeyEvent { code: KeyCode::Up, .. } if select_idx > 0 => {
+                select_idx -= 1;
+                true
             }
             KeyEvent {
                 code: KeyCode::Down, ..
-            } => {
-                if select_idx < flattened.len() as i32 {
-                    select_idx += 1;
-                    true
-                } else {
-                    false
-                }
+            } if select_idx < flattened.len() as i32 => {
+                select_idx += 1;
+                true
             }
             _ => false,
         };
@@ -231,4 +249,74 @@ impl AppWidget for TreeWidget {
             InputResult::None
         }
     }
+    fn snapshot(&self, out: &mut String) -> std::fmt::Result {
+        write_lines(out, &self.lines())
+    }
+    fn json(&self) -> Section<Self::Json> {
+        Section::data(
+            self.tree
+                .flatten()
+                .into_iter()
+                .map(|(_, entry)| json::TreeEntry::from(entry))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use procdump::source::{DataSource, FixtureSource};
+    use ratatui::{style::Color, text::Text};
+
+    use super::{AppWidget, TreeWidget};
+    use crate::ui::widgets::{buffer_lines, fixture, render};
+
+    #[test]
+    fn test_draw_fixture() {
+        let source = fixture();
+        let proc = source.process(101).unwrap();
+        let mut widget = TreeWidget::new(&proc, source);
+
+        let buffer = render(30, 5, |f| widget.draw(f, f.size(), &mut Text::default()));
+        assert_eq!(
+            buffer_lines(&buffer),
+            [
+                "━┳╸1 /sbin/init               ",
+                " ┗┳╸100 sh -c sleep 1000 | cat",
+                "  ┣━╸101 sleep 1000           ",
+                "  ┗━╸102 cat                  ",
+                // 201's parent has exited, so it isn't under init
+                "                              ",
+            ]
+        );
+        // the process that we're looking at is selected
+        assert_eq!(buffer.get(6, 2).fg, Color::Magenta);
+        assert_eq!(buffer.get(6, 3).fg, Color::Reset);
+    }
+
+    #[test]
+    fn test_draw_without_init() {
+        // a capture where init couldn't be read
+        let dir = std::env::temp_dir().join(format!("procdump-tree-{}", std::process::id()));
+        procdump::capture::capture(fixture().root(), 101, &dir).unwrap();
+        std::fs::remove_dir_all(dir.join("1")).unwrap();
+        let source = std::sync::Arc::new(FixtureSource::new(&dir));
+        let proc = source.process(101).unwrap();
+        let mut widget = TreeWidget::new(&proc, source);
+
+        let buffer = render(30, 6, |f| widget.draw(f, f.size(), &mut Text::default()));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            buffer_lines(&buffer),
+            [
+                "━┳╸1 (not captured)           ",
+                " ┣┳╸100 sh -c sleep 1000 | cat",
+                " ┆┣━╸101 sleep 1000           ",
+                " ┆┗━╸102 cat                  ",
+                // the orphan of a process that has exited hangs off the made-up root too
+                " ┗━╸201 orphan                ",
+                "                              ",
+            ]
+        );
+    }
 }
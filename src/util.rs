@@ -1,19 +1,20 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt::Display;
-use std::sync::mpsc;
-use std::thread;
+use std::io::Read;
 
-use crossterm::event::{KeyEvent, MouseEvent};
 use procfs::{
-    process::{all_processes, LimitValue, Process},
+    process::{FDInfo, FDPermissions, FDTarget, LimitValue, Process},
     ProcResult,
 };
-use ratatui::text::{Line, Span};
 
-#[derive(Debug)]
+use crate::source::DataSource;
+
+#[derive(Debug, Clone)]
 pub struct ProcessTreeEntry {
     pub pid: i32,
+    /// When the process started, which tells it apart from a later process with the same PID
+    pub starttime: u64,
     pub ppid: i32,
     pub cmdline: String,
     pub num_siblings: u32,
@@ -53,8 +54,17 @@ impl ProcessTree {
 
         v
     }
-    pub(crate) fn new(focus: Option<(&[i32], &Process)>) -> Result<Self, anyhow::Error> {
-        let all = all_processes()?;
+    /// A process followed by all of its descendants, in tree order.  Empty if the process isn't in
+    /// the tree
+    pub fn subtree(&self, pid: i32) -> Vec<&ProcessTreeEntry> {
+        let mut v = Vec::new();
+        if self.entries.contains_key(&pid) {
+            Self::flatten_helper(&self.entries, &mut v, pid, 0);
+        }
+        v.into_iter().map(|(_, entry)| entry).collect()
+    }
+    pub fn new(source: &dyn DataSource, focus: Option<(&[i32], &Process)>) -> Result<Self, anyhow::Error> {
+        let all = source.all_processes()?;
 
         // map from pid to Process
         let mut procs: HashMap<i32, Process> = HashMap::new();
@@ -65,22 +75,47 @@ impl ProcessTree {
         // map from pid to ProcessTreeEntry, which we'll return
         let mut map: HashMap<i32, ProcessTreeEntry> = HashMap::new();
 
-        for proc in all.flatten() {
+        for proc in all {
             let Ok(proc_stat) = proc.stat() else { continue };
             child_map.entry(proc_stat.ppid).or_default().push(proc.pid);
             procs.insert(proc.pid, proc);
         }
+        // directory order isn't guaranteed to be numeric, so sort children to keep the tree stable
+        for children in child_map.values_mut() {
+            children.sort_unstable();
+        }
 
-        let root_proc = procs.get(&1).unwrap();
-        let mut root = ProcessTreeEntry {
-            pid: root_proc.pid,
-            ppid: 0,
-            cmdline: root_proc
-                .cmdline()
-                .ok()
-                .map_or(root_proc.stat()?.comm, |cmdline| cmdline.join(" ")),
-            children: Vec::new(),
-            num_siblings: 0,
+        let mut root = if let Some(root_proc) = procs.get(&1) {
+            let root_stat = root_proc.stat()?;
+            ProcessTreeEntry {
+                pid: root_proc.pid,
+                starttime: root_stat.starttime,
+                ppid: 0,
+                cmdline: root_proc
+                    .cmdline()
+                    .ok()
+                    .map_or(root_stat.comm, |cmdline| cmdline.join(" ")),
+                children: Vec::new(),
+                num_siblings: 0,
+            }
+        } else {
+            // a partial capture (see `--capture`) might not include init.  Make up an entry for it,
+            // and adopt every process whose parent is missing, like the kernel would
+            let mut orphans: Vec<i32> = child_map
+                .iter()
+                .filter(|(ppid, _)| !procs.contains_key(ppid))
+                .flat_map(|(_, children)| children.iter().copied())
+                .collect();
+            orphans.sort_unstable();
+            child_map.insert(1, orphans);
+            ProcessTreeEntry {
+                pid: 1,
+                starttime: 0,
+                ppid: 0,
+                cmdline: "(not captured)".to_owned(),
+                children: Vec::new(),
+                num_siblings: 0,
+            }
         };
         build_entry(&mut root, &mut map, &procs, &child_map);
         map.insert(1, root);
@@ -124,6 +159,7 @@ fn build_entry(
             };
             let mut child_entry = ProcessTreeEntry {
                 pid: *child_pid,
+                starttime: stat.starttime,
                 ppid: entry.pid,
                 cmdline: p.cmdline().ok().map_or(stat.comm.clone(), |cmdline| cmdline.join(" ")),
                 children: Vec::new(),
@@ -137,48 +173,58 @@ fn build_entry(
     }
 }
 
-pub(crate) fn limit_to_string(limit: &LimitValue) -> Cow<'static, str> {
-    match limit {
-        LimitValue::Unlimited => Cow::Borrowed("Unlimited"),
-        LimitValue::Value(v) => Cow::Owned(format!("{v}")),
-    }
+/// What a process is running.  This changes when the process calls `execve`, even though its PID
+/// and start time stay the same
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ExecImage {
+    /// The device and inode of the executable, which can only be read with permission to ptrace
+    /// the process
+    pub exe: Option<(u64, u64)>,
+    pub comm: Option<String>,
+    /// The first word of the command line, which is usually the program name
+    pub argv0: Option<String>,
 }
 
-pub(crate) fn get_numlines_from_spans<'t, I>(spans: I, width: usize) -> usize
-where
-    I: Iterator<Item = &'t Line<'t>>,
-{
-    let mut num_lines = 1;
-    for span in spans {
-        num_lines += 1 + (span.width() / width);
-    }
+impl ExecImage {
+    pub fn of(proc: &Process) -> ExecImage {
+        use std::os::unix::fs::MetadataExt;
 
-    num_lines
-}
+        ExecImage {
+            // opening the link gets the file that was executed, even if it has since been replaced
+            exe: proc
+                .open_relative("exe")
+                .and_then(|f| Ok(f.metadata()?))
+                .ok()
+                .map(|m| (m.dev(), m.ino())),
+            comm: proc.stat().ok().map(|stat| stat.comm),
+            // a zombie has an empty command line
+            argv0: proc.cmdline().ok().and_then(|cmdline| cmdline.into_iter().next()),
+        }
+    }
 
-/// Given some text, and a width, try to figure out how many lines it needs
-pub(crate) fn get_numlines<'t, I>(i: I, width: usize) -> usize
-where
-    I: Iterator<Item = &'t Span<'t>>,
-{
-    let mut cur_line_length = 0;
-    let mut num_lines = 1;
-    for item in i {
-        // we assume that if there is a newline, it will only be at the *end*
-        if item.content.ends_with('\n') {
-            cur_line_length += item.content.len() - 1;
-            num_lines += 1 + (cur_line_length / width);
-            cur_line_length = 0;
-        } else {
-            cur_line_length += item.content.len();
+    /// True if `new` looks like a different program than `self`.  When the executable can be read
+    /// on both sides it decides on its own, since a process can rename itself (with
+    /// `prctl(PR_SET_NAME)` or by rewriting its command line) without an exec.  Anything that
+    /// couldn't be read on either side is left out of the comparison
+    pub fn changed(&self, new: &ExecImage) -> bool {
+        fn differs<T: PartialEq>(old: &Option<T>, new: &Option<T>) -> bool {
+            matches!((old, new), (Some(old), Some(new)) if old != new)
+        }
+        if let (Some(old), Some(new)) = (self.exe, new.exe) {
+            return old != new;
         }
+        differs(&self.comm, &new.comm) || differs(&self.argv0, &new.argv0)
     }
-    num_lines += cur_line_length / width;
+}
 
-    num_lines
+pub fn limit_to_string(limit: &LimitValue) -> Cow<'static, str> {
+    match limit {
+        LimitValue::Unlimited => Cow::Borrowed("Unlimited"),
+        LimitValue::Value(v) => Cow::Owned(format!("{v}")),
+    }
 }
 
-pub(crate) fn fmt_time(dt: chrono::DateTime<chrono::offset::Local>) -> impl Display {
+pub fn fmt_time(dt: chrono::DateTime<chrono::offset::Local>) -> impl Display {
     use chrono::offset::Local;
     let now = Local::now();
 
@@ -199,7 +245,7 @@ pub(crate) fn fmt_time(dt: chrono::DateTime<chrono::offset::Local>) -> impl Disp
     }
 }
 
-pub(crate) fn fmt_bytes(b: u64, suffix: &'static str) -> String {
+pub fn fmt_bytes(b: u64, suffix: &'static str) -> String {
     if b > 1000 * 1000 * 1000 {
         format!("{:.2}\u{00A0}G{}", b as f64 / 1000.0 / 1000.0 / 1000.0, suffix)
     } else if b > 1000 * 1000 {
@@ -211,7 +257,7 @@ pub(crate) fn fmt_bytes(b: u64, suffix: &'static str) -> String {
     }
 }
 
-pub(crate) fn fmt_rate(b: f32, suffix: &'static str) -> String {
+pub fn fmt_rate(b: f32, suffix: &'static str) -> String {
     if b > 1000.0 * 1000.0 {
         format!("{:.1}\u{00A0}M{}", b / 1000.0 / 1000.0, suffix)
     } else if b > 1000.0 {
@@ -221,64 +267,83 @@ pub(crate) fn fmt_rate(b: f32, suffix: &'static str) -> String {
     }
 }
 
-#[derive(Debug)]
-pub(crate) enum Event {
-    Key(KeyEvent),
-    Mouse(MouseEvent),
-    Tick,
+/// Returns the name of a signal, like `SIGSEGV`
+pub fn signal_name(signum: i32) -> String {
+    let name = match signum {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGUSR1 => "SIGUSR1",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGUSR2 => "SIGUSR2",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGSTKFLT => "SIGSTKFLT",
+        libc::SIGCHLD => "SIGCHLD",
+        libc::SIGCONT => "SIGCONT",
+        libc::SIGSTOP => "SIGSTOP",
+        libc::SIGTSTP => "SIGTSTP",
+        libc::SIGTTIN => "SIGTTIN",
+        libc::SIGTTOU => "SIGTTOU",
+        libc::SIGURG => "SIGURG",
+        libc::SIGXCPU => "SIGXCPU",
+        libc::SIGXFSZ => "SIGXFSZ",
+        libc::SIGVTALRM => "SIGVTALRM",
+        libc::SIGPROF => "SIGPROF",
+        libc::SIGWINCH => "SIGWINCH",
+        libc::SIGIO => "SIGIO",
+        libc::SIGPWR => "SIGPWR",
+        libc::SIGSYS => "SIGSYS",
+        n if (libc::SIGRTMIN()..=libc::SIGRTMAX()).contains(&n) => {
+            return format!("SIGRTMIN+{}", n - libc::SIGRTMIN());
+        }
+        n => return format!("signal {n}"),
+    };
+    name.to_owned()
 }
 
-pub(crate) struct Events {
-    pub rx: mpsc::Receiver<Event>,
+/// Parses a signal given by number or by name, with or without the `SIG` prefix (like `9`, `KILL`,
+/// `sigterm`, or `SIGRTMIN+3`)
+pub fn parse_signal(s: &str) -> Option<i32> {
+    let s = s.trim();
+    if let Ok(n) = s.parse::<i32>() {
+        return (1..=libc::SIGRTMAX()).contains(&n).then_some(n);
+    }
+    let s = s.to_ascii_uppercase();
+    let name = if s.starts_with("SIG") { s } else { format!("SIG{s}") };
+    (1..=libc::SIGRTMAX()).find(|&n| signal_name(n) == name)
 }
 
-impl Events {
-    pub fn new() -> Events {
-        // spawn a thread to handle keyboard input
-        let (tx, rx) = mpsc::channel();
-        let kbd_tx = tx.clone();
-        thread::Builder::new()
-            .name("kbd-reader".to_owned())
-            .spawn(move || {
-                use crossterm::event::{read, Event};
-
-                loop {
-                    let evt = read();
-                    if let Err(..) = match evt {
-                        Err(..) => return,
-                        Ok(Event::Key(e)) => kbd_tx.send(self::Event::Key(e)),
-                        Ok(Event::Mouse(m)) => kbd_tx.send(self::Event::Mouse(m)),
-                        _ => continue
-                        // Ok(Event::Unsupported(bytes)) => match bytes.as_slice() {
-                        //     // manual parsing of cursor movement keys in application mode
-                        //     [0x1b, 79, 65] => kbd_tx.send(self::Event::Key(Key::Up)),
-                        //     [0x1b, 79, 66] => kbd_tx.send(self::Event::Key(Key::Down)),
-                        //     [0x1b, 79, 67] => kbd_tx.send(self::Event::Key(Key::Right)),
-                        //     [0x1b, 79, 68] => kbd_tx.send(self::Event::Key(Key::Left)),
-                        //     _ => continue,
-                        // },
-                    } {
-                        return;
-                    }
-                }
-            })
-            .unwrap();
-
-        thread::Builder::new()
-            .name("tick".to_owned())
-            .spawn(move || loop {
-                thread::sleep(std::time::Duration::from_millis(1500));
-                if let Err(..) = tx.send(self::Event::Tick) {
-                    return;
-                }
-            })
-            .unwrap();
+/// Describes how a process ended, like `exited with 1` or `killed by SIGSEGV (core dumped)`
+pub fn fmt_exit_status(status: std::process::ExitStatus) -> String {
+    use std::os::unix::process::ExitStatusExt;
+
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("exited with {code}"),
+        (None, Some(sig)) if status.core_dumped() => format!("killed by {} (core dumped)", signal_name(sig)),
+        (None, Some(sig)) => format!("killed by {}", signal_name(sig)),
+        (None, None) => format!("{status}"),
+    }
+}
 
-        Events { rx }
+/// Formats a duration as `m:ss`, or `h:mm:ss` if it's at least an hour
+pub fn fmt_duration(d: std::time::Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
     }
 }
 
-pub(crate) fn lookup_username(uid: u32) -> String {
+pub fn lookup_username(uid: u32) -> String {
     use libc::{getpwuid_r, passwd, sysconf, _SC_GETPW_R_SIZE_MAX};
     use std::ffi::CStr;
     use std::mem::zeroed;
@@ -304,7 +369,34 @@ pub(crate) fn lookup_username(uid: u32) -> String {
     "???".to_owned()
 }
 
-pub(crate) fn lookup_groupname(gid: u32) -> String {
+/// Finds the UID of a user by name.  Returns `None` if there's no such user
+pub fn lookup_uid(name: &str) -> Option<u32> {
+    use libc::{getpwnam_r, passwd, sysconf, _SC_GETPW_R_SIZE_MAX};
+    use std::ffi::CString;
+    use std::mem::zeroed;
+
+    let name = CString::new(name).ok()?;
+    let buf_size = match unsafe { sysconf(_SC_GETPW_R_SIZE_MAX) } {
+        x if x <= 0 => {
+            // make some something that we think will be big enough
+            1024
+        }
+        x => x as usize,
+    };
+
+    let mut buf = vec![0; buf_size];
+    let mut pwd: passwd = unsafe { zeroed() };
+
+    let mut ptr = std::ptr::null_mut::<passwd>();
+
+    if unsafe { getpwnam_r(name.as_ptr(), &mut pwd, buf.as_mut_ptr(), buf_size, &mut ptr) } == 0 && !ptr.is_null() {
+        return Some(pwd.pw_uid);
+    }
+
+    None
+}
+
+pub fn lookup_groupname(gid: u32) -> String {
     use libc::{getgrgid_r, group, sysconf, _SC_GETGR_R_SIZE_MAX};
     use std::ffi::CStr;
     use std::mem::zeroed;
@@ -330,8 +422,8 @@ pub(crate) fn lookup_groupname(gid: u32) -> String {
     "???".to_owned()
 }
 
-pub(crate) fn get_locks_for_pid(pid: i32) -> ProcResult<Vec<procfs::Lock>> {
-    procfs::locks().map(|locks| {
+pub fn get_locks_for_pid(source: &dyn DataSource, pid: i32) -> ProcResult<Vec<procfs::Lock>> {
+    source.locks().map(|locks| {
         locks
             .into_iter()
             .filter(|lock| lock.pid == Some(pid))
@@ -339,34 +431,71 @@ pub(crate) fn get_locks_for_pid(pid: i32) -> ProcResult<Vec<procfs::Lock>> {
     })
 }
 
-pub(crate) fn get_pipe_pairs() -> HashMap<u64, (ProcessTreeEntry, ProcessTreeEntry)> {
-    let mut read_map = HashMap::new();
-    let mut write_map = HashMap::new();
+/// The mode that an fd was opened with.  In a copy of `/proc` (like a capture, or the test
+/// fixtures), the permissions of the links in `fd/` can't be set, so it comes from the `flags` line
+/// of `fdinfo/<fd>` instead
+pub fn fd_mode(source: &dyn DataSource, proc: &Process, fd: &FDInfo) -> FDPermissions {
+    if source.is_live() {
+        return fd.mode();
+    }
+    let mut fdinfo = String::new();
+    let flags = proc
+        .open_relative(&format!("fdinfo/{}", fd.fd))
+        .ok()
+        .and_then(|mut f| f.read_to_string(&mut fdinfo).ok())
+        .and_then(|_| fdinfo.lines().find_map(|line| line.strip_prefix("flags:")))
+        .and_then(|flags| i32::from_str_radix(flags.trim(), 8).ok());
+    match flags.map(|flags| flags & libc::O_ACCMODE) {
+        Some(libc::O_RDONLY) => FDPermissions::READ,
+        Some(libc::O_WRONLY) => FDPermissions::WRITE,
+        Some(..) => FDPermissions::READ | FDPermissions::WRITE,
+        None => fd.mode(),
+    }
+}
 
-    if let Ok(procs) = procfs::process::all_processes() {
-        for proc in procs.filter_map(|p| p.ok()) {
-            if let Ok(fds) = proc.fd() {
-                let proc_stat = proc.stat().unwrap();
-                for fd in fds.filter_map(|fd| fd.ok()) {
-                    if let procfs::process::FDTarget::Pipe(uid) = fd.target {
-                        let pti = ProcessTreeEntry {
-                            pid: proc.pid,
-                            ppid: proc_stat.ppid,
-                            cmdline: proc_stat.comm.clone(),
-                            children: Vec::new(),
-                            num_siblings: 0,
-                        };
-                        if fd.mode().contains(procfs::process::FDPermissions::READ) {
-                            read_map.insert(uid, pti);
-                        } else if fd.mode().contains(procfs::process::FDPermissions::WRITE) {
-                            write_map.insert(uid, pti);
-                        }
-                    }
+pub fn get_pipe_pairs(source: &dyn DataSource) -> HashMap<u64, (ProcessTreeEntry, ProcessTreeEntry)> {
+    let mut ends = Vec::new();
+
+    for proc in source.all_processes().unwrap_or_default() {
+        if let (Ok(fds), Ok(proc_stat)) = (proc.fd(), proc.stat()) {
+            for fd in fds.filter_map(|fd| fd.ok()) {
+                if let FDTarget::Pipe(uid) = fd.target {
+                    let pti = ProcessTreeEntry {
+                        pid: proc.pid,
+                        starttime: proc_stat.starttime,
+                        ppid: proc_stat.ppid,
+                        cmdline: proc_stat.comm.clone(),
+                        children: Vec::new(),
+                        num_siblings: 0,
+                    };
+                    ends.push((uid, fd_mode(source, &proc, &fd), pti));
                 }
             }
         }
     }
 
+    pair_pipes(ends)
+}
+
+/// Given the pipe inode and mode of every pipe fd on the system (along with the process that owns
+/// it), returns a map from pipe inode to the (read side, write side) processes.
+///
+/// Pipes that are only open on one side are dropped.
+pub fn pair_pipes<I>(ends: I) -> HashMap<u64, (ProcessTreeEntry, ProcessTreeEntry)>
+where
+    I: IntoIterator<Item = (u64, FDPermissions, ProcessTreeEntry)>,
+{
+    let mut read_map = HashMap::new();
+    let mut write_map = HashMap::new();
+
+    for (uid, mode, pti) in ends {
+        if mode.contains(FDPermissions::READ) {
+            read_map.insert(uid, pti);
+        } else if mode.contains(FDPermissions::WRITE) {
+            write_map.insert(uid, pti);
+        }
+    }
+
     let mut map = HashMap::new();
     for (uid, read_pti) in read_map.drain() {
         if let Some(write_pti) = write_map.remove(&uid) {
@@ -377,7 +506,7 @@ pub(crate) fn get_pipe_pairs() -> HashMap<u64, (ProcessTreeEntry, ProcessTreeEnt
     map
 }
 
-pub(crate) fn get_tcp_table(p: &procfs::process::Process) -> HashMap<u64, procfs::net::TcpNetEntry> {
+pub fn get_tcp_table(p: &procfs::process::Process) -> HashMap<u64, procfs::net::TcpNetEntry> {
     let mut map = HashMap::new();
 
     if let Ok(tcp) = p.tcp() {
@@ -394,7 +523,7 @@ pub(crate) fn get_tcp_table(p: &procfs::process::Process) -> HashMap<u64, procfs
     map
 }
 
-pub(crate) fn get_udp_table(p: &procfs::process::Process) -> HashMap<u64, procfs::net::UdpNetEntry> {
+pub fn get_udp_table(p: &procfs::process::Process) -> HashMap<u64, procfs::net::UdpNetEntry> {
     let mut map = HashMap::new();
 
     if let Ok(udp) = p.udp() {
@@ -411,7 +540,7 @@ pub(crate) fn get_udp_table(p: &procfs::process::Process) -> HashMap<u64, procfs
     map
 }
 
-pub(crate) fn get_unix_table(p: &procfs::process::Process) -> HashMap<u64, procfs::net::UnixNetEntry> {
+pub fn get_unix_table(p: &procfs::process::Process) -> HashMap<u64, procfs::net::UnixNetEntry> {
     let mut map = HashMap::new();
 
     if let Ok(unix) = p.unix() {
@@ -425,22 +554,112 @@ pub(crate) fn get_unix_table(p: &procfs::process::Process) -> HashMap<u64, procf
 
 #[cfg(test)]
 mod tests {
-    use ratatui::text::Span;
-
-    #[test]
-    fn test_boxsize() {
-        let text = vec![Span::raw("hi\n"), Span::raw("hey")];
+    use procfs::process::FDPermissions;
 
-        let l = super::get_numlines(text.iter(), 5);
-        assert_eq!(l, 2);
-    }
+    use super::{pair_pipes, ExecImage, ProcessTree, ProcessTreeEntry};
+    use crate::source::{fixture, DataSource, LiveSource};
 
     #[test]
     fn test_proc_all_tree() {
-        let tree = super::ProcessTree::new(None).unwrap();
+        let tree = super::ProcessTree::new(&LiveSource, None).unwrap();
         println!("{tree:#?}");
         //let me = procfs::process::Process::myself().unwrap();
         //let all = super::proc_all_tree(Some(&me)).unwrap();
         //println!("{:#?}", all);
     }
+
+    #[test]
+    fn test_tree_dead_pids() {
+        let source = fixture();
+        let tree = ProcessTree::new(&source, None).unwrap();
+        let flat: Vec<(u8, i32)> = tree.flatten().into_iter().map(|(d, e)| (d, e.pid)).collect();
+        // 200 has exited (no stat), so neither it nor its orphaned child 201 are reachable from init
+        assert_eq!(flat, [(1, 1), (2, 100), (3, 101), (3, 102)]);
+        assert_eq!(tree.entries[&100].cmdline, "sh -c sleep 1000 | cat");
+        assert_eq!(tree.entries[&101].num_siblings, 2);
+        let subtree: Vec<i32> = tree.subtree(100).iter().map(|e| e.pid).collect();
+        assert_eq!(subtree, [100, 101, 102]);
+        assert!(tree.subtree(200).is_empty());
+
+        // focusing on 101 keeps only its parents, but still flattens cleanly
+        let proc = source.process(101).unwrap();
+        let tree = ProcessTree::new(&source, Some((&[101, 100, 1], &proc))).unwrap();
+        let flat: Vec<i32> = tree.flatten().into_iter().map(|(_, e)| e.pid).collect();
+        assert_eq!(flat, [1, 100, 101]);
+
+        // the focused process can be dead too
+        assert!(source.process(200).unwrap().stat().is_err());
+        let proc = source.process(200).unwrap();
+        let tree = ProcessTree::new(&source, Some((&[200, 1], &proc))).unwrap();
+        let flat: Vec<i32> = tree.flatten().into_iter().map(|(_, e)| e.pid).collect();
+        assert_eq!(flat, [1]);
+    }
+
+    #[test]
+    fn test_exec_image() {
+        let source = fixture();
+        let sleep = ExecImage::of(&source.process(101).unwrap());
+        assert_eq!(sleep.comm.as_deref(), Some("sleep"));
+        assert_eq!(sleep.argv0.as_deref(), Some("sleep"));
+        assert!(!sleep.changed(&sleep));
+        assert!(sleep.changed(&ExecImage::of(&source.process(102).unwrap())));
+        // nothing can be read from a process that's gone, which isn't a sign of an exec
+        assert!(!sleep.changed(&ExecImage::default()));
+        let other_exe = ExecImage {
+            exe: Some((1, 2)),
+            ..sleep.clone()
+        };
+        assert!(!sleep.changed(&other_exe));
+        assert!(other_exe.changed(&ExecImage {
+            exe: Some((1, 3)),
+            ..sleep.clone()
+        }));
+        // renaming itself doesn't make it another program, when the executable says it's the same
+        let renamed = ExecImage {
+            comm: Some("worker".to_owned()),
+            argv0: Some("worker: idle".to_owned()),
+            ..other_exe.clone()
+        };
+        assert!(!other_exe.changed(&renamed));
+        // but without the executable, the name is all there is to go on
+        assert!(sleep.changed(&ExecImage {
+            comm: Some("worker".to_owned()),
+            ..sleep.clone()
+        }));
+    }
+
+    #[test]
+    fn test_pair_pipes() {
+        let entry = |pid| ProcessTreeEntry {
+            pid,
+            starttime: 0,
+            ppid: 100,
+            cmdline: format!("proc{pid}"),
+            num_siblings: 0,
+            children: Vec::new(),
+        };
+        let pairs = pair_pipes([
+            (5000, FDPermissions::WRITE, entry(101)),
+            (5000, FDPermissions::READ, entry(102)),
+            // only the read side is open
+            (5001, FDPermissions::READ, entry(102)),
+            // a fd with neither permission doesn't count as either side
+            (5002, FDPermissions::READ, entry(101)),
+            (5002, FDPermissions::empty(), entry(102)),
+        ]);
+        assert_eq!(pairs.len(), 1);
+        let (rd_side, wr_side) = &pairs[&5000];
+        assert_eq!(rd_side.pid, 102);
+        assert_eq!(wr_side.pid, 101);
+    }
+
+    #[test]
+    fn test_parse_signal() {
+        assert_eq!(super::parse_signal("9"), Some(libc::SIGKILL));
+        assert_eq!(super::parse_signal("term"), Some(libc::SIGTERM));
+        assert_eq!(super::parse_signal(" SIGUSR1 "), Some(libc::SIGUSR1));
+        assert_eq!(super::parse_signal("SIGRTMIN+2"), Some(libc::SIGRTMIN() + 2));
+        assert_eq!(super::parse_signal("0"), None);
+        assert_eq!(super::parse_signal("SIGNOPE"), None);
+    }
 }
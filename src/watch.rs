@@ -0,0 +1,168 @@
+//! Watches a process for the triggers on the command line (like `--cpu-above`), and writes core
+//! dumps and reports when they fire.  This runs on its own with no UI, or alongside the UI with
+//! `--ui`.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use anyhow::Context;
+use procdump::{
+    collect::StatDelta,
+    coredump,
+    source::{DataSource, ProcessId},
+    trigger::{Monitor, Sample},
+    util::fmt_bytes,
+};
+use procfs::process::{Process, Stat};
+
+use crate::{cli, App};
+
+pub(crate) struct Watcher {
+    monitor: Monitor,
+    /// The process being watched.  The UI can switch to other processes, but the triggers stay
+    /// with this one
+    id: ProcessId,
+    stat_d: StatDelta<Stat>,
+    dump_dir: Option<PathBuf>,
+    report_dir: Option<PathBuf>,
+    exited: bool,
+}
+
+impl Watcher {
+    /// Returns `None` if no triggers were given
+    pub fn new(opts: &cli::Options, source: &dyn DataSource, proc: &Process) -> anyhow::Result<Option<Watcher>> {
+        if opts.triggers.is_empty() {
+            return Ok(None);
+        }
+        // core dumps are the default, unless reports were asked for instead
+        let dump_dir = match (&opts.dump_dir, &opts.report_dir) {
+            (Some(dir), _) => Some(dir.clone()),
+            (None, None) => Some(".".into()),
+            (None, Some(_)) => None,
+        };
+        for dir in dump_dir.iter().chain(&opts.report_dir) {
+            std::fs::create_dir_all(dir).with_context(|| format!("Unable to create {}", dir.display()))?;
+        }
+        let tps = source.system_info().ticks_per_second();
+        Ok(Some(Watcher {
+            monitor: Monitor::new(
+                opts.triggers.clone(),
+                opts.dumps.unwrap_or(1),
+                opts.cooldown.unwrap_or(std::time::Duration::from_secs(60)),
+            ),
+            id: ProcessId::of(proc).with_context(|| format!("Unable to read process {}", proc.pid))?,
+            stat_d: StatDelta::<Stat>::new(proc, tps, source.now())
+                .with_context(|| format!("Unable to read process {}", proc.pid))?,
+            dump_dir,
+            report_dir: opts.report_dir.clone(),
+            exited: false,
+        }))
+    }
+
+    /// Says what will be written, and where
+    pub fn describe(&self) -> String {
+        let mut what = Vec::new();
+        if let Some(dir) = &self.dump_dir {
+            what.push(format!("core dumps into {}", dir.display()));
+        }
+        if let Some(dir) = &self.report_dir {
+            what.push(format!("reports into {}", dir.display()));
+        }
+        format!("writing {}", what.join(" and "))
+    }
+
+    pub fn wants_reports(&self) -> bool {
+        self.report_dir.is_some()
+    }
+
+    /// True once everything that was asked for has been written
+    pub fn done(&self) -> bool {
+        self.monitor.done()
+    }
+
+    /// Samples the process, and writes whatever the triggers call for.  `app` has the data that
+    /// goes into a report.  Returns a line of text for every trigger that fired and everything that
+    /// was written, or couldn't be written
+    pub fn check(&mut self, proc: &Process, now: Instant, app: Option<&App>) -> Vec<String> {
+        // once the process is gone its start time can't be read, but it still has to be noticed
+        let same = ProcessId::of(proc).map_or(proc.pid == self.id.pid, |id| id == self.id);
+        if !same || self.exited {
+            return Vec::new();
+        }
+        if !proc.is_alive() {
+            self.exited = true;
+            return match self.monitor.exited() {
+                Some(reason) => self.fire(&reason, proc, app),
+                None => Vec::new(),
+            };
+        }
+        self.stat_d.update(proc, now);
+        let sample = Sample::read(proc, &self.stat_d);
+        match self.monitor.check(&sample, now) {
+            Some(reason) => self.fire(&reason, proc, app),
+            None => Vec::new(),
+        }
+    }
+
+    /// Writes a report and a core dump.  Failing to write one (like when ptrace isn't allowed, or
+    /// the disk is full) doesn't stop the watching, and doesn't count towards `--dumps`
+    fn fire(&mut self, reason: &str, proc: &Process, app: Option<&App>) -> Vec<String> {
+        let mut lines = vec![format!("{} {reason}", chrono::Local::now().format("%T"))];
+        let mut failed = false;
+        // the report goes first, since it's quick and doesn't disturb the process
+        if let (Some(dir), Some(app)) = (&self.report_dir, app) {
+            match app.report(reason).write(&*app.source, dir) {
+                Ok(path) => lines.push(format!("wrote a report into {}", path.display())),
+                Err(e) => {
+                    lines.push(format!("error: unable to write a report: {e:#}"));
+                    failed = true;
+                }
+            }
+        }
+        if let Some(dir) = &self.dump_dir {
+            if proc.is_alive() {
+                match self.dump(dir, proc) {
+                    Ok(dump_lines) => lines.extend(dump_lines),
+                    Err(e) => {
+                        lines.push(format!("error: {e:#}"));
+                        failed = true;
+                    }
+                }
+            }
+        }
+        if failed {
+            self.monitor.dump_failed();
+        }
+        lines
+    }
+
+    /// Writes a core dump into `dir`, and describes it
+    fn dump(&self, dir: &Path, proc: &Process) -> anyhow::Result<Vec<String>> {
+        let mut lines = Vec::new();
+        let path = coredump::core_path(dir, proc);
+        let dump = coredump::write_core(self.id, &path)
+            .with_context(|| format!("Unable to write a core dump of process {}", proc.pid))?;
+        lines.push(format!(
+            "wrote {} ({}, {} threads, {} mappings)",
+            path.display(),
+            fmt_bytes(dump.bytes, "B"),
+            dump.threads,
+            dump.regions
+        ));
+        if !dump.missing_threads.is_empty() {
+            lines.push(format!(
+                "warning: threads {:?} didn't stop, and aren't in the core dump",
+                dump.missing_threads
+            ));
+        }
+        if dump.unreadable > 0 {
+            lines.push(format!(
+                "warning: {} of memory couldn't be read, and is zeroed in the core dump",
+                fmt_bytes(dump.unreadable, "B")
+            ));
+        }
+        Ok(lines)
+    }
+}